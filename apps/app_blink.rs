@@ -7,30 +7,15 @@ extern crate zinc;
 #[phase(plugin)] extern crate macro_platformtree;
 
 platformtree!(
-  lpc17xx@mcu {
-    clock {
-      source = "main-oscillator";
-      source_frequency = 12_000_000;
-      pll {
-        m = 50;
-        n = 3;
-        divisor = 4;
-      }
-    }
+  mbed_lpc1768@board;
 
+  mcu {
     timer {
       timer@1 {
         counter = 25;
         divisor = 4;
       }
     }
-
-    gpio {
-      1 {
-        led1@18 { direction = "out"; }
-        led2@20 { direction = "out"; }
-      }
-    }
   }
 
   os {
@@ -4,12 +4,40 @@
 
 extern crate core;
 extern crate zinc;
+#[phase(plugin)] extern crate macro_platformtree;
 
-use core::option::Some;
-use zinc::hal::k20::{pin, watchdog};
-use zinc::hal::pin::GPIO;
 use zinc::hal::cortex_m4::systick;
 
+platformtree!(
+  k20@mcu {
+    clock {
+      source_frequency = 48_000_000;
+      flash_divisor = 2;
+    }
+
+    watchdog {
+      state = "disabled";
+    }
+
+    gpio {
+      /* Pins for MC HCK (http://www.mchck.org/) */
+      B {
+        led1@16 { direction = "out"; }
+      }
+    }
+  }
+
+  os {
+    single_task {
+      start = "start";
+      loop = "run";
+      args {
+        led1 = &led1;
+      }
+    }
+  }
+)
+
 /// Wait the given number of SysTick ticks
 pub fn wait(ticks: u32) {
   let mut n = ticks;
@@ -25,23 +53,16 @@ pub fn wait(ticks: u32) {
   }
 }
 
-#[no_mangle]
-#[allow(unused_variable)]
-#[allow(dead_code)]
-pub unsafe fn main() {
-  zinc::hal::mem_init::init_stack();
-  zinc::hal::mem_init::init_data();
-  watchdog::init(watchdog::Disabled);
-
-  // Pins for MC HCK (http://www.mchck.org/)
-  let led1 = pin::Pin::new(pin::PortB, 16, pin::GPIO, Some(zinc::hal::pin::Out));
-
+fn start(_: &pt::run_args) {
   systick::setup(systick::ten_ms().unwrap_or(480000));
   systick::enable();
-  loop {
-    led1.set_high();
-    wait(10);
-    led1.set_low();
-    wait(10);
-  }
+}
+
+fn run(args: &pt::run_args) {
+  use zinc::hal::pin::GPIO;
+
+  args.led1.set_high();
+  wait(10);
+  args.led1.set_low();
+  wait(10);
 }
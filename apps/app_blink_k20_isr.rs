@@ -1,22 +1,49 @@
 #![feature(phase)]
-#![feature(asm)]
 #![crate_type="staticlib"]
 #![no_std]
 
 extern crate core;
 extern crate zinc;
+#[phase(plugin)] extern crate macro_platformtree;
 
 use core::intrinsics::volatile_load;
 
-use core::option::Some;
-use zinc::hal::k20::{pin, watchdog};
-use zinc::hal::pin::GPIO;
 use zinc::hal::cortex_m4::systick;
 use zinc::util::support::wfi;
 
 static mut i: u32 = 0;
 static mut global_on: u32 = 0;
 
+platformtree!(
+  k20@mcu {
+    clock {
+      source_frequency = 48_000_000;
+      flash_divisor = 2;
+    }
+
+    watchdog {
+      state = "disabled";
+    }
+
+    gpio {
+      /* Pins for MC HCK (http://www.mchck.org/) */
+      B {
+        led1@16 { direction = "out"; }
+      }
+    }
+  }
+
+  os {
+    single_task {
+      start = "start";
+      loop = "run";
+      args {
+        led1 = &led1;
+      }
+    }
+  }
+)
+
 #[allow(dead_code)]
 #[no_mangle]
 pub unsafe extern fn isr_systick() {
@@ -27,26 +54,19 @@ pub unsafe extern fn isr_systick() {
     }
 }
 
-#[no_mangle]
-#[allow(dead_code)]
-pub fn main() {
-  zinc::hal::mem_init::init_stack();
-  zinc::hal::mem_init::init_data();
-  watchdog::init(watchdog::Disabled);
-
-  // Pins for MC HCK (http://www.mchck.org/)
-  let led1 = pin::Pin::new(pin::PortB, 16, pin::GPIO, Some(zinc::hal::pin::Out));
-
+fn start(_: &pt::run_args) {
   systick::setup(systick::ten_ms().unwrap_or(480000));
   systick::enable();
   systick::enable_irq();
+}
 
-  loop {
-    let on: bool = unsafe { volatile_load(&global_on as *const u32) == 0 };
-      match on {
-        true  => led1.set_high(),
-        false => led1.set_low(),
-      }
-      wfi();
+fn run(args: &pt::run_args) {
+  use zinc::hal::pin::GPIO;
+
+  let on: bool = unsafe { volatile_load(&global_on as *const u32) == 0 };
+  match on {
+    true  => args.led1.set_high(),
+    false => args.led1.set_low(),
   }
+  wfi();
 }
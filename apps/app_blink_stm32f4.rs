@@ -4,37 +4,58 @@
 
 extern crate core;
 extern crate zinc;
+#[phase(plugin)] extern crate macro_platformtree;
 
-#[no_mangle]
-#[allow(unused_variable)]
-#[allow(dead_code)]
-pub unsafe fn main() {
-  use zinc::hal::timer::Timer;
-  use zinc::hal::stm32f4::{pin, timer};
-  zinc::hal::mem_init::init_stack();
-  zinc::hal::mem_init::init_data();
-
-  let led1 = pin::PinConf{
-    port: pin::PortG,
-    pin: 13u8,
-    function: pin::GPIOOut
-  };
-  let led2 = pin::PinConf{
-    port: pin::PortG,
-    pin: 14u8,
-    function: pin::GPIOOut
-  };
-  led1.setup();
-  led2.setup();
-
-  let timer = timer::Timer::new(timer::Timer2, 25u32);
-
-  loop {
-    led1.set_high();
-    led2.set_low();
-    timer.wait_ms(300);
-    led1.set_low();
-    led2.set_high();
-    timer.wait_ms(300);
+platformtree!(
+  stm32f4@mcu {
+    clock {
+      source = "hse";
+      source_frequency = 8_000_000;
+      apb_low_divisor = 4;
+      apb_high_divisor = 2;
+      pll {
+        m = 8;
+        n = 336;
+        p = 2;
+        q = 7;
+      }
+    }
+
+    timer {
+      /* TIM2 runs at twice the 42MHz APB1 clock, prescale it to 1MHz */
+      timer@2 {
+        counter = 84;
+      }
+    }
+
+    gpio {
+      G {
+        led1@13 { mode = "out"; }
+        led2@14 { mode = "out"; }
+      }
+    }
+  }
+
+  os {
+    single_task {
+      loop = "run";
+      args {
+        timer = &timer;
+        led1 = &led1;
+        led2 = &led2;
+      }
+    }
   }
+)
+
+fn run(args: &pt::run_args) {
+  use zinc::hal::timer::Timer;
+
+  args.led1.set_high();
+  args.led2.set_low();
+  args.timer.wait_ms(300);
+
+  args.led1.set_low();
+  args.led2.set_high();
+  args.timer.wait_ms(300);
 }
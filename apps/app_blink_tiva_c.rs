@@ -7,20 +7,9 @@ extern crate zinc;
 #[phase(plugin)] extern crate macro_platformtree;
 
 platformtree!(
-  tiva_c@mcu {
-    clock {
-      source = "MOSC";
-      /* Y2 16Mhz oscillator on launchpad board */
-      source_frequency = 16_000_000;
-    }
-
-    gpio {
-      PortF {
-        led1@1 { direction = "out"; }
-        led2@2 { direction = "out"; }
-      }
-    }
+  tiva_c_launchpad@board;
 
+  mcu {
     timer {
       /* The mcu contain both 16/32bit and "wide" 32/64bit timers. */
       timer@w0 {
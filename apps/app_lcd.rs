@@ -0,0 +1,82 @@
+#![feature(phase)]
+#![crate_type="staticlib"]
+#![no_std]
+
+extern crate core;
+extern crate zinc;
+#[phase(plugin)] extern crate macro_platformtree;
+
+platformtree!(
+  lpc17xx@mcu {
+    clock {
+      source = "main-oscillator";
+      source_frequency = 12_000_000;
+      target_frequency = 100_000_000;
+    }
+
+    timer {
+      timer@1 {
+        counter = 25;
+        divisor = 4;
+      }
+    }
+
+    spi {
+      ssp@1 {
+        frequency = 6MHz;
+        bits = 8;
+        mode = 3;
+        mosi = &lcd_mosi;
+        sclk = &lcd_sclk;
+      }
+    }
+
+    gpio {
+      0 {
+        lcd_dc@6    { direction = "out"; }
+        lcd_sclk@7;
+        lcd_reset@8 { direction = "out"; }
+        lcd_mosi@9;
+        lcd_cs@18   { direction = "out"; }
+      }
+    }
+  }
+
+  os {
+    single_task {
+      loop = "run";
+      args {
+        timer = &timer;
+        spi = &ssp;
+        dc = &lcd_dc;
+        cs = &lcd_cs;
+        reset = &lcd_reset;
+      }
+    }
+  }
+)
+
+fn run(args: &pt::run_args) {
+  use zinc::drivers::chario::CharIO;
+  use zinc::drivers::lcd;
+  use zinc::hal::timer::Timer;
+
+  let screen = &lcd::c12332::C12332::new(args.spi, args.timer, args.dc,
+      args.cs, args.reset) as &lcd::LCD;
+
+  screen.clear();
+  screen.puts("hello lcd");
+  screen.flush();
+
+  let mut i = 0;
+  loop {
+    screen.clear();
+    screen.puts("waiting ");
+    screen.puti(i);
+    screen.puts(" seconds");
+    screen.flush();
+
+    i += 1;
+    args.timer.wait(1);
+  }
+}
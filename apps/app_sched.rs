@@ -0,0 +1,85 @@
+#![feature(phase)]
+#![crate_type="staticlib"]
+#![no_std]
+
+extern crate core;
+extern crate zinc;
+#[phase(plugin)] extern crate macro_platformtree;
+
+// Needs zinc built with the `multitasking` feature and the scheduler
+// from `hal/cortex_m3/sched.S` linked in.
+
+platformtree!(
+  mbed_lpc1768@board;
+
+  mcu {
+    timer {
+      timer@1 {
+        counter = 25;
+        divisor = 4;
+      }
+    }
+
+    uart {
+      uart@0 {
+        baud_rate = 115200baud;
+        mode = "8N1";
+        tx = &uart_tx;
+        rx = &uart_rx;
+      }
+    }
+  }
+
+  os {
+    tasks {
+      main@1 {
+        loop = "main_task";
+        stack_size = 512;
+        args {
+          timer = &timer;
+          uart = &uart;
+        }
+      }
+      first@2 {
+        loop = "first_task";
+        stack_size = 512;
+        args {
+          id = 1;
+          timer = &timer;
+          uart = &uart;
+        }
+      }
+      second@3 {
+        loop = "second_task";
+        stack_size = 512;
+        args {
+          id = 2;
+          timer = &timer;
+          uart = &uart;
+        }
+      }
+    }
+  }
+)
+
+fn main_task(args: &pt::main_task_args) {
+  use zinc::drivers::chario::CharIO;
+  use zinc::hal::timer::Timer;
+
+  args.timer.wait(2);
+  args.uart.puts("running in main task\n");
+}
+
+fn first_task(args: &pt::first_task_args) {
+  report(args.id, args.timer, args.uart);
+}
+
+fn second_task(args: &pt::second_task_args) {
+  report(args.id, args.timer, args.uart);
+}
+
+fn report<T: zinc::hal::timer::Timer, C: zinc::drivers::chario::CharIO>(
+    id: u32, timer: &T, uart: &C) {
+  timer.wait(2);
+  uart.puts("running in task "); uart.puti(id); uart.puts("\n");
+}
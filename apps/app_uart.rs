@@ -7,17 +7,9 @@ extern crate zinc;
 #[phase(plugin)] extern crate macro_platformtree;
 
 platformtree!(
-  lpc17xx@mcu {
-    clock {
-      source = "main-oscillator";
-      source_frequency = 12_000_000;
-      pll {
-        m = 50;
-        n = 3;
-        divisor = 4;
-      }
-    }
+  mbed_lpc1768@board;
 
+  mcu {
     timer {
       timer@1 {
         counter = 25;
@@ -27,22 +19,12 @@ platformtree!(
 
     uart {
       uart@0 {
-        baud_rate = 115200;
+        baud_rate = 115200baud;
         mode = "8N1";
         tx = &uart_tx;
         rx = &uart_rx;
       }
     }
-
-    gpio {
-      0 {
-        uart_tx@2;
-        uart_rx@3;
-      }
-      1 {
-        led4@23 { direction = "out"; }
-      }
-    }
   }
 
   os {
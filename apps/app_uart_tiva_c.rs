@@ -7,13 +7,9 @@ extern crate zinc;
 #[phase(plugin)] extern crate macro_platformtree;
 
 platformtree!(
-  tiva_c@mcu {
-    clock {
-      source = "MOSC";
-      /* Y2 16Mhz oscillator on launchpad board */
-      source_frequency = 16_000_000;
-    }
+  tiva_c_launchpad@board;
 
+  mcu {
     timer {
       /* The mcu contain both 16/32bit and "wide" 32/64bit timers. */
       timer@w0 {
@@ -26,18 +22,8 @@ platformtree!(
 
 
     gpio {
-      PortA {
-        uart_rx@0 {
-          direction = "in";
-          function  = 1;
-        }
-        uart_tx@1 {
-          direction = "in";
-          function  = 1;
-        }
-      }
       PortF {
-        txled@2 { direction = "out"; }
+        txled@2;
       }
     }
 
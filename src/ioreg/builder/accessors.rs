@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::rc::Rc;
 use syntax::ast;
 use syntax::ptr::P;
 use syntax::codemap::DUMMY_SP;
@@ -34,11 +35,26 @@ pub struct BuildAccessors<'a> {
 impl<'a> node::RegVisitor for BuildAccessors<'a> {
   fn visit_prim_reg(&mut self, path: &Vec<String>, reg: &node::Reg,
                     _width: node::RegWidth, fields: &Vec<node::Field>) {
-    if fields.iter().any(|f| f.access != node::WriteOnly) {
+    let item = build_addr_fn(self.cx, path, reg);
+    self.builder.push_item(item);
+    let item = build_raw_fns(self.cx, path, reg);
+    self.builder.push_item(item);
+
+    if fields.iter().any(|f| f.access.is_readable()) {
       let item = build_get_fn(self.cx, path, reg);
       self.builder.push_item(item);
     }
 
+    if fields.iter().any(|f| f.access.is_writable()) {
+      let item = build_write_fn(self.cx, path, reg);
+      self.builder.push_item(item);
+
+      if reg.reset.is_some() {
+        let item = build_reset_fn(self.cx, path, reg);
+        self.builder.push_item(item);
+      }
+    }
+
     for field in fields.iter() {
       match build_field_accessors(self.cx, path, reg, field) {
         Some(item) => self.builder.push_item(item),
@@ -46,6 +62,12 @@ impl<'a> node::RegVisitor for BuildAccessors<'a> {
       }
     }
   }
+
+  fn visit_union_reg(&mut self, path: &Vec<String>, reg: &node::Reg,
+                     _subregs: Rc<Vec<node::Reg>>) {
+    let item = build_addr_fn(self.cx, path, reg);
+    self.builder.push_item(item);
+  }
 }
 
 impl<'a> BuildAccessors<'a> {
@@ -62,20 +84,34 @@ fn build_field_accessors(cx: &ExtCtxt, path: &Vec<String>,
   let reg_ty: P<ast::Ty> =
     cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
 
-  let items = match field.access {
+  let mut items = match field.access {
     node::ReadWrite => vec!(build_field_set_fn(cx, path, reg, field),
                             build_field_get_fn(cx, path, reg, field)),
     node::ReadOnly  => vec!(build_field_get_fn(cx, path, reg, field)),
     node::WriteOnly => vec!(build_field_set_fn(cx, path, reg, field)),
-    node::SetToClear => vec!(build_field_clear_fn(cx, path, reg, field),
-                             build_field_get_fn(cx, path, reg, field)),
+    node::SetToClear | node::WriteZeroToClear =>
+      vec!(build_field_flag_fn(cx, path, reg, field, "clear_"),
+           build_field_get_fn(cx, path, reg, field)),
+    node::ReadToClear => vec!(build_field_get_fn(cx, path, reg, field)),
+    node::WriteOneToSet =>
+      vec!(build_field_flag_fn(cx, path, reg, field, "set_"),
+           build_field_get_fn(cx, path, reg, field)),
   };
+  match field.access {
+    node::ReadWrite | node::WriteOnly
+        if utils::out_of_range(cx, reg, field).is_some() =>
+      items.push(build_field_try_set_fn(cx, path, reg, field)),
+    _ => {},
+  }
 
   let access_tag = match field.access {
     node::ReadWrite => "read/write",
     node::ReadOnly  => "read-only",
     node::WriteOnly => "write-only",
     node::SetToClear => "set-to-clear",
+    node::WriteZeroToClear => "write-zero-to-clear",
+    node::ReadToClear => "read-to-clear",
+    node::WriteOneToSet => "write-one-to-set",
   };
 
   let field_doc = match field.docstring {
@@ -122,6 +158,126 @@ fn build_get_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
   item.unwrap()
 }
 
+fn build_write_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                  -> P<ast::Item>
+{
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+  let setter_ty = utils::setter_name(cx, path);
+
+  let docstring = format!("Overwrite the `{}` register. The update starts \
+                           from the register's reset value (or zero) and is \
+                           written once when dropped, without reading the \
+                           register first",
+                          reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  let item = quote_item!(cx,
+    impl $reg_ty {
+      $doc_attr
+      #[allow(dead_code)]
+      pub fn write<'a>(&'a self) -> $setter_ty<'a> {
+        $setter_ty::new_write(self)
+      }
+    }
+    );
+  item.unwrap()
+}
+
+/// Build the `addr` method giving the absolute address of a register
+/// or group, e.g. for use as a DMA target
+fn build_addr_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                 -> P<ast::Item>
+{
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+
+  let docstring = format!("The address of `{}`", reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  let item = quote_item!(cx,
+    impl $reg_ty {
+      $doc_attr
+      #[allow(dead_code)]
+      #[inline(always)]
+      pub fn addr(&self) -> uint {
+        self as *const $reg_ty as uint
+      }
+    }
+    );
+  item.unwrap()
+}
+
+/// Build the `raw_get` and `raw_set` methods accessing the whole value
+/// of a register, bypassing its fields and their access restrictions
+fn build_raw_fns(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                 -> P<ast::Item>
+{
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+  let packed_ty =
+    utils::reg_primitive_type(cx, reg)
+    .expect("Unexpected non-primitive reg");
+
+  let get_doc = format!("Read the raw value of the `{}` register, \
+                         ignoring its fields",
+                        reg.name.node);
+  let get_doc_attr =
+    utils::doc_attribute(cx, utils::intern_string(cx, get_doc));
+  let set_doc = format!("Write a raw value to the `{}` register, \
+                         ignoring its fields and their access restrictions",
+                        reg.name.node);
+  let set_doc_attr =
+    utils::doc_attribute(cx, utils::intern_string(cx, set_doc));
+
+  let store = utils::store(cx, reg, quote_expr!(cx, self),
+                           quote_expr!(cx, value));
+
+  let item = quote_item!(cx,
+    impl $reg_ty {
+      $get_doc_attr
+      #[allow(dead_code)]
+      #[inline(always)]
+      pub fn raw_get(&self) -> $packed_ty {
+        self.value.get()
+      }
+
+      $set_doc_attr
+      #[allow(dead_code)]
+      #[inline(always)]
+      pub fn raw_set(&self, value: $packed_ty) {
+        $store
+      }
+    }
+    );
+  item.unwrap()
+}
+
+fn build_reset_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                  -> P<ast::Item>
+{
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+  let reset = utils::reset_name(cx, path);
+  let store = utils::store(cx, reg, quote_expr!(cx, self),
+                           quote_expr!(cx, $reset));
+
+  let docstring = format!("Restore the `{}` register to its reset value",
+                          reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  let item = quote_item!(cx,
+    impl $reg_ty {
+      $doc_attr
+      #[allow(dead_code)]
+      pub fn reset(&self) {
+        $store;
+      }
+    }
+    );
+  item.unwrap()
+}
+
 fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>,
                       reg: &node::Reg, field: &node::Field)
                       -> P<ast::Method>
@@ -131,9 +287,11 @@ fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>,
   let field_ty: P<ast::Ty> =
     cx.ty_path(utils::field_type_path(cx, path, reg, field), None);
   let setter_ty = utils::setter_name(cx, path);
+  let range_attr = utils::range_attribute(cx, reg, field);
   if field.count.node == 1 {
     quote_method!(cx,
-      #[allow(dead_code, missing_doc)]
+      $range_attr
+      #[allow(dead_code, missing_doc, unused_attribute)]
       pub fn $fn_name<'a>(&'a self, new_value: $field_ty) -> $setter_ty<'a> {
         let mut setter: $setter_ty = $setter_ty::new(self);
         setter.$fn_name(new_value);
@@ -142,7 +300,8 @@ fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>,
     )
   } else {
     quote_method!(cx,
-      #[allow(dead_code, missing_doc)]
+      $range_attr
+      #[allow(dead_code, missing_doc, unused_attribute)]
       pub fn $fn_name<'a>(&'a self, idx: uint, new_value: $field_ty) -> $setter_ty<'a> {
         let mut setter: $setter_ty = $setter_ty::new(self);
         setter.$fn_name(idx, new_value);
@@ -152,13 +311,50 @@ fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>,
   }
 }
 
+fn build_field_try_set_fn(cx: &ExtCtxt, path: &Vec<String>,
+                          reg: &node::Reg, field: &node::Field)
+                          -> P<ast::Method>
+{
+  let fn_name =
+    cx.ident_of((String::from_str("try_set_")+field.name.node).as_slice());
+  let field_ty: P<ast::Ty> =
+    cx.ty_path(utils::field_type_path(cx, path, reg, field), None);
+  let setter_ty = utils::setter_name(cx, path);
+  if field.count.node == 1 {
+    quote_method!(cx,
+      #[allow(dead_code, missing_doc)]
+      pub fn $fn_name<'a>(&'a self, new_value: $field_ty)
+          -> ::core::result::Result<$setter_ty<'a>, $field_ty> {
+        let mut setter: $setter_ty = $setter_ty::new(self);
+        match setter.$fn_name(new_value) {
+          ::core::result::Ok(_) => {},
+          ::core::result::Err(e) => return ::core::result::Err(e),
+        }
+        ::core::result::Ok(setter)
+      }
+    )
+  } else {
+    quote_method!(cx,
+      #[allow(dead_code, missing_doc)]
+      pub fn $fn_name<'a>(&'a self, idx: uint, new_value: $field_ty)
+          -> ::core::result::Result<$setter_ty<'a>, $field_ty> {
+        let mut setter: $setter_ty = $setter_ty::new(self);
+        match setter.$fn_name(idx, new_value) {
+          ::core::result::Ok(_) => {},
+          ::core::result::Err(e) => return ::core::result::Err(e),
+        }
+        ::core::result::Ok(setter)
+      }
+    )
+  }
+}
+
 fn build_field_get_fn(cx: &ExtCtxt, path: &Vec<String>,
                       reg: &node::Reg, field: &node::Field)
                       -> P<ast::Method>
 {
   let fn_name = cx.ident_of(field.name.node.as_slice());
-  let field_ty: P<ast::Ty> =
-    cx.ty_path(utils::field_type_path(cx, path, reg, field), None);
+  let field_ty: P<ast::Ty> = utils::field_getter_type(cx, path, reg, field);
   let getter_ty = utils::getter_name(cx, path);
   if field.count.node == 1 {
     quote_method!(cx,
@@ -177,12 +373,13 @@ fn build_field_get_fn(cx: &ExtCtxt, path: &Vec<String>,
   }
 }
 
-fn build_field_clear_fn(cx: &ExtCtxt, path: &Vec<String>,
-                        _reg: &node::Reg, field: &node::Field)
-                        -> P<ast::Method>
+/// Build a method clearing or setting a flag (depending upon `prefix`)
+fn build_field_flag_fn(cx: &ExtCtxt, path: &Vec<String>,
+                       _reg: &node::Reg, field: &node::Field, prefix: &str)
+                       -> P<ast::Method>
 {
   let fn_name =
-    cx.ident_of((String::from_str("clear_")+field.name.node).as_slice());
+    cx.ident_of((String::from_str(prefix)+field.name.node).as_slice());
   let setter_ty = utils::setter_name(cx, path);
   if field.count.node == 1 {
     quote_method!(cx,
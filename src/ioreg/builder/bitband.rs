@@ -0,0 +1,130 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use syntax::ast;
+use syntax::ptr::P;
+use syntax::codemap::DUMMY_SP;
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::build::AstBuilder;
+use syntax::ext::quote::rt::ToTokens;
+
+use super::Builder;
+use super::utils;
+use super::super::node;
+
+/// A visitor to build the `atomic_set_` and `atomic_clear_` methods of
+/// single-bit fields. The parser only accepts blocks asking for these
+/// if all of their instances lie in a bit-band region.
+pub struct BuildBitbands<'a> {
+  builder: &'a mut Builder,
+  cx: &'a ExtCtxt<'a>,
+}
+
+impl<'a> BuildBitbands<'a> {
+  pub fn new(builder: &'a mut Builder, cx: &'a ExtCtxt<'a>)
+      -> BuildBitbands<'a> {
+    BuildBitbands { builder: builder, cx: cx }
+  }
+}
+
+impl<'a> node::RegVisitor for BuildBitbands<'a> {
+  fn visit_prim_reg(&mut self, path: &Vec<String>, reg: &node::Reg,
+                    _width: node::RegWidth, fields: &Vec<node::Field>) {
+    // The bit-band alias performs a read-modify-write of the whole
+    // register in hardware, which is only safe if no field has side
+    // effects on reads or writes and no write sequence is needed.
+    let plain = reg.sequence.is_none() &&
+      fields.iter().all(|f| f.access == node::ReadWrite ||
+                            f.access == node::ReadOnly);
+    if !plain {
+      return;
+    }
+    for field in fields.iter() {
+      match build_bitband_accessors(self.cx, path, field) {
+        Some(item) => self.builder.push_item(item),
+        None       => {}
+      }
+    }
+  }
+}
+
+/// Build the `atomic_set_` and `atomic_clear_` methods of a read/write
+/// boolean field. These write to the field's bit-band alias and are only
+/// available on architectures with bit-banding.
+fn build_bitband_accessors(cx: &ExtCtxt, path: &Vec<String>,
+                           field: &node::Field) -> Option<P<ast::Item>>
+{
+  match (&field.access, &field.ty.node) {
+    (&node::ReadWrite, &node::BoolField) => {},
+    _ => return None,
+  }
+
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+  let items = vec!(build_bitband_fn(cx, path, field, "atomic_set_", 1),
+                   build_bitband_fn(cx, path, field, "atomic_clear_", 0));
+
+  let docstring = format!("Atomic bit-band accessors of the `{}` field",
+                          field.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  quote_item!(cx,
+    $doc_attr
+    #[cfg(cfg_arch_has_bitband)]
+    impl $reg_ty {
+      $items
+    }
+  )
+}
+
+fn build_bitband_fn(cx: &ExtCtxt, path: &Vec<String>, field: &node::Field,
+                    prefix: &str, value: u32) -> P<ast::Method>
+{
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+  let fn_name =
+    cx.ident_of((String::from_str(prefix)+field.name.node).as_slice());
+  let low_bit = utils::expr_int(cx, field.low_bit as i64);
+  let value = utils::expr_int(cx, value as i64);
+  if field.count.node == 1 {
+    quote_method!(cx,
+      #[allow(dead_code, missing_doc)]
+      #[inline(always)]
+      pub fn $fn_name(&self) {
+        let addr = self as *const $reg_ty as uint;
+        let alias = (addr & 0xf000_0000) + 0x0200_0000 +
+                    ((addr & 0x000f_ffff) << 5) + ($low_bit << 2);
+        unsafe {
+          (*(alias as *const ::zinc::util::volatile_cell::VolatileCell<u32>))
+            .set($value)
+        }
+      }
+    )
+  } else {
+    quote_method!(cx,
+      #[allow(dead_code, missing_doc)]
+      #[inline(always)]
+      pub fn $fn_name(&self, idx: uint) {
+        let addr = self as *const $reg_ty as uint;
+        let alias = (addr & 0xf000_0000) + 0x0200_0000 +
+                    ((addr & 0x000f_ffff) << 5) + (($low_bit + idx) << 2);
+        unsafe {
+          (*(alias as *const ::zinc::util::volatile_cell::VolatileCell<u32>))
+            .set($value)
+        }
+      }
+    )
+  }
+}
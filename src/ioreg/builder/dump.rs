@@ -0,0 +1,292 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ast;
+use syntax::ptr::P;
+use syntax::ext::base::ExtCtxt;
+use syntax::codemap::DUMMY_SP;
+use syntax::ext::build::AstBuilder;
+use syntax::ext::quote::rt::ToTokens;
+
+use super::Builder;
+use super::super::node;
+use super::utils;
+
+/// A visitor to build the `dump` methods printing the values of
+/// registers to a `CharIO`
+pub struct BuildDumps<'a> {
+  builder: &'a mut Builder,
+  cx: &'a ExtCtxt<'a>,
+}
+
+impl<'a> BuildDumps<'a> {
+  pub fn new(builder: &'a mut Builder, cx: &'a ExtCtxt<'a>)
+      -> BuildDumps<'a> {
+    BuildDumps { builder: builder, cx: cx }
+  }
+}
+
+impl<'a> node::RegVisitor for BuildDumps<'a> {
+  fn visit_prim_reg(&mut self, path: &Vec<String>,
+                    reg: &node::Reg, _width: node::RegWidth,
+                    fields: &Vec<node::Field>) {
+    if fields.iter().any(|f| f.access.is_readable()) {
+      let it = build_getter_dump(self.cx, path, reg, fields);
+      self.builder.push_item(it);
+    }
+  }
+
+  fn visit_union_reg(&mut self, path: &Vec<String>, _reg: &node::Reg,
+                     subregs: Rc<Vec<node::Reg>>) {
+    let it = build_union_dump(self.cx, path, subregs.deref());
+    self.builder.push_item(it);
+  }
+}
+
+/// Whether a register can be read (and hence dumped) without side effects
+fn is_dumpable(reg: &node::Reg) -> bool {
+  match reg.ty {
+    node::RegPrim(_, ref fields) =>
+      fields.iter().any(|f| f.access.is_readable()) &&
+      fields.iter().all(|f| f.access != node::ReadToClear),
+    node::RegUnion(_) => true,
+  }
+}
+
+fn puts(cx: &ExtCtxt, s: &str) -> P<ast::Expr> {
+  let s = cx.expr_str(DUMMY_SP, utils::intern_string(cx, s.to_string()));
+  quote_expr!(cx, io.puts($s))
+}
+
+fn block(cx: &ExtCtxt, exprs: Vec<P<ast::Expr>>) -> P<ast::Expr> {
+  let stmts = exprs.into_iter().map(|e| cx.stmt_expr(e)).collect();
+  cx.expr_block(cx.block(DUMMY_SP, stmts, None))
+}
+
+/// Print the given raw value of a field of the given width in hexadecimal
+fn put_hex(cx: &ExtCtxt, raw: P<ast::Expr>, width: uint) -> P<ast::Expr> {
+  if width <= 32 {
+    quote_expr!(cx, {
+      io.puts("0x");
+      io.puth(($raw) as u32);
+    })
+  } else {
+    // `puth` only takes 32-bit values, print the low word ourselves
+    quote_expr!(cx, {
+      let v = ($raw) as u64;
+      io.puts("0x");
+      if (v >> 32) == 0 {
+        io.puth(v as u32);
+      } else {
+        io.puth((v >> 32) as u32);
+        let mut shift = 32u;
+        while shift > 0 {
+          shift -= 4;
+          let d = ((v >> shift) & 0xf) as u8;
+          io.putc((if d < 10 { '0' as u8 + d } else { 'a' as u8 + d - 10 })
+                  as char);
+        }
+      }
+    })
+  }
+}
+
+/// Build an expression printing the value of a field
+fn put_value(cx: &ExtCtxt, field: &node::Field, idx: Option<P<ast::Expr>>)
+             -> P<ast::Expr> {
+  let getter = cx.ident_of(field.name.node.as_slice());
+  let value = match idx {
+    Some(ref idx) => quote_expr!(cx, self.$getter($idx)),
+    None => quote_expr!(cx, self.$getter()),
+  };
+  let mask = utils::mask(cx, field);
+  let shift = utils::shift(cx, idx, field);
+  let raw = quote_expr!(cx, (self.value >> $shift) & $mask);
+
+  match field.ty.node {
+    node::BoolField =>
+      quote_expr!(cx, io.puts(if $value { "true" } else { "false" })),
+    node::SIntField if field.width <= 32 =>
+      quote_expr!(cx, {
+        let v = $value as i64;
+        if v < 0 {
+          io.puts("-");
+          io.puti((-v) as u32);
+        } else {
+          io.puti(v as u32);
+        }
+      }),
+    node::UIntField | node::SIntField => put_hex(cx, raw, field.width),
+    node::FixedField { frac_bits: frac_bits, .. } => {
+      let hex = put_hex(cx, raw, field.width);
+      let scale = puts(cx, format!("/2^{}", frac_bits).as_slice());
+      quote_expr!(cx, { $hex; $scale; })
+    },
+    node::EnumField { variants: ref variants, .. } => {
+      let exhaustive = field.is_exhaustive();
+      let mut arms: Vec<ast::Arm> = variants.iter().map(|v| {
+        let pat = cx.pat_ident(DUMMY_SP,
+                               cx.ident_of(v.name.node.as_slice()));
+        let pat = if exhaustive {
+          pat
+        } else {
+          cx.pat_enum(DUMMY_SP,
+                      cx.path_global(DUMMY_SP,
+                                     vec!(cx.ident_of("core"),
+                                          cx.ident_of("option"),
+                                          cx.ident_of("Some"))),
+                      vec!(pat))
+        };
+        cx.arm(DUMMY_SP, vec!(pat), puts(cx, v.name.node.as_slice()))
+      }).collect();
+      if !exhaustive {
+        // print the raw value of values without a variant
+        arms.push(cx.arm(DUMMY_SP, vec!(cx.pat_wild(DUMMY_SP)),
+                         put_hex(cx, raw, field.width)));
+      }
+      cx.expr_match(DUMMY_SP, value, arms)
+    },
+  }
+}
+
+/// Build the `dump` method of the getter of a register, printing
+/// the name and value of each readable field
+fn build_getter_dump(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
+                     fields: &Vec<node::Field>) -> P<ast::Item> {
+  let getter_ty = utils::getter_name(cx, path);
+  let mut exprs: Vec<P<ast::Expr>> = Vec::new();
+  for field in fields.iter().filter(|f| f.access.is_readable()) {
+    if !exprs.is_empty() {
+      exprs.push(puts(cx, " "));
+    }
+    if field.count.node == 1 {
+      exprs.push(puts(cx, format!("{}=", field.name.node).as_slice()));
+      exprs.push(put_value(cx, field, None));
+    } else {
+      let count = utils::expr_int(cx, field.count.node as i64);
+      let name = puts(cx, format!("{}[", field.name.node).as_slice());
+      let value = put_value(cx, field, Some(quote_expr!(cx, idx)));
+      exprs.push(quote_expr!(cx, {
+        let mut idx = 0u;
+        while idx < $count {
+          if idx > 0 {
+            io.puts(" ");
+          }
+          $name;
+          io.puti(idx as u32);
+          io.puts("]=");
+          $value;
+          idx += 1;
+        }
+      }));
+    }
+  }
+  let body = block(cx, exprs);
+
+  let docstring = format!("Write the values of the fields of `{}` to a \
+                           `CharIO`",
+                          reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+  let item = quote_item!(cx,
+    #[allow(dead_code)]
+    impl $getter_ty {
+      $doc_attr
+      pub fn dump<T: CharIO>(&self, io: &T) {
+        $body
+      }
+    }
+  );
+  item.unwrap()
+}
+
+/// Build the `dump` method of a register group, printing each of its
+/// registers on a separate line. Registers which can't be read without
+/// side effects are skipped.
+fn build_union_dump(cx: &ExtCtxt, path: &Vec<String>,
+                    subregs: &Vec<node::Reg>) -> P<ast::Item> {
+  let union_ty = utils::path_ident(cx, path);
+  let mut regs: Vec<&node::Reg> =
+    subregs.iter().filter(|r| is_dumpable(*r)).collect();
+  regs.sort_by(|r1, r2| r1.offset.cmp(&r2.offset));
+
+  let exprs: Vec<P<ast::Expr>> = regs.iter().map(|reg| {
+    let name = cx.ident_of(reg.name.node.as_slice());
+    let (this, label) = if reg.count.node == 1 {
+      (quote_expr!(cx, self.$name),
+       puts(cx, reg.name.node.as_slice()))
+    } else {
+      let label = puts(cx, format!("{}[", reg.name.node).as_slice());
+      (quote_expr!(cx, self.$name[idx]),
+       quote_expr!(cx, {
+         $label;
+         io.puti(idx as u32);
+         io.puts("]");
+       }))
+    };
+    let line = match reg.ty {
+      node::RegPrim(..) => quote_expr!(cx, {
+        let mut i = 0u;
+        while i < level {
+          io.puts("  ");
+          i += 1;
+        }
+        $label;
+        io.puts(": ");
+        $this.get().dump(io);
+        io.puts("\n");
+      }),
+      node::RegUnion(_) => quote_expr!(cx, {
+        let mut i = 0u;
+        while i < level {
+          io.puts("  ");
+          i += 1;
+        }
+        $label;
+        io.puts(":\n");
+        $this.dump_indented(io, level + 1);
+      }),
+    };
+    if reg.count.node == 1 {
+      line
+    } else {
+      let count = utils::expr_int(cx, reg.count.node as i64);
+      quote_expr!(cx, {
+        let mut idx = 0u;
+        while idx < $count {
+          $line;
+          idx += 1;
+        }
+      })
+    }
+  }).collect();
+  let body = block(cx, exprs);
+
+  let item = quote_item!(cx,
+    #[allow(dead_code, unused_variable)]
+    impl $union_ty {
+      #[doc="Write the values of all registers to a `CharIO`, one per line"]
+      pub fn dump<T: CharIO>(&self, io: &T) {
+        self.dump_indented(io, 0);
+      }
+
+      #[doc(hidden)]
+      pub fn dump_indented<T: CharIO>(&self, io: &T, level: uint) {
+        $body
+      }
+    }
+  );
+  item.unwrap()
+}
@@ -42,7 +42,7 @@ impl<'a> node::RegVisitor for BuildGetters<'a> {
   fn visit_prim_reg(&mut self, path: &Vec<String>,
                     reg: &node::Reg, _width: node::RegWidth,
                     fields: &Vec<node::Field>) {
-    if fields.iter().any(|f| f.access != node::WriteOnly) {
+    if fields.iter().any(|f| f.access.is_readable()) {
       let it = build_type(self.cx, path, reg);
       self.builder.push_item(it);
 
@@ -100,6 +100,18 @@ fn from_primitive(cx: &ExtCtxt, reg: &node::Reg,
                   -> P<ast::Expr> {
   match field.ty.node {
     node::UIntField => prim,
+    node::SIntField => utils::sign_extend(cx, reg, field, prim),
+    node::FixedField { signed: signed, frac_bits: frac_bits } => {
+      let float_ty = cx.ty_ident(DUMMY_SP,
+                                 cx.ident_of(utils::float_type_name(field)));
+      let raw = if signed {
+        utils::sign_extend(cx, reg, field, prim)
+      } else {
+        prim
+      };
+      let scale = utils::expr_scale(cx, frac_bits);
+      quote_expr!(cx, ($raw as $float_ty) / $scale)
+    },
     node::BoolField =>
       cx.expr_binary(DUMMY_SP, ast::BiNe,
                      prim, utils::expr_int(cx, 0)),
@@ -107,24 +119,27 @@ fn from_primitive(cx: &ExtCtxt, reg: &node::Reg,
       let from = match reg.ty {
         node::RegPrim(width,_) =>
           match width {
+            node::Reg64 | node::Split64 => "from_u64",
             node::Reg32 => "from_u32",
             node::Reg16 => "from_u16",
             node::Reg8  => "from_u8",
           },
         _ => fail!("Can't convert group register to primitive type"),
       };
-      cx.expr_method_call(
+      let value = cx.expr_call_global(
         DUMMY_SP,
-        cx.expr_call_global(
-          DUMMY_SP,
-          vec!(cx.ident_of("core"),
-               cx.ident_of("num"),
-               cx.ident_of(from)),
-          vec!(prim)
-        ),
-        cx.ident_of("unwrap"),
-        Vec::new()
-      )
+        vec!(cx.ident_of("core"),
+             cx.ident_of("num"),
+             cx.ident_of(from)),
+        vec!(prim)
+      );
+      // Values without a variant can only be seen if the enumeration
+      // isn't exhaustive, otherwise the getter returns an `Option`
+      if field.is_exhaustive() {
+        cx.expr_method_call(DUMMY_SP, value, cx.ident_of("unwrap"), Vec::new())
+      } else {
+        value
+      }
     },
   }
 }
@@ -153,8 +168,7 @@ fn build_field_get_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
                       field: &node::Field) -> P<ast::Method>
 {
   let fn_name = cx.ident_of(field.name.node.as_slice());
-  let field_ty: P<ast::Ty> =
-    cx.ty_path(utils::field_type_path(cx, path, reg, field), None);
+  let field_ty: P<ast::Ty> = utils::field_getter_type(cx, path, reg, field);
   let mask = utils::mask(cx, field);
   let field_doc = match field.docstring {
     Some(d) => d.node,
@@ -0,0 +1,81 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use syntax::ast;
+use syntax::ptr::P;
+use syntax::codemap::DUMMY_SP;
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::build::AstBuilder;
+use syntax::ext::quote::rt::ToTokens;
+
+use node;
+use super::utils;
+
+/// Build an accessor function for an instance of a register block at
+/// a fixed address. For instance,
+///
+///     pub fn UART0() -> &'static UART { ... }
+///
+/// Instances with registers of their own return their extension type
+/// instead, which dereferences to the register block.
+pub fn build_instance(cx: &ExtCtxt, reg: &node::Reg,
+                      instance: &node::Instance) -> P<ast::Item> {
+  let name = cx.ident_of(instance.name.node.as_slice());
+  let ty_name = match instance.extension {
+    Some(ref ext) => ext.name.node.clone(),
+    None => reg.name.node.clone(),
+  };
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, cx.ident_of(ty_name.as_slice()));
+  let address = utils::expr_int(cx, instance.address.node as i64);
+
+  let docstring = format!("The `{}` instance of `{}` at `0x{:x}`",
+                          instance.name.node,
+                          reg.name.node,
+                          instance.address.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  let item = quote_item!(cx,
+    $doc_attr
+    #[allow(non_snake_case, dead_code)]
+    #[inline(always)]
+    pub fn $name() -> &'static $reg_ty {
+      unsafe { &*($address as *const $reg_ty) }
+    }
+  );
+  let mut item: ast::Item = item.unwrap().deref().clone();
+  item.span = instance.name.span;
+  P(item)
+}
+
+/// Build the `Deref` implementation giving access to the registers of
+/// a block through the extension type of one of its instances. Both
+/// share the base address of the instance.
+pub fn build_extension_deref(cx: &ExtCtxt, reg: &node::Reg,
+                             ext: &node::Reg) -> P<ast::Item> {
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, cx.ident_of(reg.name.node.as_slice()));
+  let ext_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, cx.ident_of(ext.name.node.as_slice()));
+  let item = quote_item!(cx,
+    impl ::core::ops::Deref<$reg_ty> for $ext_ty {
+      #[inline(always)]
+      fn deref<'a>(&'a self) -> &'a $reg_ty {
+        unsafe { &*(self as *const $ext_ty as *const $reg_ty) }
+      }
+    }
+  );
+  item.unwrap()
+}
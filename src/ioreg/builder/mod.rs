@@ -26,6 +26,9 @@ mod getter;
 mod union;
 mod register;
 mod accessors;
+mod instance;
+mod dump;
+mod bitband;
 
 pub struct Builder {
   items: Vec<P<ast::Item>>,
@@ -36,16 +39,42 @@ impl Builder {
     Builder {items: Vec::new()}
   }
 
-  pub fn emit_items(&mut self, cx: &ExtCtxt, reg: Rc<node::Reg>)
-                    -> Vec<P<ast::Item>> {
-    node::visit_reg(&*reg, &mut setter::BuildSetters::new(self, cx));
-    node::visit_reg(&*reg, &mut getter::BuildGetters::new(self, cx));
-    node::visit_reg(&*reg, &mut register::BuildRegStructs::new(self, cx));
-    node::visit_reg(&*reg, &mut union::BuildUnionTypes::new(self, cx));
-    node::visit_reg(&*reg, &mut accessors::BuildAccessors::new(self, cx));
+  pub fn emit_items(&mut self, cx: &ExtCtxt, reg: Rc<node::Reg>,
+                    instances: &Vec<node::Instance>,
+                    options: &node::BlockOptions) -> Vec<P<ast::Item>> {
+    self.emit_group(cx, &*reg, options);
+    // extensions shared by several instances are only emitted once
+    let mut extensions: Vec<String> = Vec::new();
+    for i in instances.iter() {
+      match i.extension {
+        Some(ref ext) if !extensions.contains(&ext.name.node) => {
+          self.emit_group(cx, &**ext, options);
+          self.push_item(instance::build_extension_deref(cx, &*reg, &**ext));
+          extensions.push(ext.name.node.clone());
+        },
+        _ => {},
+      }
+      self.push_item(instance::build_instance(cx, &*reg, i));
+    }
     self.items.clone()
   }
 
+  /// Emit the types of a register group and their methods
+  fn emit_group(&mut self, cx: &ExtCtxt, reg: &node::Reg,
+                options: &node::BlockOptions) {
+    node::visit_reg(reg, &mut setter::BuildSetters::new(self, cx));
+    node::visit_reg(reg, &mut getter::BuildGetters::new(self, cx));
+    node::visit_reg(reg, &mut register::BuildRegStructs::new(self, cx));
+    node::visit_reg(reg, &mut union::BuildUnionTypes::new(self, cx));
+    node::visit_reg(reg, &mut accessors::BuildAccessors::new(self, cx));
+    if options.dump {
+      node::visit_reg(reg, &mut dump::BuildDumps::new(self, cx));
+    }
+    if options.bitband {
+      node::visit_reg(reg, &mut bitband::BuildBitbands::new(self, cx));
+    }
+  }
+
   pub fn push_item(&mut self, item: P<ast::Item>) {
     self.items.push(item);
   }
@@ -14,10 +14,11 @@
 // limitations under the License.
 
 use std::iter::FromIterator;
+use std::rc::Rc;
 use syntax::ast;
 use syntax::ptr::P;
 use syntax::ast_util::empty_generics;
-use syntax::codemap::{respan, mk_sp};
+use syntax::codemap::{respan, mk_sp, DUMMY_SP};
 use syntax::ext::base::ExtCtxt;
 use syntax::ext::build::AstBuilder;
 use syntax::ext::quote::rt::ToTokens;
@@ -43,8 +44,32 @@ impl<'a> node::RegVisitor for BuildRegStructs<'a> {
       }
     }
 
+    match width {
+      node::Split64 => {
+        for item in build_split_words(self.cx, path, reg).into_iter() {
+          self.builder.push_item(item);
+        }
+      },
+      _ => {},
+    }
+
     let reg_struct = build_reg_struct(self.cx, path, reg, width);
     self.builder.push_item(reg_struct);
+
+    match build_reset_const(self.cx, path, reg) {
+      Some(item) => self.builder.push_item(item),
+      None       => {}
+    }
+
+    self.builder.push_item(build_offset_const(self.cx, path, reg));
+  }
+
+  fn visit_union_reg(&mut self, path: &Vec<String>, reg: &node::Reg,
+                     _subregs: Rc<Vec<node::Reg>>) {
+    // the top-level group has no offset
+    if path.len() > 1 {
+      self.builder.push_item(build_offset_const(self.cx, path, reg));
+    }
   }
 }
 
@@ -96,10 +121,14 @@ fn build_field_type(cx: &ExtCtxt, path: &Vec<String>,
 ///
 ///     pub struct REG {_value: u32}
 fn build_reg_struct(cx: &ExtCtxt, path: &Vec<String>,
-    reg: &node::Reg, _width: node::RegWidth) -> P<ast::Item> {
+    reg: &node::Reg, width: node::RegWidth) -> P<ast::Item> {
   let packed_ty =
     utils::reg_primitive_type(cx, reg)
     .expect("Unexpected non-primitive reg");
+  let value_ty: P<ast::Ty> = match width {
+    node::Split64 => cx.ty_ident(DUMMY_SP, utils::words_name(cx, path)),
+    _ => quote_ty!(cx, VolatileCell<$packed_ty>),
+  };
 
   let reg_doc = match reg.docstring {
     Some(d) => token::get_ident(d.node).get().into_string(),
@@ -115,9 +144,101 @@ fn build_reg_struct(cx: &ExtCtxt, path: &Vec<String>,
     $doc_attr
     #[allow(non_camel_case_types)]
     pub struct $ty_name {
-      value: VolatileCell<$packed_ty>,
+      value: $value_ty,
+    }
+  );
+  let mut item: ast::Item = item.unwrap().deref().clone();
+  item.span = reg.name.span;
+  P(item)
+}
+
+/// Build the type holding the words of a split 64-bit register. This
+/// behaves like a `VolatileCell<u64>` but is accessed a word at a time,
+/// reading the high word both before and after the low word to obtain
+/// a consistent value should the register change in between.
+fn build_split_words(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                     -> Vec<P<ast::Item>> {
+  let name = utils::words_name(cx, path);
+  let docstring = format!("Words of split register `{}`", reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  let item = quote_item!(cx,
+    $doc_attr
+    #[allow(non_camel_case_types)]
+    pub struct $name {
+      low: VolatileCell<u32>,
+      high: VolatileCell<u32>,
+    }
+  );
+  let impl_ = quote_item!(cx,
+    #[allow(dead_code)]
+    impl $name {
+      #[inline]
+      fn get(&self) -> u64 {
+        loop {
+          let high = self.high.get();
+          let low = self.low.get();
+          if self.high.get() == high {
+            return ((high as u64) << 32) | (low as u64);
+          }
+        }
+      }
+
+      #[inline]
+      fn set(&self, value: u64) {
+        self.low.set(value as u32);
+        self.high.set((value >> 32) as u32);
+      }
     }
   );
+  vec!(item.unwrap(), impl_.unwrap())
+}
+
+/// Build the constant holding the reset value of a register if one was
+/// given. For instance,
+///
+///     pub const REG_RESET: u32 = 0x40;
+fn build_reset_const(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                      -> Option<P<ast::Item>> {
+  reg.reset.as_ref().map(|reset| {
+    let packed_ty =
+      utils::reg_primitive_type(cx, reg)
+      .expect("Unexpected non-primitive reg");
+    let name = utils::reset_name(cx, path);
+    let value = utils::expr_uint(cx, reset.node as u64);
+
+    let docstring = format!("Reset value of register `{}`", reg.name.node);
+    let doc_attr = utils::doc_attribute(cx,
+                                        utils::intern_string(cx, docstring));
+    let item = quote_item!(cx,
+      $doc_attr
+      #[allow(non_uppercase_statics, dead_code)]
+      pub const $name: $packed_ty = $value;
+    );
+    let mut item: ast::Item = item.unwrap().deref().clone();
+    item.span = reset.span;
+    P(item)
+  })
+}
+
+/// Build the constant holding the offset in bytes of a register (or of
+/// the first element of a register array) within its group. For
+/// instance,
+///
+///     pub const REG_OFFSET: uint = 0x4;
+fn build_offset_const(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                       -> P<ast::Item> {
+  let name = utils::offset_name(cx, path);
+  let value = utils::expr_uint(cx, reg.offset as u64);
+
+  let docstring = format!("Offset of register `{}` within its group",
+                          reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+  let item = quote_item!(cx,
+    $doc_attr
+    #[allow(non_uppercase_statics, dead_code)]
+    pub const $name: uint = $value;
+  );
   let mut item: ast::Item = item.unwrap().deref().clone();
   item.span = reg.name.span;
   P(item)
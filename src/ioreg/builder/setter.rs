@@ -42,7 +42,7 @@ impl<'a> node::RegVisitor for BuildSetters<'a> {
   fn visit_prim_reg<'a>(&'a mut self, path: &Vec<String>,
       reg: &'a node::Reg, _width: node::RegWidth, fields: &Vec<node::Field>)
   {
-    if fields.iter().any(|f| f.access != node::ReadOnly) {
+    if fields.iter().any(|f| f.access.is_writable()) {
       let it = build_type(self.cx, path, reg, fields);
       self.builder.push_item(it);
 
@@ -78,6 +78,7 @@ fn build_type(cx: &ExtCtxt, path: &Vec<String>,
     pub struct $name<'a> {
       value: $packed_ty,
       mask: $packed_ty,
+      write: bool,
       reg: &'a $reg_ty,
     }
   );
@@ -98,34 +99,73 @@ fn build_new<'a>(cx: &'a ExtCtxt, path: &Vec<String>)
       $setter_ty {
         value: 0,
         mask: 0,
+        write: false,
         reg: reg,
       }
     });
   item.unwrap()
 }
 
-fn build_drop(cx: &ExtCtxt, path: &Vec<String>,
-    reg: &node::Reg, fields: &Vec<node::Field>) -> P<ast::Item>
-{
+fn build_new_write<'a>(cx: &'a ExtCtxt, path: &Vec<String>,
+                       reg: &node::Reg, fields: &Vec<node::Field>)
+                       -> P<ast::Item> {
+  let reg_ty: P<ast::Ty> =
+    cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
   let setter_ty: P<ast::Ty> = cx.ty_ident(DUMMY_SP,
                                           utils::setter_name(cx, path));
-  let unpacked_ty = utils::reg_primitive_type(cx, reg)
-    .expect("Unexpected non-primitive register");
 
-  // ensure we don't unintentionally clear a set-to-clear flag
-  let mut clear: u32 = 0;
+  // start from the reset value without accidentally clearing or
+  // setting any flags
+  let reset = reg.reset.as_ref().map_or(0, |r| r.node as u64);
+  let (zeros, ones) = flag_masks(fields);
+  let initial_value = utils::expr_uint(cx, reset & !zeros | ones);
+  let item = quote_item!(cx,
+    #[doc="Create a new updater which overwrites the whole register, \
+           starting from its reset value"]
+    pub fn new_write(reg: &'a $reg_ty) -> $setter_ty {
+      $setter_ty {
+        value: $initial_value,
+        mask: 0,
+        write: true,
+        reg: reg,
+      }
+    });
+  item.unwrap()
+}
+
+/// The masks of the bits which must be written as zero (resp. one) to
+/// leave the flags of a register untouched
+fn flag_masks(fields: &Vec<node::Field>) -> (u64, u64) {
+  let mut zeros: u64 = 0;
+  let mut ones: u64 = 0;
   for f in fields.iter() {
+    let mask = utils::bit_mask(f.count.node * f.width) << f.low_bit;
     match f.access {
-      node::SetToClear => {
-        let mask = 1 << (f.count.node * f.width) - 1;
-        clear |= mask;
-      },
+      node::SetToClear | node::WriteOneToSet => zeros |= mask,
+      node::WriteZeroToClear => ones |= mask,
       _ => {},
     }
   }
+  (zeros, ones)
+}
+
+fn build_drop(cx: &ExtCtxt, path: &Vec<String>,
+    reg: &node::Reg, fields: &Vec<node::Field>) -> P<ast::Item>
+{
+  let setter_ty: P<ast::Ty> = cx.ty_ident(DUMMY_SP,
+                                          utils::setter_name(cx, path));
+  let unpacked_ty = utils::reg_primitive_type(cx, reg)
+    .expect("Unexpected non-primitive register");
+
+  // ensure we don't unintentionally clear or set a flag
+  let (zeros, ones) = flag_masks(fields);
+  let zeros = utils::expr_uint(cx, zeros);
+  let ones = utils::expr_uint(cx, ones);
 
   // no need to read write-only registers
-  let wo_reg: bool = fields.iter().all(|f| f.access == node::WriteOnly);
+  let wo_reg: bool = fields.iter().all(|f| {
+    f.access == node::WriteOnly || f.access == node::WriteOneToSet
+  });
   let initial_value =
     if wo_reg {
       quote_expr!(cx, 0)
@@ -133,15 +173,23 @@ fn build_drop(cx: &ExtCtxt, path: &Vec<String>,
       quote_expr!(cx, self.reg.value.get())
     };
 
+  let write = utils::store(cx, reg, quote_expr!(cx, self.reg),
+                           quote_expr!(cx, self.value));
+  let update = utils::store(cx, reg, quote_expr!(cx, self.reg),
+                            quote_expr!(cx, self.value | v));
+
   let item = quote_item!(cx,
     #[unsafe_destructor]
     #[doc = "This performs the register update"]
     impl<'a> Drop for $setter_ty<'a> {
       fn drop(&mut self) {
-        let clear_mask: $unpacked_ty = $clear as $unpacked_ty;
-        if self.mask != 0 {
-          let v: $unpacked_ty = $initial_value & ! clear_mask & ! self.mask;
-          self.reg.value.set(self.value | v);
+        let zeros: $unpacked_ty = $zeros;
+        let ones: $unpacked_ty = $ones;
+        if self.write {
+          $write;
+        } else if self.mask != 0 {
+          let v: $unpacked_ty = ($initial_value & ! zeros | ones) & ! self.mask;
+          $update;
         }
       }
     }
@@ -162,18 +210,20 @@ fn build_impl(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
               fields: &Vec<node::Field>) -> P<ast::Item>
 {
   let new = build_new(cx, path);
+  let new_write = build_new_write(cx, path, reg, fields);
   let setter_ty: P<ast::Ty> = cx.ty_ident(
     DUMMY_SP,
     utils::setter_name(cx, path));
   let methods: Vec<P<ast::Method>> =
     FromIterator::from_iter(
       fields.iter()
-        .filter_map(|field| build_field_fn(cx, path, reg, field)));
+        .flat_map(|field| build_field_fn(cx, path, reg, field).into_iter()));
   let done: P<ast::Method> = build_done(cx);
   let impl_ = quote_item!(cx,
     #[allow(dead_code)]
     impl<'a> $setter_ty<'a> {
       $new
+      $new_write
       $methods
       $done
     }
@@ -182,12 +232,24 @@ fn build_impl(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
 }
 
 fn build_field_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
-                  field: &node::Field) -> Option<P<ast::Method>>
+                  field: &node::Field) -> Vec<P<ast::Method>>
 {
   match field.access {
-    node::ReadOnly => None,
-    node::SetToClear => Some(build_field_clear_fn(cx, path, reg, field)),
-    _ => Some(build_field_set_fn(cx, path, reg, field)),
+    node::ReadOnly | node::ReadToClear => Vec::new(),
+    node::SetToClear =>
+      vec!(build_field_flag_fn(cx, path, field, "clear_", "Clear", true)),
+    node::WriteZeroToClear =>
+      vec!(build_field_flag_fn(cx, path, field, "clear_", "Clear", false)),
+    node::WriteOneToSet =>
+      vec!(build_field_flag_fn(cx, path, field, "set_", "Set", true)),
+    node::ReadWrite | node::WriteOnly => {
+      let mut methods = vec!(build_field_set_fn(cx, path, reg, field));
+      match build_field_try_set_fn(cx, path, reg, field) {
+        Some(m) => methods.push(m),
+        None => {},
+      }
+      methods
+    },
   }
 }
 
@@ -212,14 +274,30 @@ fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
                           field.name.node,
                           field_doc);
   let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+  let new_value = to_primitive(cx, reg, field, quote_expr!(cx, new_value));
+
+  // literals which don't fit are rejected by the ioreg_out_of_range lint,
+  // other values are masked and abort in debug builds
+  let range_attr = utils::range_attribute(cx, reg, field);
+  let check = match utils::out_of_range(cx, reg, field) {
+    Some(out_of_range) => quote_expr!(cx,
+      if cfg!(cfg_debug) && $out_of_range {
+        ::zinc::util::support::abort()
+      }),
+    None => quote_expr!(cx, ()),
+  };
 
   if field.count.node == 1 {
     let shift = utils::shift(cx, None, field);
     quote_method!(cx,
       $doc_attr
+      $range_attr
+      #[allow(unused_attribute)]
       pub fn $fn_name<'b>(&'b mut self, new_value: $field_ty)
           -> &'b mut $setter_ty<'a> {
-        self.value |= (self.value & ! $mask) | ((new_value as $unpacked_ty) & $mask) << $shift;
+        $check;
+        self.value = (self.value & ! ($mask << $shift))
+                   | (($new_value as $unpacked_ty) & $mask) << $shift;
         self.mask |= $mask << $shift;
         self
       }
@@ -228,9 +306,13 @@ fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
     let shift = utils::shift(cx, Some(quote_expr!(cx, idx)), field);
     quote_method!(cx,
       $doc_attr
+      $range_attr
+      #[allow(unused_attribute)]
       pub fn $fn_name<'b>(&'b mut self, idx: uint, new_value: $field_ty)
           -> &'b mut $setter_ty<'a> {
-        self.value |= (self.value & ! $mask) | ((new_value as $unpacked_ty) & $mask) << $shift;
+        $check;
+        self.value = (self.value & ! ($mask << $shift))
+                   | (($new_value as $unpacked_ty) & $mask) << $shift;
         self.mask |= $mask << $shift;
         self
       }
@@ -238,39 +320,146 @@ fn build_field_set_fn(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg,
   }
 }
 
-fn build_field_clear_fn(cx: &ExtCtxt, path: &Vec<String>,
-    _: &node::Reg, field: &node::Field) -> P<ast::Method>
+/// Build a setter for an integer field which returns the value as an
+/// error rather than setting the field if it doesn't fit
+fn build_field_try_set_fn(cx: &ExtCtxt, path: &Vec<String>,
+                          reg: &node::Reg, field: &node::Field)
+                          -> Option<P<ast::Method>>
 {
+  let out_of_range = match utils::out_of_range(cx, reg, field) {
+    Some(e) => e,
+    None => return None,
+  };
   let setter_ty = utils::setter_name(cx, path);
+  let set_name =
+    cx.ident_of((String::from_str("set_")+field.name.node).as_slice());
   let fn_name =
-    cx.ident_of((String::from_str("clear_")+field.name.node).as_slice());
+    cx.ident_of((String::from_str("try_set_")+field.name.node).as_slice());
+  let field_ty: P<ast::Ty> =
+    cx.ty_path(utils::field_type_path(cx, path, reg, field), None);
+
+  let docstring = format!("Set value of `{}` field, failing with the value \
+                           if it doesn't fit in the field",
+                          field.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+
+  if field.count.node == 1 {
+    Some(quote_method!(cx,
+      $doc_attr
+      pub fn $fn_name<'b>(&'b mut self, new_value: $field_ty)
+          -> ::core::result::Result<&'b mut $setter_ty<'a>, $field_ty> {
+        if $out_of_range {
+          ::core::result::Err(new_value)
+        } else {
+          ::core::result::Ok(self.$set_name(new_value))
+        }
+      }
+    ))
+  } else {
+    Some(quote_method!(cx,
+      $doc_attr
+      pub fn $fn_name<'b>(&'b mut self, idx: uint, new_value: $field_ty)
+          -> ::core::result::Result<&'b mut $setter_ty<'a>, $field_ty> {
+        if $out_of_range {
+          ::core::result::Err(new_value)
+        } else {
+          ::core::result::Ok(self.$set_name(idx, new_value))
+        }
+      }
+    ))
+  }
+}
+
+/// Given an `Expr` of the given field's type, return an `Expr` which
+/// can be cast to the register's primitive type
+fn to_primitive(cx: &ExtCtxt, reg: &node::Reg, field: &node::Field,
+                value: P<ast::Expr>) -> P<ast::Expr> {
+  match field.ty.node {
+    node::FixedField { signed: signed, frac_bits: frac_bits } => {
+      let width = match reg.ty {
+        node::RegPrim(width, _) => width,
+        _ => fail!("Unexpected non-primitive register"),
+      };
+      // the raw value is computed in a type wide enough to hold both
+      // bounds of the field
+      let raw_ty = if signed {
+        cx.ty_path(utils::signed_type_path(cx, width), None)
+      } else {
+        cx.ty_path(utils::primitive_type_path(cx, width), None)
+      };
+      let float_ty = cx.ty_ident(DUMMY_SP,
+                                 cx.ident_of(utils::float_type_name(field)));
+      let scale = utils::expr_scale(cx, frac_bits);
+      let (min, max) = if signed {
+        let max = utils::bit_mask(field.width - 1);
+        let min = utils::expr_uint(cx, max + 1);
+        (quote_expr!(cx, -$min), utils::expr_uint(cx, max))
+      } else {
+        (utils::expr_uint(cx, 0),
+         utils::expr_uint(cx, utils::bit_mask(field.width)))
+      };
+      // saturate out of range values and round to the nearest
+      // representable value
+      quote_expr!(cx, {
+        let scaled = $value * $scale;
+        let raw: $raw_ty =
+          if scaled != scaled { 0 }
+          else if scaled >= ($max as $float_ty) { $max }
+          else if scaled <= ($min as $float_ty) { $min }
+          else if scaled < 0.0 { (scaled - 0.5) as $raw_ty }
+          else { (scaled + 0.5) as $raw_ty };
+        raw
+      })
+    },
+    _ => value,
+  }
+}
+
+/// Build a method writing the bits of a flag to one (or zero if `!ones`)
+/// to clear or set it
+fn build_field_flag_fn(cx: &ExtCtxt, path: &Vec<String>,
+    field: &node::Field, prefix: &str, verb: &str, ones: bool)
+    -> P<ast::Method>
+{
+  let setter_ty = utils::setter_name(cx, path);
+  let fn_name =
+    cx.ident_of((String::from_str(prefix)+field.name.node).as_slice());
   let mask = utils::mask(cx, field);
 
   let field_doc = match field.docstring {
     Some(d) => token::get_ident(d.node).get().into_string(),
     None => "no documentation".into_string(),
   };
-  let docstring = format!("Clear `{}` flag: {}",
+  let docstring = format!("{} `{}` flag: {}",
+                          verb,
                           field.name.node,
                           field_doc);
   let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
 
+  let update = |shift: P<ast::Expr>| if ones {
+    quote_expr!(cx, self.value | ($mask << $shift))
+  } else {
+    quote_expr!(cx, self.value & ! ($mask << $shift))
+  };
+
   if field.count.node == 1 {
     let shift = utils::shift(cx, None, field);
+    let new_value = update(shift.clone());
     quote_method!(cx,
       $doc_attr
       pub fn $fn_name<'b>(&'b mut self) -> &'b mut $setter_ty<'a> {
-        self.value |= $mask << $shift;
+        self.value = $new_value;
         self.mask |= $mask << $shift;
         self
       }
     )
   } else {
     let shift = utils::shift(cx, Some(quote_expr!(cx, idx)), field);
+    let new_value = update(shift.clone());
     quote_method!(cx,
       $doc_attr
       pub fn $fn_name<'b>(&'b mut self, idx: uint) -> &'b mut $setter_ty<'a> {
-        self.value |= $mask << $shift;
+        self.value = $new_value;
         self.mask |= $mask << $shift;
         self
       }
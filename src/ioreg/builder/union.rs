@@ -14,7 +14,6 @@
 // limitations under the License.
 
 use std::rc::Rc;
-use std::iter::FromIterator;
 use syntax::ast;
 use syntax::ptr::P;
 use syntax::ast_util::empty_generics;
@@ -89,6 +88,9 @@ impl<'a> BuildUnionTypes<'a> {
 /// within a `RegGroup` struct
 fn reg_struct_type(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
                    -> P<ast::Ty> {
+  if reg.is_strided() {
+    return cx.ty_ident(DUMMY_SP, utils::array_name(cx, path));
+  }
   let base_ty_path = cx.path_ident(DUMMY_SP, utils::path_ident(cx, path));
   let base_ty: P<ast::Ty> = cx.ty_path(base_ty_path, None);
   match reg.count.node {
@@ -100,12 +102,75 @@ fn reg_struct_type(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
   }
 }
 
+/// Build the types holding the elements of a strided register array.
+/// All but the last element are padded out to the stride, leaving the
+/// space following the last element to the registers placed there. For
+/// instance,
+///
+///     pub struct REG_Padded { reg: REG, _pad: [u8, ..12] }
+///     pub struct REG_Array { init: [REG_Padded, ..3], last: REG }
+///     impl Index<uint, REG> for REG_Array { ... }
+fn build_strided_array(cx: &ExtCtxt, path: &Vec<String>, reg: &node::Reg)
+                       -> Vec<P<ast::Item>> {
+  let reg_ty = cx.ty_ident(DUMMY_SP, utils::path_ident(cx, path));
+  let padded_ty = cx.ty_ident(DUMMY_SP, utils::padded_name(cx, path));
+  let array_ty = cx.ty_ident(DUMMY_SP, utils::array_name(cx, path));
+  let pad = utils::expr_int(cx, (reg.element_size() - reg.ty.size()) as i64);
+  let init_count = utils::expr_int(cx, (reg.count.node - 1) as i64);
+
+  let docstring = format!("Elements of register array `{}`",
+                          reg.name.node);
+  let doc_attr = utils::doc_attribute(cx, utils::intern_string(cx, docstring));
+  let padded = quote_item!(cx,
+    #[allow(non_camel_case_types, dead_code)]
+    #[doc(hidden)]
+    pub struct $padded_ty {
+      reg: $reg_ty,
+      _pad: [u8, ..$pad],
+    }
+  );
+  let array = quote_item!(cx,
+    $doc_attr
+    #[allow(non_camel_case_types)]
+    pub struct $array_ty {
+      init: [$padded_ty, ..$init_count],
+      last: $reg_ty,
+    }
+  );
+  let index = quote_item!(cx,
+    impl ::core::ops::Index<uint, $reg_ty> for $array_ty {
+      #[inline(always)]
+      fn index<'a>(&'a self, idx: &uint) -> &'a $reg_ty {
+        if *idx == $init_count {
+          &self.last
+        } else {
+          &self.init[*idx].reg
+        }
+      }
+    }
+  );
+  vec!(padded.unwrap(), array.unwrap(), index.unwrap())
+}
 
 impl<'a> node::RegVisitor for BuildUnionTypes<'a> {
+  fn visit_prim_reg<'a>(&'a mut self, path: &Vec<String>, reg: &'a node::Reg,
+                        _width: node::RegWidth, _fields: &Vec<node::Field>) {
+    if reg.is_strided() {
+      for item in build_strided_array(self.cx, path, reg).into_iter() {
+        self.builder.push_item(item);
+      }
+    }
+  }
+
   fn visit_union_reg<'a>(&'a mut self, path: &Vec<String>, reg: &'a node::Reg,
                          subregs: Rc<Vec<node::Reg>>) {
     let union_type = self.build_union_type(path, reg, &*subregs);
     self.builder.push_item(union_type);
+    if reg.is_strided() {
+      for item in build_strided_array(self.cx, path, reg).into_iter() {
+        self.builder.push_item(item);
+      }
+    }
   }
 }
 
@@ -168,10 +233,11 @@ impl<'a> BuildUnionTypes<'a> {
     // Registers are already sorted by parser
     let mut regs = regs.clone();
     let padded_regs = PaddedRegsIterator::new(&mut regs);
-    let fields =
-      padded_regs.enumerate().map(|(n,r)| self.build_pad_or_reg(path, r, n));
+    let fields: Vec<ast::StructField> =
+      padded_regs.enumerate().map(|(n,r)| self.build_pad_or_reg(path, r, n))
+      .collect();
     let struct_def = ast::StructDef {
-      fields: FromIterator::from_iter(fields),
+      fields: fields,
       ctor_id: None,
     };
     let mut attrs: Vec<ast::Attribute> = vec!(
@@ -28,6 +28,12 @@ pub fn expr_int(cx: &ExtCtxt, n: i64) -> P<ast::Expr> {
   cx.expr_lit(DUMMY_SP, ast::LitInt(n as u64, ast::UnsuffixedIntLit(sign)))
 }
 
+/// Generate an unsuffixed unsigned integer literal expression with a
+/// dummy span
+pub fn expr_uint(cx: &ExtCtxt, n: u64) -> P<ast::Expr> {
+  cx.expr_lit(DUMMY_SP, ast::LitInt(n, ast::UnsuffixedIntLit(ast::Plus)))
+}
+
 /// The name of the structure representing a register
 pub fn path_ident(cx: &ExtCtxt, path: &Vec<String>)
                       -> ast::Ident {
@@ -58,6 +64,7 @@ pub fn doc_attribute(cx: &ExtCtxt, docstring: token::InternedString)
 pub fn primitive_type_path(cx: &ExtCtxt, width: node::RegWidth)
                            -> ast::Path {
   let name = match width {
+    node::Reg64 | node::Split64 => "u64",
     node::Reg8  => "u8",
     node::Reg16 => "u16",
     node::Reg32 => "u32",
@@ -65,6 +72,18 @@ pub fn primitive_type_path(cx: &ExtCtxt, width: node::RegWidth)
   cx.path_ident(DUMMY_SP, cx.ident_of(name))
 }
 
+/// The signed counterpart of `primitive_type_path`
+pub fn signed_type_path(cx: &ExtCtxt, width: node::RegWidth)
+                        -> ast::Path {
+  let name = match width {
+    node::Reg64 | node::Split64 => "i64",
+    node::Reg8  => "i8",
+    node::Reg16 => "i16",
+    node::Reg32 => "i32",
+  };
+  cx.path_ident(DUMMY_SP, cx.ident_of(name))
+}
+
 /// The `Path` to the type corresponding to the primitive type of
 /// the given register
 pub fn reg_primitive_type_path(cx: &ExtCtxt, reg: &node::Reg)
@@ -91,7 +110,15 @@ pub fn field_type_path(cx: &ExtCtxt, path: &Vec<String>,
         _  => fail!("The impossible happened: a union register with fields"),
       }
     },
+    node::SIntField => {
+      match reg.ty {
+        node::RegPrim(width, _) => signed_type_path(cx, width),
+        _  => fail!("The impossible happened: a union register with fields"),
+      }
+    },
     node::BoolField => cx.path_ident(span, cx.ident_of("bool")),
+    node::FixedField {..} =>
+      cx.path_ident(span, cx.ident_of(float_type_name(field))),
     node::EnumField { opt_name: ref opt_name, ..} => {
       match opt_name {
         &Some(ref name) =>
@@ -106,9 +133,128 @@ pub fn field_type_path(cx: &ExtCtxt, path: &Vec<String>,
   }
 }
 
+/// The type returned by the getter of a field. This is the field type
+/// itself unless the field is an enumeration which does not cover every
+/// value, in which case it is wrapped in an `Option`.
+pub fn field_getter_type(cx: &ExtCtxt, path: &Vec<String>,
+    reg: &node::Reg, field: &node::Field) -> P<ast::Ty> {
+  let field_ty = cx.ty_path(field_type_path(cx, path, reg, field), None);
+  if field.is_exhaustive() {
+    field_ty
+  } else {
+    cx.ty_path(
+      cx.path_all(
+        field.ty.span,
+        true,
+        vec!(cx.ident_of("core"),
+             cx.ident_of("option"),
+             cx.ident_of("Option")),
+        Vec::new(),
+        vec!(field_ty)),
+      None)
+  }
+}
+
+/// The floating point type used for a fixed-point field. `f32` is used
+/// as long as its mantissa can represent every value of the field.
+pub fn float_type_name(field: &node::Field) -> &'static str {
+  if field.width <= 24 {"f32"} else {"f64"}
+}
+
+/// Generate an unsuffixed floating point literal of the scale
+/// (`2^frac_bits`) of a fixed-point field
+pub fn expr_scale(cx: &ExtCtxt, frac_bits: uint) -> P<ast::Expr> {
+  let mut scale = 1f64;
+  for _ in range(0, frac_bits) {
+    scale *= 2.0;
+  }
+  let lit = token::intern_and_get_ident(format!("{:.1}", scale).as_slice());
+  cx.expr_lit(DUMMY_SP, ast::LitFloatUnsuffixed(lit))
+}
+
+/// Given an `Expr` of a register's primitive type holding the (masked)
+/// bits of a field, sign-extend it to the signed counterpart of that type
+pub fn sign_extend(cx: &ExtCtxt, reg: &node::Reg, field: &node::Field,
+                   prim: P<ast::Expr>) -> P<ast::Expr> {
+  let width = match reg.ty {
+    node::RegPrim(width, _) => width,
+    _ => fail!("Can't sign-extend field of group register"),
+  };
+  let signed_ty = cx.ty_path(signed_type_path(cx, width), None);
+  let unused = expr_int(cx, (8 * width.size() - field.width) as i64);
+  quote_expr!(cx, (($prim << $unused) as $signed_ty) >> $unused)
+}
+
+/// The bounds of the values fitting in the given integer field, or
+/// `None` if every value of the field's type fits
+pub fn field_range(reg: &node::Reg, field: &node::Field)
+                   -> Option<(i64, i64)> {
+  let reg_bits = match reg.ty {
+    node::RegPrim(width, _) => 8 * width.size(),
+    _ => fail!("The impossible happened: a union register with fields"),
+  };
+  if field.width >= reg_bits {
+    return None;
+  }
+  match field.ty.node {
+    node::UIntField => Some((0, bit_mask(field.width) as i64)),
+    node::SIntField => {
+      let max = bit_mask(field.width - 1) as i64;
+      Some((-max - 1, max))
+    },
+    _ => None,
+  }
+}
+
+/// Build an expression which is true when `new_value` doesn't fit in
+/// the given integer field, or `None` if every value of the field's
+/// type fits
+pub fn out_of_range(cx: &ExtCtxt, reg: &node::Reg, field: &node::Field)
+                    -> Option<P<ast::Expr>> {
+  field_range(reg, field).map(|(min, max)| {
+    let max = expr_uint(cx, max as u64);
+    if min == 0 {
+      quote_expr!(cx, new_value > $max)
+    } else {
+      let min = expr_uint(cx, -min as u64);
+      quote_expr!(cx, new_value < -$min || new_value > $max)
+    }
+  })
+}
+
+/// Build the `ioreg_range` attribute of a setter of the given integer
+/// field, which the `ioreg_out_of_range` lint uses to check literal
+/// arguments at compile time
+pub fn range_attribute(cx: &ExtCtxt, reg: &node::Reg, field: &node::Field)
+                       -> Vec<ast::Attribute> {
+  match field_range(reg, field) {
+    Some((min, max)) => {
+      let bound = |name: &'static str, value: i64| {
+        let s = ast::LitStr(intern_string(cx, format!("{}", value)),
+                            ast::CookedStr);
+        cx.meta_name_value(DUMMY_SP, token::InternedString::new(name), s)
+      };
+      let attr = cx.meta_list(DUMMY_SP,
+                              token::InternedString::new("ioreg_range"),
+                              vec!(bound("min", min), bound("max", max)));
+      vec!(cx.attribute(DUMMY_SP, attr))
+    },
+    None => Vec::new(),
+  }
+}
+
 /// Build an expression for the mask of a field
 pub fn mask(cx: &ExtCtxt, field: &node::Field) -> P<ast::Expr> {
-  expr_int(cx, ((1i << field.width) - 1) as i64)
+  expr_uint(cx, bit_mask(field.width))
+}
+
+/// A mask of the given number of low-order bits
+pub fn bit_mask(width: uint) -> u64 {
+  if width >= 64 {
+    !0
+  } else {
+    (1u64 << width) - 1
+  }
 }
 
 /// Build an expression for the shift of a field (including the array
@@ -139,6 +285,83 @@ pub fn getter_name(cx: &ExtCtxt, path: &Vec<String>) -> ast::Ident {
   path_ident(cx, &s)
 }
 
+/// The name of the type holding the two words of a split register
+pub fn words_name(cx: &ExtCtxt, path: &Vec<String>) -> ast::Ident {
+  let mut s = path.clone();
+  s.push("Words".to_string());
+  path_ident(cx, &s)
+}
+
+/// The name of the type holding the elements of a strided register array
+pub fn array_name(cx: &ExtCtxt, path: &Vec<String>) -> ast::Ident {
+  let mut s = path.clone();
+  s.push("Array".to_string());
+  path_ident(cx, &s)
+}
+
+/// The name of the type of an element of a strided register array
+/// padded out to the stride
+pub fn padded_name(cx: &ExtCtxt, path: &Vec<String>) -> ast::Ident {
+  let mut s = path.clone();
+  s.push("Padded".to_string());
+  path_ident(cx, &s)
+}
+
+/// The name of the constant holding the reset value of a register
+pub fn reset_name(cx: &ExtCtxt, path: &Vec<String>) -> ast::Ident {
+  let mut s = path.clone();
+  s.push("RESET".to_string());
+  path_ident(cx, &s)
+}
+
+/// The name of the constant holding the offset of a register within its
+/// group
+pub fn offset_name(cx: &ExtCtxt, path: &Vec<String>) -> ast::Ident {
+  let mut s = path.clone();
+  s.push("OFFSET".to_string());
+  path_ident(cx, &s)
+}
+
 pub fn intern_string(cx: &ExtCtxt, s: String) -> token::InternedString {
   token::get_ident(cx.ident_of(s.as_slice()))
 }
+
+/// Build an expression storing a value to a register, given an
+/// expression referencing the register struct. For registers with a
+/// write sequence the key register is written before (resp. after) the
+/// register with interrupts masked by a `NoInterrupts` critical section
+/// (the one of `cortex_m3`, which the M4 shares). Unlock sequences are
+/// followed by a read of the key register.
+pub fn store(cx: &ExtCtxt, reg: &node::Reg, target: P<ast::Expr>,
+             value: P<ast::Expr>) -> P<ast::Expr> {
+  let seq = match reg.sequence {
+    Some(ref seq) => seq,
+    None => return quote_expr!(cx, $target.value.set($value)),
+  };
+  let key_ty = cx.ty_path(primitive_type_path(cx, seq.key_width), None);
+  let delta = expr_int(cx, seq.key_offset as i64 - reg.offset as i64);
+  let mut keys: Vec<P<ast::Stmt>> = seq.values.iter().map(|v| {
+    let v = expr_uint(cx, v.node as u64);
+    cx.stmt_expr(quote_expr!(cx, (*key).set($v)))
+  }).collect();
+  if seq.kind == node::Unlock {
+    // reading the key register back waits for the unlock to complete
+    keys.push(cx.stmt_expr(quote_expr!(cx, (*key).get())));
+  }
+  let keys = cx.expr_block(cx.block(DUMMY_SP, keys, None));
+  let (before, after) = match seq.kind {
+    node::Unlock => (keys, quote_expr!(cx, ())),
+    node::Feed => (quote_expr!(cx, ()), keys),
+  };
+  quote_expr!(cx, {
+    let value = $value;
+    let key = ($target.addr() as int + $delta)
+      as *const ::zinc::util::volatile_cell::VolatileCell<$key_ty>;
+    let _crit = ::zinc::hal::cortex_m3::irq::NoInterrupts::new();
+    unsafe {
+      $before;
+      $target.value.set(value);
+      $after;
+    }
+  })
+}
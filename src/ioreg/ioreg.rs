@@ -178,6 +178,57 @@ address        register
 0x30           sr
 ```
 
+Elements of register and group arrays are packed contiguously unless
+a `stride` is given, in which case each element is padded out to the
+given number of bytes. For instance, were the channels above placed
+every `0x10` bytes,
+
+```
+ioregs!(DMA = {
+    0x0    => reg32 cr { ... }
+    0x10   => group channel[4] @ stride=0x10 {
+        0x0    => reg32 cr { ... }
+        0x4    => reg32 sr { ... }
+    }
+    0x50   => reg32 sr { ... }
+})
+```
+
+Elements are accessed by indexing the array (e.g. `channel[2].sr`).
+The padding following the last element isn't part of the array, so
+other registers can be placed there (e.g. `sr` could be at `0x48`
+rather than `0x50` above). It is a compile-time error for the stride to
+be smaller than an element or for arrays to overlap neighbouring
+registers.
+
+#### Wide registers
+
+Peripherals on 32-bit buses often expose 64-bit quantities (e.g.
+counters and unique identifiers) as a pair of consecutive 32-bit
+registers. These can be declared as a single `split64` register whose
+fields may span both words,
+
+```
+ioregs!(TIMER = {
+    0x10   => split64 cnt {
+        0..63  => count,
+    }
+})
+```
+
+The low word is at the register's offset and the high word follows it.
+Reads fetch the high word, the low word, and the high word again,
+retrying should the high word have changed in between, so that the
+value is consistent even if the low word overflows during the read.
+Writes store the low word and then the high word. Registers which are
+natively 64 bits wide are declared with `reg64` instead.
+
+Registers must be placed at offsets which are a multiple of their
+width (four bytes for `split64`, eight for `reg64`), and groups at a
+multiple of the alignment of their widest register. A misaligned
+offset is a compile-time error, as the register would otherwise be
+moved to the next aligned offset along with everything following it.
+
 ### What is produced
 
 The `ioregs!` extension produces a variety of types and methods for
@@ -202,12 +253,65 @@ pub struct UART {
 
 The `UART` struct is the the "entry-point" into the interface and is
 ultimately what will be instantiated to represent the peripheral's
-register window, typically as a `static extern` item,
+register window. Instances at fixed addresses can be declared after the
+register block,
 
 ```
-extern { pub static UART: UART; }
+ioregs!(UART = {
+    ...
+} UART0 @ 0x4000_C000, UART1 @ 0x4001_0000)
+```
+
+producing an accessor function for each instance,
+
+```
+pub fn UART0() -> &'static UART { ... }
 ```
 
+It is a compile-time error for instances of the same block to overlap.
+
+Some instances of a peripheral carry registers the others lack. These
+can be given in a block following the instance's address,
+
+```
+ioregs!(UART = {
+    ...
+} UART0 @ 0x4000_C000, UART1 @ 0x4001_0000 {
+    0x10 => reg32 mcr { ... }
+})
+```
+
+The accessor of such an instance returns a type of its own, named
+after the block and the instance, which dereferences to the register
+block,
+
+```
+pub struct UART_UART1 {
+    pub mcr: UART_UART1_mcr,
+}
+impl Deref<UART> for UART_UART1 { ... }
+
+pub fn UART1() -> &'static UART_UART1 { ... }
+```
+
+so that `UART1().cr` and `UART1().mcr` are both available while code
+expecting a `&UART` can be passed `&**UART1()`. The registers of an
+instance must not overlap with those of the block.
+
+Registers shared by several instances are given once, after the
+instances in parentheses,
+
+```
+ioregs!(UART = {
+    ...
+} (UART0 @ 0x4000_C000, UART2 @ 0x4009_8000) {
+    0x24 => reg32 icr { ... }
+})
+```
+
+and the accessors of these instances return the same type, named after
+the block and all of the instances (here `UART_UART0_UART2`).
+
 The register structs (`UART_cr`, `UART_sr`, and `UART_dr`)
 have no user visible members but expose a variety of methods. Let's
 look at `cr` in particular,
@@ -225,12 +329,17 @@ impl UART_cr {
     pub fn br(&self) -> u32 { ... }
 
     pub fn set_parity(&self, new_value: UART_cr_parity) -> UART_cr_Update { ... }
-    pub fn parity(&self) -> UART_cr_parity { ... }
+    pub fn parity(&self) -> Option<UART_cr_parity> { ... }
 }
 ```
 
 Here we see each field has a corresponding "get" function (e.g. `rxe`,
-`br`, and `parity`) as well as a "set" function. Note that the set
+`br`, and `parity`) as well as a "set" function. As the `parity`
+enumeration has no variant for the value `0x1`, its getter returns
+`None` when the hardware reports a value without a variant. Getters of
+enumerations covering every value of their field return the
+enumeration directly. It is a compile-time error for a variant's value
+not to fit in its field. Note that the set
 function returns a `UART_cr_Update` object. This object mirrors the
 setter methods of `UART_cr`, collecting multiple field updates within
 a register, performing them on destruction with the `Drop` trait,
@@ -251,7 +360,7 @@ chained together. For instance, we can update the `rxe` and `txe`
 fields of the `cr` register atomically,
 
 ```
-UART.cr.set_rxe(true).set_txe(false);
+UART0().cr.set_rxe(true).set_txe(false);
 ```
 
 In addition to get and set methods, `UART_cr` also implements a `get`
@@ -260,20 +369,171 @@ of `UART_cr`. This object captures the state of the register allowing
 field values to be later atomically queried,
 
 ```
-let cr: UART_cr_Get = UART.cr.get();
+let cr: UART_cr_Get = UART0().cr.get();
 format!("txe={}, rxe={}, br={}", cr.txe(), cr.rxe(), cr.br())
 ```
 
+Each writable register also has a `write` method returning an update
+object which, rather than performing a read-modify-write, starts from
+the register's reset value (or zero if none was given) and writes the
+register exactly once when dropped. This is useful for registers where
+reading has side effects or where a known starting state is wanted,
+
+```
+UART0().cr.write().set_rxe(true).set_br(42);
+```
+
+Registers declared with a reset value (e.g. `0x0 => reg32 cr @
+reset=0x40 { ... }`) additionally get a `UART_cr_RESET` constant holding
+the value and a `reset` method restoring it.
+
+Values passed to the setters of integer fields narrower than their
+register are range checked. An integer literal which doesn't fit is
+rejected at compile time by the `ioreg_out_of_range` lint, e.g.
+`UART0().cr.set_br(0x200)` for the 9-bit `br` field. At runtime, in
+debug builds (built with `--cfg cfg_debug`, which rake passes when
+`DEBUG` is set), a value which doesn't fit calls
+`zinc::util::support::abort`, halting in the debugger. Otherwise it is
+masked to the width of the field. Such fields also get a `try_set` method leaving the
+field untouched and returning the value as an error if it doesn't fit,
+
+```
+match UART0().cr.try_set_br(divisor) {
+  Ok(_) => {},
+  Err(divisor) => ...,
+}
+```
+
+Independently of setter arguments, the definitions themselves are
+validated when the macro is expanded: reset values must fit in their
+register and enumeration values in their field.
+
 In the case of read-only (resp. write-only) fields the set (resp. get)
-method is omitted. In the case of `set_to_clear` fields a `clear`
-method is instead produced in place of `set`. For instance, in the
-case of the `sr` register's `fe` flag,
+method is omitted. In the case of `set_to_clear` and `w0c` fields a
+`clear` method is instead produced in place of `set`, `w1s` fields
+get a `set` method taking no value and `rc` fields only have a get
+method. For instance, in the case of the `sr` register's `fe` flag,
 
 ```
 pub fn fe(self: &UART_sr_Getter) -> bool { ... }
 pub fn clear_fe(self: &UART_sr_Update) -> UART_sr_Update { ... }
 ```
 
+Fixed-point fields are read and written as `f32` (or `f64` for
+fields wider than 24 bits). For instance, a temperature sensor
+reporting degrees in a signed 12-bit field with four fractional bits,
+
+```
+0x0 => reg16 temp {
+  0..11 => value: ro i fixed(4),
+}
+```
+
+gives `value()` returning `-1.5` when the field holds `0xfe8`. Setters
+of fixed-point fields round to the nearest representable value and
+saturate values outside of the field's range.
+
+Blocks declared with the `#[bitband]` option, e.g. `ioregs!(#[bitband]
+UART = { ... } UART0 @ 0x4000_C000)`, additionally get `atomic_set_` and
+`atomic_clear_` methods for their single-bit read/write fields on
+architectures with bit-banding (those listing `arch_has_bitband` among
+their features in `architectures.yml`). These write to the field's
+alias in the bit-band region, which the hardware turns into an
+uninterruptible read-modify-write, so they can be used from interrupt
+handlers without masking interrupts,
+
+```
+UART0().cr.atomic_set_rxe();
+```
+
+Only the first megabyte of SRAM (`0x2000_0000` to `0x200F_FFFF`) and of
+the peripherals (`0x4000_0000` to `0x400F_FFFF`) have a bit-band alias,
+so the option requires every instance of the block to lie entirely
+within one of these regions and is rejected otherwise (e.g. for the
+system control space at `0xE000_E000`). As the hardware rewrites the
+whole register, the methods are not produced for registers having
+fields with side effects (`wo`, `set_to_clear`, `w0c`, `rc` and `w1s`).
+
+Some registers are protected by a sequence of writes to a key
+register, either unlocking them (e.g. watchdog configuration) or
+committing changes to them (e.g. the feed sequence of a PLL). The
+sequence is given as an attribute naming the key register, which must
+be a single register of the same group, and the values written to it,
+
+```
+0x0 => reg16 stctrlh @ unlock=unlock(0xc520, 0xd928) { ... }
+0xe => reg16 unlock { 0..15 => key: wo }
+0x84 => reg32 pll0cfg @ feed=pll0feed(0xaa, 0x55) { ... }
+0x8c => reg32 pll0feed { 0..7 => key: wo }
+```
+
+Every write to a protected register (through its updates, `reset` or
+`raw_set`) then writes the `unlock` values to the key register before
+(resp. the `feed` values after) the register itself, with interrupts
+masked so that nothing comes between the writes. The key register is
+read back after an unlock sequence, giving the unlock time to take
+effect before the register is written. Interrupts are masked with a
+`zinc::hal::cortex_m3::irq::NoInterrupts` critical section, which the
+Cortex-M4 shares. Protected registers don't get bit-band accessors.
+
+Every register and group has an `addr` method returning its absolute
+address, e.g. to point a DMA channel at a data register, and the
+offset of each register and group within its group is available as a
+constant,
+
+```
+pub fn addr(&self) -> uint { ... }
+pub const UART_dr_OFFSET: uint = 0x8;
+```
+
+Finally `raw_get` and `raw_set` read and write the whole value of a
+primitive register, ignoring its fields and their access modes. These
+are an escape hatch for registers whose definition doesn't (yet)
+describe their use; prefer the field accessors where possible. As
+these methods share a namespace with field getters, fields can't be
+named `get`, `new`, `write`, `reset`, `addr`, `raw_get`, `raw_set` or
+`dump`. The SVD importer appends an underscore to such field names.
+
+### Dumping registers
+
+Blocks declared with the `#[dump]` option, e.g. `ioregs!(#[dump] UART
+= { ... })`, additionally get methods writing register values to a
+`zinc::drivers::chario::CharIO`, which must be in scope where the
+block is defined. Each `_Get` type gets a `dump` method writing the
+name and value of each readable field on a single line,
+
+```
+UART0().cr.get().dump(&uart);
+// rxe=true txe=false rxie=false txie=false br=0x2a parity=EvenParity
+```
+
+and the block itself (along with any of its groups) a `dump` method
+writing each register on its own line. Registers with `rc` fields are
+skipped as reading them would clear their flags. Integer fields are
+written in hexadecimal, signed ones in decimal, and fixed-point fields
+as their raw value followed by their scale (e.g. `0xfe8/2^4`). As
+nothing is generated for blocks without the option, firmware not using
+it isn't burdened with the field names.
+
+### Testing
+
+Generated register structs are built upon
+`zinc::util::volatile_cell::VolatileCell`. In test builds every access
+to a `VolatileCell` can be redirected to an in-memory register file by
+creating a `volatile_cell::mock::MockRegisters`, which records each
+read and write along with its offset from a given base address and
+allows the values returned by reads to be scripted,
+
+```
+let regs = MockRegisters::new(0x4000_C000);
+regs.script_read(0x0, 0x2);
+UART0().cr.set_rxe(true);
+assert_eq!(regs.log(), vec!(Read(0x0, 0x2), Write(0x0, 0x3)));
+```
+
+Read-to-clear flags can be emulated with `clear_on_read`, which makes
+reads of a register clear the given bits of its stored value.
+
 ### Informal grammar
 
 In the below discussion `THING, ...` will denote a list of one or more
@@ -284,29 +544,41 @@ brackets.
 The `ioregs!` macro expects a definition of the form,
 
 ```
-ioregs!(IDENT = { REG, ... })
+ioregs!(⟦#[OPTION] ...⟧ IDENT = { REG, ... } ⟦INSTANCE, ...⟧)
 ```
 
+where an `INSTANCE` is given by `IDENT @ ADDRESS ⟦{ REG, ... }⟧` or
+`(IDENT @ ADDRESS, ...) { REG, ... }` and
+an `OPTION` is either `dump` or `bitband`.
+
 Where a `REG` is either a register group,
 
 ```notrust
-OFFSET => group IDENT⟦[COUNT]⟧ { REG, ... }
+OFFSET => group IDENT⟦[COUNT]⟧ ⟦@ ATTRIBUTE, ...⟧ { REG, ... }
 ```
 
 or a primitive register,
 
 ```notrust
-OFFSET => TYPE IDENT⟦[COUNT]⟧ { FIELD, ... }
+OFFSET => TYPE IDENT⟦[COUNT]⟧ ⟦@ ATTRIBUTE, ...⟧ { FIELD, ... }
 ```
 
 `COUNT` is an integer count and a register `TYPE` is one of `reg8` (a
-one byte wide register), `reg16` (two bytes wide), or `reg32` (four
-bytes wide).
+one byte wide register), `reg16` (two bytes wide), `reg32` (four
+bytes wide), `reg64` (eight bytes wide), or `split64` (two consecutive
+four byte wide registers, see below).
+
+An `ATTRIBUTE` takes the form `NAME=VALUE`. The attributes are
+`reset=N`, giving the value of a primitive register after reset,
+`stride=N`, giving the distance in bytes between the elements of a
+register or group array, and `unlock=IDENT(N, ...)` or
+`feed=IDENT(N, ...)`, giving the write sequence of a primitive
+register (see below).
 
 A field is given by
 
 ```notrust
-BITS => IDENT⟦[COUNT]⟧ ⟦: MODIFIER⟧ ⟦{ VALUE, ... }⟧
+BITS => IDENT⟦[COUNT]⟧ ⟦: MODIFIER ...⟧ ⟦{ VALUE, ... }⟧
 ```
 
 where `BITS` is either an inclusive range of integers (`N..M`) or a
@@ -315,9 +587,22 @@ the field is of an enumerated type. Otherwise single bit fields are
 of type `bool` and wider fields unsigned integers (in particular, of
 the same width as the containing register).
 
-A `MODIFIER` is one of `rw` (read/write), `ro` (read-only), `wo`
-(write-only), or `set_to_clear` (a flag which can be cleared by
-setting to one).
+The modifiers are separated by spaces. At most one of them is an
+access type, one of `rw` (read/write), `ro` (read-only), `wo`
+(write-only), `set_to_clear` or `w1c` (a flag which can be cleared by
+setting to one), `w0c` (a flag which can be cleared by writing zero),
+`rc` (a flag which is cleared by reading the register), or `w1s` (a
+flag which can be set by writing one). The others change the type of
+a field without a list of values: `i` makes it a two's complement
+signed integer (of the signed type of the same width as the containing
+register) and `fixed(N)` a fixed-point number with `N` fractional
+bits, signed if `i` is also given.
+
+Updates never disturb flags other than the ones being modified: bits
+of `set_to_clear` and `w1s` flags are written as zero and those of
+`w0c` flags as one unless explicitly cleared. Note that as field
+updates read the register, updating a register containing `rc` flags
+will clear them; use `write` to avoid this.
 
 A `VALUE` is given by,
 
@@ -325,6 +610,41 @@ A `VALUE` is given by,
 N => NAME
 ```
 
+### Importing CMSIS-SVD descriptions
+
+Rather than typing definitions by hand they can be generated from the
+CMSIS-SVD files published by most vendors. The `svd` module reads such
+a file into `node::Reg` groups and `printer::print_ioregs` turns these
+back into `ioregs!` source. The `svd2ioregs` tool wraps both,
+
+```notrust
+svd2ioregs LPC176x5x.svd UART0 SSP0
+```
+
+prints definitions for the named peripherals (or all of them if none
+are given). The output is intended as a starting point; names and
+enumerations usually deserve a review before being checked in.
+
+### Exporting register maps
+
+Going the other way, parsed definitions can be exported for use by
+other tools (e.g. the peripheral view of a debugger).
+`json::to_json` describes a register group and its instances as a JSON
+object giving the offset, width, reset value and fields of each
+register, and the bit range, access mode, type, values and
+documentation of each field. `svd::export::export` produces a
+CMSIS-SVD document with a peripheral for each instance. SVD has no
+64-bit registers made of two words, so a `split64` register `cnt` is
+exported as the 32-bit registers `cnt_LO` and `cnt_HI`. Fields
+crossing from one word to the other are cut in two the same way. The
+`ioregs2svd` tool runs these over the `ioregs!` invocations of Rust
+source files (along with the modules they load),
+
+```notrust
+ioregs2svd --device K20 src/zinc/hal/k20/sim.rs src/zinc/hal/k20/pin.rs
+ioregs2svd --json src/zinc/hal/k20/uart.rs
+```
+
 */
 
 #![feature(quote, struct_variant)]
@@ -337,3 +657,8 @@ extern crate serialize;
 pub mod node;
 pub mod parser;
 pub mod builder;
+pub mod printer;
+pub mod svd;
+pub mod json;
+
+#[cfg(test)] mod parser_test;
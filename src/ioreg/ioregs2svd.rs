@@ -0,0 +1,126 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Export the `ioregs!` definitions of Rust source files as register maps.
+//!
+//! Usage: `ioregs2svd [--json] [--device NAME] FILE.rs...`
+//!
+//! Every `ioregs!` invocation found in the given files (and the modules
+//! they load) is printed to stdout, as a CMSIS-SVD document by default
+//! or as a JSON list of register groups with `--json`.
+
+#![crate_name="ioregs2svd"]
+
+extern crate syntax;
+extern crate serialize;
+extern crate ioreg;
+
+use std::io;
+use std::os;
+use std::rc::Rc;
+use serialize::json;
+use syntax::ast;
+use syntax::parse;
+use syntax::parse::token;
+use syntax::visit;
+use syntax::visit::Visitor;
+
+use ioreg::node;
+use ioreg::parser::Parser;
+use ioreg::svd::export;
+
+/// Collects the token trees of `ioregs!` invocations
+struct FindIoregs {
+  invocations: Vec<Vec<ast::TokenTree>>,
+}
+
+impl<'v> Visitor<'v> for FindIoregs {
+  fn visit_mac(&mut self, mac: &'v ast::Mac) {
+    match mac.node {
+      ast::MacInvocTT(ref path, ref tts, _) => {
+        let is_ioregs = path.segments.len() == 1 &&
+          token::get_ident(path.segments[0].identifier).get() == "ioregs";
+        if is_ioregs {
+          self.invocations.push(tts.clone());
+        }
+      },
+    }
+  }
+}
+
+fn usage(program: &str) {
+  let _ = writeln!(io::stderr(),
+                   "usage: {} [--json] [--device NAME] FILE.rs...", program);
+  os::set_exit_status(1);
+}
+
+fn main() {
+  let args = os::args();
+  let mut as_json = false;
+  let mut device = "zinc".to_string();
+  let mut files: Vec<String> = Vec::new();
+  let mut iter = args.iter().skip(1);
+  loop {
+    match iter.next() {
+      Some(a) if a.as_slice() == "--json" => as_json = true,
+      Some(a) if a.as_slice() == "--device" => match iter.next() {
+        Some(name) => device = name.clone(),
+        None => return usage(args[0].as_slice()),
+      },
+      Some(a) => files.push(a.clone()),
+      None => break,
+    }
+  }
+  if files.is_empty() {
+    return usage(args[0].as_slice());
+  }
+
+  let sess = parse::new_parse_sess();
+  let mut groups: Vec<(Rc<node::Reg>, Vec<node::Instance>)> = Vec::new();
+  for file in files.iter() {
+    let krate = parse::parse_crate_from_file(&Path::new(file.as_slice()),
+                                             Vec::new(), &sess);
+    let mut finder = FindIoregs { invocations: Vec::new() };
+    visit::walk_crate(&mut finder, &krate);
+
+    for tts in finder.invocations.iter() {
+      let mut parser = Parser::from_sess(&sess, tts.as_slice());
+      let group = parser.parse_options()
+        .and_then(|_| parser.parse_ioregs());
+      let group = match group {
+        Some(group) => group,
+        None => {
+          os::set_exit_status(1);
+          continue;
+        },
+      };
+      match parser.parse_instances(group.deref()) {
+        Some(instances) => groups.push((group, instances)),
+        None => os::set_exit_status(1),
+      }
+    }
+  }
+
+  let mut out = io::stdout();
+  if as_json {
+    let list = json::List(groups.iter()
+      .map(|&(ref g, ref i)| ioreg::json::to_json(g.deref(), i))
+      .collect());
+    let _ = writeln!(out, "{}", list.to_pretty_str());
+  } else {
+    let refs = groups.iter().map(|&(ref g, ref i)| (g.deref(), i)).collect();
+    let _ = write!(out, "{}", export::export(device.as_slice(), &refs));
+  }
+}
@@ -0,0 +1,166 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! JSON register maps of register groups.
+//!
+//! Unlike the `Encodable` representation of `node::Reg` the register map
+//! is meant for consumption by other tools: spans are dropped, widths are
+//! given in bits and access modes and field types are spelled out.
+
+use std::collections::TreeMap;
+use serialize::json;
+use serialize::json::Json;
+use syntax::ast;
+use syntax::codemap::Spanned;
+use syntax::parse::token;
+
+use node;
+
+/// Describe a register group and its instances as a JSON object
+pub fn to_json(reg: &node::Reg, instances: &Vec<node::Instance>) -> Json {
+  let mut obj = TreeMap::new();
+  obj.insert("name".to_string(), json::String(reg.name.node.clone()));
+  obj.insert("description".to_string(), doc(&reg.docstring));
+  obj.insert("size".to_string(), json::U64(reg.size() as u64));
+  obj.insert("instances".to_string(), json::List(
+    instances.iter().map(|i| {
+      let mut obj = TreeMap::new();
+      obj.insert("name".to_string(), json::String(i.name.node.clone()));
+      obj.insert("address".to_string(), json::U64(i.address.node as u64));
+      match i.extension {
+        Some(ref ext) => match ext.ty {
+          node::RegUnion(ref regs) =>
+            obj.insert("registers".to_string(), regs_json(regs.deref())),
+          node::RegPrim(..) => fail!("extension must be a group"),
+        },
+        None => false,
+      };
+      json::Object(obj)
+    }).collect()));
+  match reg.ty {
+    node::RegUnion(ref regs) =>
+      obj.insert("registers".to_string(), regs_json(regs.deref())),
+    node::RegPrim(..) => fail!("top-level register must be a group"),
+  };
+  json::Object(obj)
+}
+
+fn doc(docstring: &Option<Spanned<ast::Ident>>) -> Json {
+  match *docstring {
+    Some(ref d) => json::String(token::get_ident(d.node).get().to_string()),
+    None => json::Null,
+  }
+}
+
+fn regs_json(regs: &Vec<node::Reg>) -> Json {
+  let mut regs: Vec<&node::Reg> = regs.iter().collect();
+  regs.sort_by(|r1, r2| r1.offset.cmp(&r2.offset));
+  json::List(regs.iter().map(|r| reg_json(*r)).collect())
+}
+
+fn reg_json(reg: &node::Reg) -> Json {
+  let mut obj = TreeMap::new();
+  obj.insert("name".to_string(), json::String(reg.name.node.clone()));
+  obj.insert("description".to_string(), doc(&reg.docstring));
+  obj.insert("offset".to_string(), json::U64(reg.offset as u64));
+  obj.insert("count".to_string(), json::U64(reg.count.node as u64));
+  obj.insert("stride".to_string(), json::U64(reg.element_size() as u64));
+  match reg.ty {
+    node::RegPrim(width, ref fields) => {
+      let ty = match width {
+        node::Reg64   => "reg64",
+        node::Split64 => "split64",
+        node::Reg32   => "reg32",
+        node::Reg16   => "reg16",
+        node::Reg8    => "reg8",
+      };
+      obj.insert("type".to_string(), json::String(ty.to_string()));
+      obj.insert("width".to_string(), json::U64(8 * width.size() as u64));
+      obj.insert("reset".to_string(), match reg.reset {
+        Some(ref reset) => json::U64(reset.node as u64),
+        None => json::Null,
+      });
+      obj.insert("sequence".to_string(), match reg.sequence {
+        Some(ref seq) => {
+          let mut obj = TreeMap::new();
+          let kind = match seq.kind {
+            node::Unlock => "unlock",
+            node::Feed => "feed",
+          };
+          obj.insert("kind".to_string(), json::String(kind.to_string()));
+          obj.insert("key".to_string(), json::String(seq.key.node.clone()));
+          obj.insert("values".to_string(), json::List(
+            seq.values.iter().map(|v| json::U64(v.node as u64)).collect()));
+          json::Object(obj)
+        },
+        None => json::Null,
+      });
+      obj.insert("fields".to_string(), json::List(
+        fields.iter().map(field_json).collect()));
+    },
+    node::RegUnion(ref regs) => {
+      obj.insert("type".to_string(), json::String("group".to_string()));
+      obj.insert("registers".to_string(), regs_json(regs.deref()));
+    },
+  }
+  json::Object(obj)
+}
+
+/// The name of an access mode as used in `ioregs!` definitions
+pub fn access_name(access: node::Access) -> &'static str {
+  match access {
+    node::ReadWrite        => "rw",
+    node::ReadOnly         => "ro",
+    node::WriteOnly        => "wo",
+    node::SetToClear       => "set_to_clear",
+    node::WriteZeroToClear => "w0c",
+    node::ReadToClear      => "rc",
+    node::WriteOneToSet    => "w1s",
+  }
+}
+
+fn field_json(field: &node::Field) -> Json {
+  let mut obj = TreeMap::new();
+  obj.insert("name".to_string(), json::String(field.name.node.clone()));
+  obj.insert("description".to_string(), doc(&field.docstring));
+  obj.insert("low_bit".to_string(), json::U64(field.low_bit as u64));
+  obj.insert("width".to_string(), json::U64(field.width as u64));
+  obj.insert("count".to_string(), json::U64(field.count.node as u64));
+  obj.insert("access".to_string(),
+             json::String(access_name(field.access).to_string()));
+  let ty = match field.ty.node {
+    node::UIntField => "uint",
+    node::SIntField => "sint",
+    node::BoolField => "bool",
+    node::FixedField { signed: signed, frac_bits: frac_bits } => {
+      obj.insert("signed".to_string(), json::Boolean(signed));
+      obj.insert("frac_bits".to_string(), json::U64(frac_bits as u64));
+      "fixed"
+    },
+    node::EnumField { ref variants, .. } => {
+      obj.insert("values".to_string(), json::List(
+        variants.iter().map(|v| {
+          let mut obj = TreeMap::new();
+          obj.insert("name".to_string(), json::String(v.name.node.clone()));
+          obj.insert("value".to_string(), json::U64(v.value.node as u64));
+          obj.insert("description".to_string(), doc(&v.docstring));
+          json::Object(obj)
+        }).collect()));
+      "enum"
+    },
+  };
+  obj.insert("type".to_string(), json::String(ty.to_string()));
+  json::Object(obj)
+}
@@ -31,8 +31,15 @@ pub struct Variant {
 pub enum FieldType {
   /// A unsigned integer
   UIntField,
+  /// A signed (two's complement) integer
+  SIntField,
   /// A boolean flag
   BoolField,
+  /// A fixed-point number with the given number of fractional bits
+  FixedField {
+    pub signed: bool,
+    pub frac_bits: uint,
+  },
   /// A enum
   EnumField {
     pub opt_name: Option<String>,
@@ -45,8 +52,30 @@ pub enum Access {
   ReadWrite,
   ReadOnly,
   WriteOnly,
-  /// A flag which can be set to clear
+  /// A flag which can be set to clear (writing zero has no effect)
   SetToClear,
+  /// A flag which is cleared by writing zero (writing one has no effect)
+  WriteZeroToClear,
+  /// A flag which is cleared when read
+  ReadToClear,
+  /// A flag which can be set by writing one (writing zero has no effect)
+  WriteOneToSet,
+}
+
+impl Access {
+  /// Whether a field with this access mode has a getter
+  pub fn is_readable(&self) -> bool {
+    *self != WriteOnly
+  }
+
+  /// Whether a field with this access mode can be modified through an
+  /// update
+  pub fn is_writable(&self) -> bool {
+    match *self {
+      ReadOnly | ReadToClear => false,
+      _ => true,
+    }
+  }
 }
 
 #[deriving(Clone, Decodable, Encodable)]
@@ -69,10 +98,30 @@ impl Field {
   pub fn high_bit(&self) -> uint {
     self.low_bit + self.width * self.count.node - 1
   }
+
+  /// Whether every bit pattern of the field has a meaning. This is only
+  /// false for enumerations lacking variants for some values
+  pub fn is_exhaustive(&self) -> bool {
+    match self.ty.node {
+      EnumField { ref variants, .. } => {
+        let mut values: Vec<uint> =
+          variants.iter().map(|v| v.value.node).collect();
+        values.sort();
+        values.dedup();
+        self.width < 64 && values.len() as u64 == 1u64 << self.width
+      },
+      _ => true,
+    }
+  }
 }
 
 #[deriving(Clone, Decodable, Encodable)]
 pub enum RegWidth {
+  /// A 64-bit wide register
+  Reg64,
+  /// A 64-bit wide register made up of two consecutive 32-bit
+  /// registers, the low word first
+  Split64,
   /// A 32-bit wide register
   Reg32,
   /// A 16-bit wide register
@@ -85,9 +134,11 @@ impl RegWidth {
   /// Size of register type in bytes
   pub fn size(&self) -> uint {
     match *self {
-      Reg32 => 4,
-      Reg16 => 2,
-      Reg8  => 1,
+      Reg64   => 8,
+      Split64 => 8,
+      Reg32   => 4,
+      Reg16   => 2,
+      Reg8    => 1,
     }
   }
 }
@@ -108,6 +159,43 @@ impl RegType {
       &RegUnion(ref regs) => regs_size(regs.deref()),
     }
   }
+
+  /// Alignment of register type in bytes
+  pub fn align(&self) -> uint {
+    match self {
+      &RegPrim(Split64, _) => 4,
+      &RegPrim(width, _)   => width.size(),
+      &RegUnion(ref regs)  =>
+        regs.iter().map(|r| r.ty.align()).max().unwrap_or(1),
+    }
+  }
+}
+
+/// Whether the writes of a sequence precede or follow the write of the
+/// register it protects
+#[deriving(Clone, PartialEq, Decodable, Encodable)]
+pub enum SequenceKind {
+  /// The key register must be written before the protected register
+  Unlock,
+  /// The key register must be written after the protected register for
+  /// the change to take effect
+  Feed,
+}
+
+/// A sequence of writes to a key register guarding writes to another
+/// register, e.g. a watchdog unlock or a PLL feed sequence
+#[deriving(Clone, Decodable, Encodable)]
+pub struct WriteSequence {
+  pub kind: SequenceKind,
+  /// The name of the key register, which must be a sibling of the
+  /// protected register
+  pub key: Spanned<String>,
+  /// The values written to the key register, in order
+  pub values: Vec<Spanned<uint>>,
+  /// The offset and width of the key register, filled in once the
+  /// registers of the group are known
+  pub key_offset: uint,
+  pub key_width: RegWidth,
 }
 
 /// A single register, either a union or primitive
@@ -118,12 +206,33 @@ pub struct Reg {
   pub ty: RegType,
   pub count: Spanned<uint>,
   pub docstring: Option<Spanned<ast::Ident>>,
+  /// The value of a primitive register after reset, if known
+  pub reset: Option<Spanned<uint>>,
+  /// The distance in bytes between the elements of a register array if
+  /// larger than the size of an element
+  pub stride: Option<Spanned<uint>>,
+  /// The sequence of writes protecting a primitive register, if any
+  pub sequence: Option<WriteSequence>,
 }
 
 impl Reg {
-  /// Size of a register in bytes
+  /// Size of a register in bytes. The padding following the last element
+  /// of a register array isn't owned by the register.
   pub fn size(&self) -> uint {
-    self.count.node * self.ty.size()
+    (self.count.node - 1) * self.element_size() + self.ty.size()
+  }
+  /// Whether the elements of this register array are padded out to a
+  /// stride
+  pub fn is_strided(&self) -> bool {
+    self.count.node > 1 && self.element_size() > self.ty.size()
+  }
+  /// Size of an element of a register array in bytes, including any
+  /// padding up to the stride
+  pub fn element_size(&self) -> uint {
+    match self.stride {
+      Some(ref stride) => stride.node,
+      None => self.ty.size(),
+    }
   }
   /// The offset of the last byte owned by this register
   pub fn last_byte(&self) -> uint {
@@ -131,10 +240,45 @@ impl Reg {
   }
 }
 
+/// An instance of a register block at a fixed address
+#[deriving(Clone, Decodable, Encodable)]
+pub struct Instance {
+  pub name: Spanned<String>,
+  pub address: Spanned<uint>,
+  /// Registers only present in this instance, in addition to those of
+  /// the block
+  pub extension: Option<Rc<Reg>>,
+}
+
+impl Instance {
+  /// Size of the registers of this instance in bytes, given the
+  /// register block it is an instance of
+  pub fn size(&self, block: &Reg) -> uint {
+    match self.extension {
+      Some(ref ext) => ::std::cmp::max(block.size(), ext.size()),
+      None => block.size(),
+    }
+  }
+}
+
+/// Names of the methods of register and getter types which would clash
+/// with the getter of a field of the same name
+pub static RESERVED_FIELD_NAMES: &'static [&'static str] =
+  &["get", "new", "write", "reset", "addr", "raw_get", "raw_set", "dump"];
+
+/// Options of a register block given before its name, e.g. `#[dump]`
+#[deriving(Clone, Decodable, Encodable)]
+pub struct BlockOptions {
+  /// Whether to generate methods dumping register values
+  pub dump: bool,
+  /// Whether to generate atomic bit-band accessors of single-bit fields
+  pub bitband: bool,
+}
+
 /// Size of registers of register group in bytes
 pub fn regs_size(regs: &Vec<Reg>) -> uint {
-  match regs.iter().max_by(|r| r.offset) {
-    Some(last) => last.offset + last.ty.size(),
+  match regs.iter().max_by(|r| r.last_byte()) {
+    Some(last) => last.last_byte() + 1,
     None => 0,
   }
 }
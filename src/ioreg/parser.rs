@@ -33,8 +33,26 @@ enum Scope {
   Outer,
 }
 
+/// Optional attributes given after a register's name
+struct RegAttributes {
+  reset: Option<Spanned<uint>>,
+  stride: Option<Spanned<uint>>,
+  sequence: Option<node::WriteSequence>,
+}
+
+/// Modifiers given after a field's name
+struct FieldModifiers {
+  access: Option<node::Access>,
+  signed: bool,
+  frac_bits: Option<Spanned<uint>>,
+}
+
+/// First and last addresses of the regions of SRAM and of the
+/// peripherals which have a bit-band alias
+static BITBAND_REGIONS: &'static [(uint, uint)] =
+  &[(0x2000_0000, 0x200f_ffff), (0x4000_0000, 0x400f_ffff)];
+
 pub struct Parser<'a> {
-  cx: &'a ExtCtxt<'a>,
   sess: &'a ParseSess,
   reader: Box<lexer::Reader+'a>,
   token: token::Token,
@@ -46,7 +64,12 @@ pub struct Parser<'a> {
 
 impl<'a> Parser<'a> {
   pub fn new<'a>(cx: &'a ExtCtxt<'a>, tts: &[TokenTree]) -> Parser<'a> {
-    let sess = cx.parse_sess();
+    Parser::from_sess(cx.parse_sess(), tts)
+  }
+
+  /// Create a parser outside of macro expansion, e.g. to read the
+  /// `ioregs!` invocations of a source file
+  pub fn from_sess<'a>(sess: &'a ParseSess, tts: &[TokenTree]) -> Parser<'a> {
     let ttsvec = tts.iter().map(|x| (*x).clone()).collect();
     let mut reader = box lexer::new_tt_reader(
         &sess.span_diagnostic, None, ttsvec) as Box<lexer::Reader>;
@@ -56,7 +79,6 @@ impl<'a> Parser<'a> {
     let span = tok0.sp;
 
     Parser {
-      cx: cx,
       sess: sess,
       reader: reader,
 
@@ -68,6 +90,35 @@ impl<'a> Parser<'a> {
     }
   }
 
+  /// Parse the options preceding a register block definition, e.g.
+  /// `#[dump]`
+  pub fn parse_options(&mut self) -> Option<node::BlockOptions> {
+    let mut options = node::BlockOptions { dump: false, bitband: false };
+    while self.token == token::POUND {
+      self.bump();
+      if !self.expect(&token::LBRACKET) {
+        return None;
+      }
+      let name = match self.expect_ident() {
+        Some(name) => respan(self.last_span, name),
+        None => return None,
+      };
+      match name.node.as_slice() {
+        "dump" => options.dump = true,
+        "bitband" => options.bitband = true,
+        s => {
+          self.sess.span_diagnostic.span_err(
+            name.span, format!("Unknown block option `{}`", s).as_slice());
+          return None;
+        },
+      }
+      if !self.expect(&token::RBRACKET) {
+        return None;
+      }
+    }
+    Some(options)
+  }
+
   /// Parse the ioregs from passed in tokens.
   pub fn parse_ioregs(&mut self) -> Option<Rc<node::Reg>> {
     let name = match self.expect_ident() {
@@ -97,11 +148,204 @@ impl<'a> Parser<'a> {
       ty: node::RegUnion(Rc::new(regs)),
       count: respan(mk_sp(sp_lo, self.span.hi), 1),
       docstring: docstring,
+      reset: None,
+      stride: None,
+      sequence: None,
     };
 
     Some(Rc::new(group))
   }
 
+  /// Parse the instances following a register block definition, e.g.
+  /// `UART0 @ 0x4000_C000, UART1 @ 0x4001_0000 { REG, ... }`. Instances
+  /// sharing registers of their own are given in parentheses, e.g.
+  /// `(UART0 @ 0x4000_C000, UART2 @ 0x4009_8000) { REG, ... }`
+  pub fn parse_instances(&mut self, group: &node::Reg)
+                         -> Option<Vec<node::Instance>> {
+    let mut instances: Vec<node::Instance> = Vec::new();
+    while self.token != token::EOF {
+      let shared = self.token == token::LPAREN;
+      if shared {
+        self.bump();
+      }
+      let mut decls: Vec<(Spanned<String>, Spanned<uint>)> = Vec::new();
+      loop {
+        match self.parse_instance_decl() {
+          Some(decl) => decls.push(decl),
+          None => return None,
+        }
+        if !shared || self.token == token::RPAREN {
+          break;
+        }
+        if !self.expect(&token::COMMA) {
+          return None;
+        }
+      }
+      if shared {
+        if !self.expect(&token::RPAREN) {
+          return None;
+        }
+        if self.token != token::LBRACE {
+          self.error(format!("expected `{{` but found `{}`",
+                             token::to_string(&self.token)));
+          return None;
+        }
+      }
+
+      let extension = match self.token {
+        token::LBRACE => {
+          // the comma following the block is eaten by `parse_regs`
+          let names: Vec<Spanned<String>> =
+            decls.iter().map(|&(ref name, _)| name.clone()).collect();
+          match self.parse_extension(group, &names) {
+            Some(ext) => Some(Rc::new(ext)),
+            None => return None,
+          }
+        },
+        token::COMMA => {
+          self.bump();
+          None
+        },
+        token::EOF => None,
+        _ => {
+          self.error(format!("expected `,` but found `{}`",
+                             token::to_string(&self.token)));
+          return None;
+        },
+      };
+      for (name, address) in decls.into_iter() {
+        instances.push(node::Instance {
+          name: name,
+          address: address,
+          extension: extension.clone(),
+        });
+      }
+    }
+
+    // Verify that instances don't overlap
+    instances.sort_by(|i1,i2| i1.address.node.cmp(&i2.address.node));
+    let mut failed = false;
+    for (i1,i2) in instances.iter().zip(instances.iter().skip(1)) {
+      let (size1, size2) = (i1.size(group), i2.size(group));
+      if i2.address.node < i1.address.node + size1 {
+        self.sess.span_diagnostic.span_err(
+          i1.name.span,
+          format!("The register block of this instance (0x{:x} to 0x{:x})",
+                  i1.address.node, i1.address.node + size1 - 1).as_slice());
+        self.sess.span_diagnostic.span_err(
+          i2.name.span,
+          format!("overlaps with the block of this instance (0x{:x} to 0x{:x})",
+                  i2.address.node, i2.address.node + size2 - 1).as_slice());
+        failed = true;
+      }
+    }
+
+    if failed {
+      None
+    } else {
+      Some(instances)
+    }
+  }
+
+  /// Verify that all instances of a block asking for bit-band accessors
+  /// lie in a bit-band region. The accessors compute the alias of a
+  /// register from its address and would write elsewhere otherwise.
+  pub fn check_bitband(&self, group: &node::Reg,
+                       instances: &Vec<node::Instance>) -> bool {
+    if instances.is_empty() {
+      self.sess.span_diagnostic.span_err(
+        group.name.span,
+        format!("Bit-band accessors need instances of `{}` at fixed \
+                 addresses", group.name.node).as_slice());
+      return false;
+    }
+
+    let mut failed = false;
+    for i in instances.iter() {
+      let first = i.address.node;
+      let last = first + ::std::cmp::max(i.size(group), 1) - 1;
+      if !BITBAND_REGIONS.iter().any(|&(lo, hi)| lo <= first && last <= hi) {
+        self.sess.span_diagnostic.span_err(
+          i.address.span,
+          format!("Instance `{}` (0x{:x} to 0x{:x}) is outside of the \
+                   bit-band regions (0x2000_0000 to 0x200f_ffff and \
+                   0x4000_0000 to 0x400f_ffff)",
+                  i.name.node, first, last).as_slice());
+        failed = true;
+      }
+    }
+    !failed
+  }
+
+  /// Parse the name and address of an instance, e.g. `UART0 @ 0x4000_C000`
+  fn parse_instance_decl(&mut self)
+                         -> Option<(Spanned<String>, Spanned<uint>)> {
+    let name = match self.expect_ident() {
+      Some(name) => respan(self.last_span, name),
+      None => return None,
+    };
+    if !self.expect(&token::AT) {
+      return None;
+    }
+    match self.expect_uint() {
+      Some(address) => Some((name, respan(self.last_span, address))),
+      None => None,
+    }
+  }
+
+  /// Parse the registers only present in some instances of a register
+  /// block. These must not overlap with the registers of the block.
+  fn parse_extension(&mut self, group: &node::Reg,
+                     instances: &Vec<Spanned<String>>) -> Option<node::Reg> {
+    let sp_lo = self.span.lo;
+    if !self.expect(&token::LBRACE) {
+      return None;
+    }
+    let docstring = self.parse_docstring(Inner);
+    let regs = match self.parse_regs() {
+      Some(regs) => regs,
+      None => return None,
+    };
+
+    let names: Vec<&str> =
+      instances.iter().map(|i| i.node.as_slice()).collect();
+    let name = format!("{}_{}", group.name.node, names.connect("_"));
+
+    let base_regs = match group.ty {
+      node::RegUnion(ref regs) => regs.clone(),
+      node::RegPrim(..) => fail!("top-level register must be a group"),
+    };
+    let mut failed = false;
+    for r in regs.iter() {
+      for b in base_regs.iter() {
+        if r.offset <= b.last_byte() && b.offset <= r.last_byte() {
+          self.sess.span_diagnostic.span_err(
+            r.name.span,
+            format!("Register `{}` of `{}` overlaps with register \
+                     `{}` of `{}`",
+                    r.name.node, name,
+                    b.name.node, group.name.node).as_slice());
+          failed = true;
+        }
+      }
+    }
+    if failed {
+      return None;
+    }
+
+    Some(node::Reg {
+      offset: 0,
+      name: respan(mk_sp(instances[0].span.lo,
+                         instances[instances.len() - 1].span.hi), name),
+      ty: node::RegUnion(Rc::new(regs)),
+      count: respan(mk_sp(sp_lo, self.last_span.hi), 1),
+      docstring: docstring,
+      reset: None,
+      stride: None,
+      sequence: None,
+    })
+  }
+
   /// Parse a block of regs
   fn parse_regs(&mut self) -> Option<Vec<node::Reg>> {
     // sitting at start of first register, after LBRACE so that the
@@ -135,7 +379,7 @@ impl<'a> Parser<'a> {
     regs.sort_by(|r1,r2| r1.offset.cmp(&r2.offset));
 
     // Verify that registers don't overlap
-    let mut failed = false;
+    let mut failed = !self.resolve_sequences(&mut regs);
     for (r1,r2) in regs.iter().zip(regs.iter().skip(1)) {
       if r2.offset <= r1.last_byte() {
         self.sess.span_diagnostic.span_err(
@@ -164,7 +408,7 @@ impl<'a> Parser<'a> {
 
     // we are still sitting at the offset
     let offset = match self.expect_uint() {
-      Some(offset) => offset,
+      Some(offset) => respan(self.last_span, offset),
       None => return None,
     };
     if !self.expect(&token::FAT_ARROW) {
@@ -172,6 +416,8 @@ impl<'a> Parser<'a> {
     }
 
     let ty = match self.expect_ident() {
+      Some(ref i) if i.equiv(&"reg64") => node::RegPrim(node::Reg64, Vec::new()),
+      Some(ref i) if i.equiv(&"split64") => node::RegPrim(node::Split64, Vec::new()),
       Some(ref i) if i.equiv(&"reg32") => node::RegPrim(node::Reg32, Vec::new()),
       Some(ref i) if i.equiv(&"reg16") => node::RegPrim(node::Reg16, Vec::new()),
       Some(ref i) if i.equiv(&"reg8")  => node::RegPrim(node::Reg8, Vec::new()),
@@ -195,6 +441,11 @@ impl<'a> Parser<'a> {
       Some(count) => count,
     };
 
+    let attrs = match self.parse_reg_attributes() {
+      None => return None,
+      Some(attrs) => attrs,
+    };
+
     // Potentially a trailing docstring before the block
     let docstring = docstring.or_else(|| self.parse_docstring(Trailing));
 
@@ -234,11 +485,50 @@ impl<'a> Parser<'a> {
               _ => {}
             }
 
+            // Verify reset value fits in register
+            match attrs.reset {
+              Some(ref reset) if width.size() < 8 &&
+                                 reset.node >> (8*width.size()) != 0 => {
+                self.sess.span_diagnostic.span_err(
+                  reset.span,
+                  format!("Reset value 0x{:x} exceeds access size of register ({} bits)",
+                          reset.node, 8*width.size()).as_slice());
+                return None;
+              },
+              _ => {}
+            }
+
+            // Field updates read the register, clearing any read-to-clear
+            // flags along the way
+            if fields.iter().any(|f| f.access == node::ReadToClear) &&
+               fields.iter().any(|f| f.access.is_writable()) {
+              self.sess.span_diagnostic.span_warn(
+                name.span,
+                "Updating fields of this register will clear its read-to-clear \
+                 flags, consider using `write()`");
+            }
+
             node::RegPrim(width, fields)
           },
         }
       },
       node::RegUnion(_) => {
+        match attrs.reset {
+          Some(ref reset) => {
+            self.sess.span_diagnostic.span_err(
+              reset.span, "Groups cannot have a reset value");
+            return None;
+          },
+          None => {},
+        }
+        match attrs.sequence {
+          Some(ref seq) => {
+            self.sess.span_diagnostic.span_err(
+              seq.key.span, "Groups cannot have a write sequence");
+            return None;
+          },
+          None => {},
+        }
         match self.parse_regs() {
           Some(regs) => node::RegUnion(Rc::new(regs)),
           None => return None,
@@ -246,15 +536,210 @@ impl<'a> Parser<'a> {
       },
     };
 
+    // Verify the register is aligned, as the register struct would
+    // otherwise be padded and place it (and what follows) elsewhere
+    if offset.node % ty.align() != 0 {
+      self.sess.span_diagnostic.span_err(
+        offset.span,
+        format!("Offset (0x{:x}) is not a multiple of the register's \
+                 alignment ({} bytes)",
+                offset.node, ty.align()).as_slice());
+      return None;
+    }
+
+    // Verify the stride leaves room for the register and keeps array
+    // elements aligned
+    match attrs.stride {
+      Some(ref stride) if stride.node < ty.size() => {
+        self.sess.span_diagnostic.span_err(
+          stride.span,
+          format!("Stride (0x{:x} bytes) is smaller than the register ({} bytes)",
+                  stride.node, ty.size()).as_slice());
+        return None;
+      },
+      Some(ref stride) if stride.node % ty.align() != 0 => {
+        self.sess.span_diagnostic.span_err(
+          stride.span,
+          format!("Stride (0x{:x} bytes) is not a multiple of the register's \
+                   alignment ({} bytes)",
+                  stride.node, ty.align()).as_slice());
+        return None;
+      },
+      _ => {}
+    }
+
+    // The key register is found relative to the protected register
+    match attrs.sequence {
+      Some(ref seq) if count.node != 1 => {
+        self.sess.span_diagnostic.span_err(
+          seq.key.span, "Register arrays cannot have a write sequence");
+        return None;
+      },
+      _ => {}
+    }
+
     Some(node::Reg {
-      offset: offset,
+      offset: offset.node,
       name: name,
       ty: ty,
       count: count,
       docstring: docstring,
+      reset: attrs.reset,
+      stride: attrs.stride,
+      sequence: attrs.sequence,
+    })
+  }
+
+  /// Parse an optional list of register attributes of the form
+  /// `@ NAME=VALUE, ...`
+  fn parse_reg_attributes(&mut self) -> Option<RegAttributes> {
+    let mut attrs = RegAttributes { reset: None, stride: None, sequence: None };
+    if self.token != token::AT {
+      return Some(attrs);
+    }
+    self.bump();
+
+    loop {
+      let key = match self.expect_ident() {
+        Some(key) => respan(self.last_span, key),
+        None => return None,
+      };
+      if !self.expect(&token::EQ) {
+        return None;
+      }
+      match key.node.as_slice() {
+        "reset" => {
+          match self.expect_uint() {
+            Some(v) => attrs.reset = Some(respan(self.last_span, v)),
+            None => return None,
+          }
+        },
+        "stride" => {
+          match self.expect_uint() {
+            Some(v) => attrs.stride = Some(respan(self.last_span, v)),
+            None => return None,
+          }
+        },
+        "unlock" | "feed" => {
+          if attrs.sequence.is_some() {
+            self.sess.span_diagnostic.span_err(
+              key.span, "A register can only have one write sequence");
+            return None;
+          }
+          let kind = if key.node.as_slice() == "unlock" {
+            node::Unlock
+          } else {
+            node::Feed
+          };
+          match self.parse_write_sequence(kind) {
+            Some(seq) => attrs.sequence = Some(seq),
+            None => return None,
+          }
+        },
+        _ => {
+          self.sess.span_diagnostic.span_err(
+            key.span,
+            format!("Unknown register attribute `{}`", key.node).as_slice());
+          return None;
+        },
+      }
+
+      if self.token == token::COMMA {
+        self.bump();
+      } else {
+        break;
+      }
+    }
+    Some(attrs)
+  }
+
+  /// Parse the key register and values of a write sequence, e.g.
+  /// `unlock(0xc520, 0xd928)`
+  fn parse_write_sequence(&mut self, kind: node::SequenceKind)
+                          -> Option<node::WriteSequence> {
+    let key = match self.expect_ident() {
+      Some(key) => respan(self.last_span, key),
+      None => return None,
+    };
+    if !self.expect(&token::LPAREN) {
+      return None;
+    }
+    let mut values: Vec<Spanned<uint>> = Vec::new();
+    loop {
+      match self.expect_uint() {
+        Some(v) => values.push(respan(self.last_span, v)),
+        None => return None,
+      }
+      if self.token == token::COMMA {
+        self.bump();
+      } else {
+        break;
+      }
+    }
+    if !self.expect(&token::RPAREN) {
+      return None;
+    }
+    Some(node::WriteSequence {
+      kind: kind,
+      key: key,
+      values: values,
+      // resolved by `resolve_sequences`
+      key_offset: 0,
+      key_width: node::Reg32,
     })
   }
 
+  /// Look up the key registers of the write sequences of a group's
+  /// registers, verifying that their values fit
+  fn resolve_sequences(&self, regs: &mut Vec<node::Reg>) -> bool {
+    let keys: Vec<(String, uint, Option<node::RegWidth>, uint)> = regs.iter()
+      .map(|r| {
+        let width = match r.ty {
+          node::RegPrim(width, _) => Some(width),
+          node::RegUnion(_) => None,
+        };
+        (r.name.node.clone(), r.offset, width, r.count.node)
+      }).collect();
+    let mut failed = false;
+    for reg in regs.iter_mut() {
+      let seq = match reg.sequence {
+        Some(ref mut seq) => seq,
+        None => continue,
+      };
+      let key = keys.iter().find(|&&(ref name, _, _, _)| *name == seq.key.node);
+      match key {
+        Some(&(_, offset, Some(width), 1)) if width.size() <= 4 => {
+          seq.key_offset = offset;
+          seq.key_width = width;
+          for v in seq.values.iter() {
+            if v.node >> (8*width.size()) != 0 {
+              self.sess.span_diagnostic.span_err(
+                v.span,
+                format!("Value 0x{:x} exceeds access size of key register \
+                         `{}` ({} bits)",
+                        v.node, seq.key.node, 8*width.size()).as_slice());
+              failed = true;
+            }
+          }
+        },
+        Some(_) => {
+          self.sess.span_diagnostic.span_err(
+            seq.key.span,
+            format!("Key register `{}` must be a single register of at most \
+                     32 bits", seq.key.node).as_slice());
+          failed = true;
+        },
+        None => {
+          self.sess.span_diagnostic.span_err(
+            seq.key.span,
+            format!("Unknown key register `{}`", seq.key.node).as_slice());
+          failed = true;
+        },
+      }
+    }
+    !failed
+  }
+
   fn parse_fields(&mut self) -> Option<Vec<node::Field>> {
     // sitting at starting bit number
     let mut fields: Vec<node::Field> = Vec::new();
@@ -322,6 +807,13 @@ impl<'a> Parser<'a> {
       Some(name) => respan(self.last_span, name),
       None => return None,
     };
+    if node::RESERVED_FIELD_NAMES.contains(&name.node.as_slice()) {
+      self.sess.span_diagnostic.span_err(
+        name.span,
+        format!("Field name `{}` clashes with a method of its register",
+                name.node).as_slice());
+      return None;
+    }
 
     let (count, width): (Spanned<uint>, uint) =
       match self.parse_count() {
@@ -340,31 +832,11 @@ impl<'a> Parser<'a> {
         None => return None,
       };
 
-    let access = match self.token.clone() {
-      token::COLON => {
-        self.bump();
-        match self.token.clone() {
-          ref t@token::IDENT(_,_) => {
-            match token::to_string(t) {
-              ref s if s.equiv(&"rw") => { self.bump(); node::ReadWrite },
-              ref s if s.equiv(&"ro") => { self.bump(); node::ReadOnly  },
-              ref s if s.equiv(&"wo") => { self.bump(); node::WriteOnly },
-              ref s if s.equiv(&"set_to_clear") => { self.bump(); node::SetToClear },
-              s => {
-                self.error(format!("Expected access type, saw `{}`", s));
-                return None;
-              },
-            }
-          },
-          ref t => {
-            self.error(format!("Expected access type, saw `{}`",
-                               token::to_string(t)));
-            return None;
-          },
-        }
-      },
-      _ => node::ReadWrite,
+    let modifiers = match self.parse_field_modifiers() {
+      Some(modifiers) => modifiers,
+      None => return None,
     };
+    let access = modifiers.access.unwrap_or(node::ReadWrite);
 
     let (docstring, ty) = match self.token {
       token::COMMA | token::RBRACE => {
@@ -372,20 +844,53 @@ impl<'a> Parser<'a> {
           self.bump();
         }
         let docstring = docstring.or_else(|| self.parse_docstring(Trailing));
-        let ty = match width {
-          1 => node::BoolField,
-          _ => node::UIntField,
+        let ty = match (modifiers.signed, modifiers.frac_bits.as_ref()) {
+          (signed, Some(frac_bits)) => {
+            if frac_bits.node > width {
+              self.sess.span_diagnostic.span_err(
+                frac_bits.span,
+                format!("Number of fractional bits ({}) exceeds width of \
+                         field ({} bits)",
+                        frac_bits.node, width).as_slice());
+              return None;
+            }
+            node::FixedField { signed: signed, frac_bits: frac_bits.node }
+          },
+          (true, None) => node::SIntField,
+          (false, None) if width == 1 => node::BoolField,
+          (false, None) => node::UIntField,
         };
         (docstring, respan(name.span, ty))
       },
       // A list of enumeration variants
       token::LBRACE => {
+        if modifiers.signed || modifiers.frac_bits.is_some() {
+          self.error("Enumerations cannot be signed or fixed-point"
+                     .to_string());
+          return None;
+        }
         self.bump();
 
         let sp_lo = self.span.lo;
         let docstring = docstring.or_else(|| self.parse_docstring(Inner));
         match self.parse_enum_variants() {
           Some(variants) => {
+            let mut failed = false;
+            for v in variants.iter() {
+              if width < 64 && v.value.node as u64 >= 1u64 << width {
+                self.sess.span_diagnostic.span_err(
+                  v.value.span,
+                  format!("Value of variant `{}` (0x{:x}) does not fit \
+                           in the {}-bit field `{}`",
+                          v.name.node, v.value.node, width,
+                          name.node).as_slice());
+                failed = true;
+              }
+            }
+            if failed {
+              return None;
+            }
+
             if self.token == token::COMMA {
               self.bump();
             }
@@ -418,6 +923,84 @@ impl<'a> Parser<'a> {
     Some(field)
   }
 
+  /// Parse an optional list of field modifiers of the form
+  /// `: MODIFIER ...`
+  fn parse_field_modifiers(&mut self) -> Option<FieldModifiers> {
+    let mut modifiers = FieldModifiers {
+      access: None,
+      signed: false,
+      frac_bits: None,
+    };
+    if self.token != token::COLON {
+      return Some(modifiers);
+    }
+    self.bump();
+
+    loop {
+      let modifier = match self.token.clone() {
+        ref t@token::IDENT(_,_) => token::to_string(t),
+        ref t if modifiers.access.is_none() && !modifiers.signed &&
+                 modifiers.frac_bits.is_none() => {
+          self.error(format!("Expected field modifier, saw `{}`",
+                             token::to_string(t)));
+          return None;
+        },
+        _ => break,
+      };
+      let span = self.span;
+      self.bump();
+
+      let access = match modifier.as_slice() {
+        "rw"           => Some(node::ReadWrite),
+        "ro"           => Some(node::ReadOnly),
+        "wo"           => Some(node::WriteOnly),
+        "set_to_clear" => Some(node::SetToClear),
+        "w1c"          => Some(node::SetToClear),
+        "w0c"          => Some(node::WriteZeroToClear),
+        "rc"           => Some(node::ReadToClear),
+        "w1s"          => Some(node::WriteOneToSet),
+        _              => None,
+      };
+      match (access, modifier.as_slice()) {
+        (Some(_), _) if modifiers.access.is_some() => {
+          self.sess.span_diagnostic.span_err(
+            span, "Field has more than one access type");
+          return None;
+        },
+        (Some(access), _) => modifiers.access = Some(access),
+        (None, "i") if modifiers.signed => {
+          self.sess.span_diagnostic.span_err(
+            span, "Field is already declared signed");
+          return None;
+        },
+        (None, "i") => modifiers.signed = true,
+        (None, "fixed") if modifiers.frac_bits.is_some() => {
+          self.sess.span_diagnostic.span_err(
+            span, "Field is already declared fixed-point");
+          return None;
+        },
+        (None, "fixed") => {
+          if !self.expect(&token::LPAREN) {
+            return None;
+          }
+          match self.expect_uint() {
+            Some(n) => modifiers.frac_bits = Some(respan(self.last_span, n)),
+            None => return None,
+          }
+          if !self.expect(&token::RPAREN) {
+            return None;
+          }
+        },
+        (None, s) => {
+          self.sess.span_diagnostic.span_err(
+            span, format!("Unknown field modifier `{}`", s).as_slice());
+          return None;
+        },
+      }
+    }
+    Some(modifiers)
+  }
+
   fn parse_enum_variants(&mut self) -> Option<Vec<node::Variant>> {
     // sitting at beginning of block after LBRACE
     let mut variants: Vec<node::Variant> = Vec::new();
@@ -496,7 +1079,7 @@ impl<'a> Parser<'a> {
     let string = docs.connect("\n");
     let string = string.as_slice().trim();
     if !string.is_empty() {
-      Some(respan(self.last_span, self.cx.ident_of(string)))
+      Some(respan(self.last_span, token::str_to_ident(string)))
     } else {
       None
     }
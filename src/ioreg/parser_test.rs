@@ -0,0 +1,119 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::parse;
+
+use node;
+use parser::Parser;
+
+/// Parse the arguments of an `ioregs!` invocation the way the macro
+/// does, returning `None` if any error was reported.
+fn parse_ioregs(src: &str) -> Option<(Rc<node::Reg>, Vec<node::Instance>)> {
+  let sess = parse::new_parse_sess();
+  let tts = parse::tts_from_source_str("<test>".to_string(), src.to_string(),
+                                       Vec::new(), &sess);
+  let mut parser = Parser::from_sess(&sess, tts.as_slice());
+  let options = match parser.parse_options() {
+    Some(options) => options,
+    None => return None,
+  };
+  let group = match parser.parse_ioregs() {
+    Some(group) => group,
+    None => return None,
+  };
+  let instances = match parser.parse_instances(&*group) {
+    Some(instances) => instances,
+    None => return None,
+  };
+  if options.bitband && !parser.check_bitband(&*group, &instances) {
+    return None;
+  }
+  if sess.span_diagnostic.handler.has_errors() {
+    None
+  } else {
+    Some((group, instances))
+  }
+}
+
+fn parses(src: &str) {
+  assert!(parse_ioregs(src).is_some());
+}
+
+fn fails_to_parse(src: &str) {
+  assert!(parse_ioregs(src).is_none());
+}
+
+#[test]
+fn parses_bitband_blocks_in_bitband_regions() {
+  parses("#[bitband] BB = { 0x0 => reg32 cr { 0 => en } }
+          BB0 @ 0x2000_0000, BB1 @ 0x400f_fffc");
+}
+
+#[test]
+fn fails_to_parse_bitband_blocks_outside_bitband_regions() {
+  // system control space, lpc17xx AHB GPIO and stm32f4 AHB2
+  fails_to_parse("#[bitband] BB = { 0x0 => reg32 cr { 0 => en } }
+                  BB0 @ 0xe000_e000");
+  fails_to_parse("#[bitband] BB = { 0x0 => reg32 cr { 0 => en } }
+                  BB0 @ 0x2009_c000");
+  fails_to_parse("#[bitband] BB = { 0x0 => reg32 cr { 0 => en } }
+                  BB0 @ 0x4000_0000, BB1 @ 0x5000_0000");
+  // straddling the end of the peripheral region
+  fails_to_parse("#[bitband] BB = { 0x4 => reg32 cr { 0 => en } }
+                  BB0 @ 0x400f_fffc");
+}
+
+#[test]
+fn fails_to_parse_bitband_blocks_without_instances() {
+  fails_to_parse("#[bitband] BB = { 0x0 => reg32 cr { 0 => en } }");
+}
+
+#[test]
+fn fails_to_parse_misaligned_registers() {
+  fails_to_parse("MISALIGNED = { 0x4 => reg64 counter { 0..63 => value } }");
+  fails_to_parse("MISALIGNED = { 0x2 => reg32 cr { 0 => en } }");
+  fails_to_parse("MISALIGNED = {
+                    0x4 => group ch { 0x0 => reg64 counter { 0..63 => value } }
+                  }");
+}
+
+#[test]
+fn parses_aligned_registers() {
+  parses("ALIGNED = {
+            0x0 => reg8 a { 0 => en }
+            0x2 => reg16 b { 0 => en }
+            0x8 => reg64 counter { 0..63 => value }
+          }");
+}
+
+#[test]
+fn parses_extensions_shared_by_instances() {
+  let (_, instances) = parse_ioregs(
+    "EXT = { 0x0 => reg32 cr { 0 => en } }
+     (EXT0 @ 0x1000, EXT2 @ 0x3000) { 0x4 => reg32 icr { 0 => en } },
+     EXT1 @ 0x2000").expect("failed to parse");
+  let names: Vec<Option<String>> = instances.iter()
+    .map(|i| i.extension.as_ref().map(|e| e.name.node.clone()))
+    .collect();
+  assert_eq!(names, vec!(Some("EXT_EXT0_EXT2".to_string()), None,
+                         Some("EXT_EXT0_EXT2".to_string())));
+}
+
+#[test]
+fn fails_to_parse_shared_instances_without_registers() {
+  fails_to_parse("EXT = { 0x0 => reg32 cr { 0 => en } }
+                  (EXT0 @ 0x1000, EXT2 @ 0x3000), EXT1 @ 0x2000");
+}
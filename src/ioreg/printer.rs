@@ -0,0 +1,216 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Pretty-printer producing `ioregs!` source from a register group.
+
+use syntax::ast;
+use syntax::codemap::Spanned;
+use syntax::parse::token;
+
+use node;
+
+/// Produce an `ioregs!` invocation defining the given register group
+/// and its instances
+pub fn print_ioregs(reg: &node::Reg, instances: &Vec<node::Instance>)
+                    -> String {
+  let mut s = String::new();
+  s.push_str(format!("ioregs!({} = {{", reg.name.node).as_slice());
+  push_doc(&mut s, "//!", &reg.docstring);
+  s.push_str("\n");
+  match reg.ty {
+    node::RegUnion(ref regs) => print_regs(&mut s, regs.deref(), 1),
+    node::RegPrim(..) => fail!("top-level register must be a group"),
+  }
+  s.push_str("}");
+  if !instances.is_empty() {
+    s.push_str("\n");
+    let mut printed: Vec<String> = Vec::new();
+    for i in instances.iter() {
+      // instances sharing an extension are printed together with it
+      let sharing: Vec<&node::Instance> = match i.extension {
+        Some(ref ext) if printed.contains(&ext.name.node) => continue,
+        Some(ref ext) => instances.iter().filter(|j| {
+          j.extension.as_ref().map_or(false, |e| e.name.node == ext.name.node)
+        }).collect(),
+        None => vec!(i),
+      };
+      let decls: Vec<String> = sharing.iter().map(|j| {
+        format!("{} @ 0x{:x}", j.name.node, j.address.node)
+      }).collect();
+      if decls.len() > 1 {
+        s.push_str(format!("  ({})", decls.connect(", ")).as_slice());
+      } else {
+        s.push_str(format!("  {}", decls[0]).as_slice());
+      }
+      match i.extension {
+        Some(ref ext) => {
+          printed.push(ext.name.node.clone());
+          s.push_str(" {");
+          push_doc(&mut s, "//!", &ext.docstring);
+          s.push_str("\n");
+          match ext.ty {
+            node::RegUnion(ref regs) => print_regs(&mut s, regs.deref(), 2),
+            node::RegPrim(..) => fail!("extension must be a group"),
+          }
+          s.push_str("  },\n");
+        },
+        None => s.push_str(",\n"),
+      }
+    }
+  }
+  s.push_str(")\n");
+  s
+}
+
+fn indent(s: &mut String, level: uint) {
+  for _ in range(0, level) {
+    s.push_str("  ");
+  }
+}
+
+fn push_doc(s: &mut String, prefix: &str,
+            docstring: &Option<Spanned<ast::Ident>>) {
+  match *docstring {
+    Some(ref d) => {
+      let doc = token::get_ident(d.node);
+      s.push_str(format!("  {} {}", prefix,
+                         doc.get().replace("\n", " ")).as_slice());
+    },
+    None => {},
+  }
+}
+
+fn count(c: uint) -> String {
+  if c == 1 {
+    String::new()
+  } else {
+    format!("[{}]", c)
+  }
+}
+
+fn print_regs(s: &mut String, regs: &Vec<node::Reg>, level: uint) {
+  for (i, reg) in regs.iter().enumerate() {
+    if i != 0 {
+      s.push_str("\n");
+    }
+    indent(s, level);
+    let ty = match reg.ty {
+      node::RegPrim(node::Reg64, _) => "reg64",
+      node::RegPrim(node::Split64, _) => "split64",
+      node::RegPrim(node::Reg32, _) => "reg32",
+      node::RegPrim(node::Reg16, _) => "reg16",
+      node::RegPrim(node::Reg8, _)  => "reg8",
+      node::RegUnion(_)             => "group",
+    };
+    s.push_str(format!("0x{:x} => {} {}{}", reg.offset, ty, reg.name.node,
+                       count(reg.count.node)).as_slice());
+    let mut attrs = Vec::new();
+    match reg.reset {
+      Some(ref reset) => attrs.push(format!("reset=0x{:x}", reset.node)),
+      None => {},
+    }
+    match reg.stride {
+      Some(ref stride) => attrs.push(format!("stride=0x{:x}", stride.node)),
+      None => {},
+    }
+    match reg.sequence {
+      Some(ref seq) => {
+        let kind = match seq.kind {
+          node::Unlock => "unlock",
+          node::Feed => "feed",
+        };
+        let values: Vec<String> =
+          seq.values.iter().map(|v| format!("0x{:x}", v.node)).collect();
+        attrs.push(format!("{}={}({})", kind, seq.key.node,
+                           values.connect(", ")));
+      },
+      None => {},
+    }
+    if !attrs.is_empty() {
+      s.push_str(format!(" @ {}", attrs.connect(", ")).as_slice());
+    }
+    s.push_str(" {");
+    push_doc(s, "//!", &reg.docstring);
+    s.push_str("\n");
+
+    match reg.ty {
+      node::RegPrim(_, ref fields) => {
+        for f in fields.iter() {
+          print_field(s, f, level + 1);
+        }
+      },
+      node::RegUnion(ref regs) => print_regs(s, regs.deref(), level + 1),
+    }
+
+    indent(s, level);
+    s.push_str("}\n");
+  }
+}
+
+fn print_field(s: &mut String, field: &node::Field, level: uint) {
+  indent(s, level);
+  let bits = if field.low_bit == field.high_bit() {
+    format!("{}", field.low_bit)
+  } else {
+    format!("{}..{}", field.low_bit, field.high_bit())
+  };
+  s.push_str(format!("{} => {}{}", bits, field.name.node,
+                     count(field.count.node)).as_slice());
+  let mut modifiers: Vec<String> = Vec::new();
+  match field.access {
+    node::ReadWrite  => {},
+    node::ReadOnly   => modifiers.push("ro".to_string()),
+    node::WriteOnly  => modifiers.push("wo".to_string()),
+    node::SetToClear => modifiers.push("set_to_clear".to_string()),
+    node::WriteZeroToClear => modifiers.push("w0c".to_string()),
+    node::ReadToClear => modifiers.push("rc".to_string()),
+    node::WriteOneToSet => modifiers.push("w1s".to_string()),
+  }
+  match field.ty.node {
+    node::SIntField => modifiers.push("i".to_string()),
+    node::FixedField { signed: signed, frac_bits: frac_bits } => {
+      if signed {
+        modifiers.push("i".to_string());
+      }
+      modifiers.push(format!("fixed({})", frac_bits));
+    },
+    _ => {},
+  }
+  if !modifiers.is_empty() {
+    s.push_str(format!(": {}", modifiers.connect(" ")).as_slice());
+  }
+
+  match field.ty.node {
+    node::EnumField { ref variants, .. } => {
+      s.push_str(" {");
+      push_doc(s, "//!", &field.docstring);
+      s.push_str("\n");
+      for v in variants.iter() {
+        indent(s, level + 1);
+        s.push_str(format!("0x{:x} => {},", v.value.node, v.name.node)
+                   .as_slice());
+        push_doc(s, "//=", &v.docstring);
+        s.push_str("\n");
+      }
+      indent(s, level);
+      s.push_str("}\n");
+    },
+    _ => {
+      s.push_str(",");
+      push_doc(s, "//=", &field.docstring);
+      s.push_str("\n");
+    },
+  }
+}
@@ -0,0 +1,303 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! CMSIS-SVD exporter.
+//!
+//! Register groups become peripherals, nested groups clusters, and
+//! register and group arrays `[%s]` arrays. Field arrays are expanded
+//! into individually numbered fields. SVD has no notion of signed or
+//! fixed-point fields, these are exported as plain bit fields.
+//! `split64` registers are exported as two 32-bit registers suffixed
+//! with `_LO` and `_HI`, cutting the fields crossing from one word to
+//! the other in two.
+
+use std::cmp;
+use syntax::ast;
+use syntax::codemap::Spanned;
+use syntax::parse::token;
+
+use node;
+
+/// Produce an SVD document describing a device with the given register
+/// groups. Each instance of a group becomes a peripheral, the first one
+/// carrying the registers and the others derived from it, unless they
+/// have registers of their own. Groups without instances are exported
+/// as a single peripheral at address zero.
+pub fn export(device: &str, groups: &Vec<(&node::Reg, &Vec<node::Instance>)>)
+              -> String {
+  let mut s = String::new();
+  s.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+  s.push_str("<device schemaVersion=\"1.1\">\n");
+  push_elem(&mut s, 1, "name", device);
+  push_elem(&mut s, 1, "version", "1.0");
+  push_elem(&mut s, 1, "description", device);
+  push_elem(&mut s, 1, "addressUnitBits", "8");
+  push_elem(&mut s, 1, "width", "32");
+  push_elem(&mut s, 1, "size", "32");
+  push_elem(&mut s, 1, "access", "read-write");
+  indent(&mut s, 1);
+  s.push_str("<peripherals>\n");
+  for &(reg, instances) in groups.iter() {
+    let regs = match reg.ty {
+      node::RegUnion(ref regs) => regs,
+      node::RegPrim(..) => fail!("top-level register must be a group"),
+    };
+    if instances.is_empty() {
+      push_peripheral(&mut s, reg, reg.name.node.as_slice(), 0, reg.size(),
+                      regs.deref());
+    } else {
+      let first = &instances[0];
+      push_peripheral(&mut s, reg, first.name.node.as_slice(),
+                      first.address.node, first.size(reg),
+                      &instance_regs(regs.deref(), first));
+      for i in instances.iter().skip(1) {
+        // instances with registers of their own can't be derived
+        if i.extension.is_some() {
+          push_peripheral(&mut s, reg, i.name.node.as_slice(),
+                          i.address.node, i.size(reg),
+                          &instance_regs(regs.deref(), i));
+          continue;
+        }
+        indent(&mut s, 2);
+        s.push_str(format!("<peripheral derivedFrom=\"{}\">\n",
+                           escape(first.name.node.as_slice())).as_slice());
+        push_elem(&mut s, 3, "name", i.name.node.as_slice());
+        push_elem(&mut s, 3, "baseAddress",
+                  format!("0x{:x}", i.address.node).as_slice());
+        indent(&mut s, 2);
+        s.push_str("</peripheral>\n");
+      }
+    }
+  }
+  indent(&mut s, 1);
+  s.push_str("</peripherals>\n");
+  s.push_str("</device>\n");
+  s
+}
+
+/// The registers of an instance, including those of its extension
+fn instance_regs(regs: &Vec<node::Reg>, instance: &node::Instance)
+                 -> Vec<node::Reg> {
+  let mut regs = regs.clone();
+  match instance.extension {
+    Some(ref ext) => match ext.ty {
+      node::RegUnion(ref ext_regs) => regs.push_all(ext_regs.as_slice()),
+      node::RegPrim(..) => fail!("extension must be a group"),
+    },
+    None => {},
+  }
+  regs
+}
+
+fn indent(s: &mut String, level: uint) {
+  for _ in range(0, level) {
+    s.push_str("  ");
+  }
+}
+
+fn escape(text: &str) -> String {
+  text.replace("&", "&amp;")
+      .replace("<", "&lt;")
+      .replace(">", "&gt;")
+      .replace("\"", "&quot;")
+}
+
+fn push_elem(s: &mut String, level: uint, name: &str, text: &str) {
+  indent(s, level);
+  s.push_str(format!("<{}>{}</{}>\n", name, escape(text), name).as_slice());
+}
+
+fn push_doc(s: &mut String, level: uint,
+            docstring: &Option<Spanned<ast::Ident>>) {
+  match *docstring {
+    Some(ref d) => {
+      let doc = token::get_ident(d.node);
+      push_elem(s, level, "description", doc.get());
+    },
+    None => {},
+  }
+}
+
+fn push_peripheral(s: &mut String, group: &node::Reg, name: &str,
+                   address: uint, size: uint, regs: &Vec<node::Reg>) {
+  indent(s, 2);
+  s.push_str("<peripheral>\n");
+  push_elem(s, 3, "name", name);
+  push_doc(s, 3, &group.docstring);
+  push_elem(s, 3, "headerStructName", group.name.node.as_slice());
+  push_elem(s, 3, "baseAddress", format!("0x{:x}", address).as_slice());
+  indent(s, 3);
+  s.push_str("<addressBlock>\n");
+  push_elem(s, 4, "offset", "0x0");
+  push_elem(s, 4, "size", format!("0x{:x}", size).as_slice());
+  push_elem(s, 4, "usage", "registers");
+  indent(s, 3);
+  s.push_str("</addressBlock>\n");
+  indent(s, 3);
+  s.push_str("<registers>\n");
+  push_regs(s, 4, regs);
+  indent(s, 3);
+  s.push_str("</registers>\n");
+  indent(s, 2);
+  s.push_str("</peripheral>\n");
+}
+
+fn push_regs(s: &mut String, level: uint, regs: &Vec<node::Reg>) {
+  let mut regs: Vec<&node::Reg> = regs.iter().collect();
+  regs.sort_by(|r1, r2| r1.offset.cmp(&r2.offset));
+  for reg in regs.iter() {
+    let reset = reg.reset.as_ref().map(|r| r.node);
+    match reg.ty {
+      node::RegPrim(node::Split64, ref fields) => {
+        // SVD has no registers spanning two words, so each word becomes
+        // a register of its own
+        push_reg(s, level, *reg, "_LO", reg.offset, 32, fields, 0,
+                 reset.map(|r| r & 0xffff_ffff));
+        push_reg(s, level, *reg, "_HI", reg.offset + 4, 32, fields, 32,
+                 reset.map(|r| r >> 32));
+      },
+      node::RegPrim(width, ref fields) =>
+        push_reg(s, level, *reg, "", reg.offset, 8 * width.size(), fields, 0,
+                 reset),
+      node::RegUnion(ref regs) => {
+        push_reg_header(s, level, *reg, "cluster", "", reg.offset);
+        push_regs(s, level + 1, regs.deref());
+        indent(s, level);
+        s.push_str("</cluster>\n");
+      },
+    }
+  }
+}
+
+/// Open the element of a register or cluster, with its name, array
+/// dimensions, description and offset
+fn push_reg_header(s: &mut String, level: uint, reg: &node::Reg, tag: &str,
+                   suffix: &str, offset: uint) {
+  indent(s, level);
+  s.push_str(format!("<{}>\n", tag).as_slice());
+  if reg.count.node == 1 {
+    push_elem(s, level + 1, "name",
+              format!("{}{}", reg.name.node, suffix).as_slice());
+  } else {
+    push_elem(s, level + 1, "dim",
+              format!("{}", reg.count.node).as_slice());
+    push_elem(s, level + 1, "dimIncrement",
+              format!("0x{:x}", reg.element_size()).as_slice());
+    push_elem(s, level + 1, "name",
+              format!("{}{}[%s]", reg.name.node, suffix).as_slice());
+  }
+  push_doc(s, level + 1, &reg.docstring);
+  push_elem(s, level + 1, "addressOffset",
+            format!("0x{:x}", offset).as_slice());
+}
+
+/// Push a register of `size` bits holding the bits of `fields` starting
+/// at `low_bit`
+fn push_reg(s: &mut String, level: uint, reg: &node::Reg, suffix: &str,
+            offset: uint, size: uint, fields: &Vec<node::Field>,
+            low_bit: uint, reset: Option<uint>) {
+  push_reg_header(s, level, reg, "register", suffix, offset);
+  push_elem(s, level + 1, "size", format!("{}", size).as_slice());
+  match reset {
+    Some(reset) =>
+      push_elem(s, level + 1, "resetValue",
+                format!("0x{:x}", reset).as_slice()),
+    None => {},
+  }
+  indent(s, level + 1);
+  s.push_str("<fields>\n");
+  for f in fields.iter() {
+    push_field(s, level + 2, f, low_bit, low_bit + size);
+  }
+  indent(s, level + 1);
+  s.push_str("</fields>\n");
+  indent(s, level);
+  s.push_str("</register>\n");
+}
+
+/// Push the elements of a field lying between bits `low` and `high`
+/// (exclusive), relative to `low`. Elements crossing either bound are
+/// cut, their low part being suffixed with `_LO` and their high part
+/// with `_HI`.
+fn push_field(s: &mut String, level: uint, field: &node::Field, low: uint,
+              high: uint) {
+  let (access, modified, read_action) = match field.access {
+    node::ReadWrite        => ("read-write", None, None),
+    node::ReadOnly         => ("read-only", None, None),
+    node::WriteOnly        => ("write-only", None, None),
+    node::SetToClear       => ("read-write", Some("oneToClear"), None),
+    node::WriteZeroToClear => ("read-write", Some("zeroToClear"), None),
+    node::WriteOneToSet    => ("read-write", Some("oneToSet"), None),
+    node::ReadToClear      => ("read-only", None, Some("clear")),
+  };
+
+  for i in range(0, field.count.node) {
+    let first = field.low_bit + i * field.width;
+    let last = first + field.width;
+    if last <= low || first >= high {
+      continue;
+    }
+    let (from, to) = (cmp::max(first, low), cmp::min(last, high));
+    let whole = from == first && to == last;
+
+    indent(s, level);
+    s.push_str("<field>\n");
+    let mut name = if field.count.node == 1 {
+      field.name.node.clone()
+    } else {
+      format!("{}{}", field.name.node, i)
+    };
+    if !whole {
+      name.push_str(if from == first { "_LO" } else { "_HI" });
+    }
+    push_elem(s, level + 1, "name", name.as_slice());
+    push_doc(s, level + 1, &field.docstring);
+    push_elem(s, level + 1, "bitOffset", format!("{}", from - low).as_slice());
+    push_elem(s, level + 1, "bitWidth", format!("{}", to - from).as_slice());
+    push_elem(s, level + 1, "access", access);
+    match modified {
+      Some(m) => push_elem(s, level + 1, "modifiedWriteValues", m),
+      None => {},
+    }
+    match read_action {
+      Some(r) => push_elem(s, level + 1, "readAction", r),
+      None => {},
+    }
+
+    // the values of a field don't apply to its parts
+    match field.ty.node {
+      node::EnumField { ref variants, .. } if whole => {
+        indent(s, level + 1);
+        s.push_str("<enumeratedValues>\n");
+        for v in variants.iter() {
+          indent(s, level + 2);
+          s.push_str("<enumeratedValue>\n");
+          push_elem(s, level + 3, "name", v.name.node.as_slice());
+          push_doc(s, level + 3, &v.docstring);
+          push_elem(s, level + 3, "value",
+                    format!("0x{:x}", v.value.node).as_slice());
+          indent(s, level + 2);
+          s.push_str("</enumeratedValue>\n");
+        }
+        indent(s, level + 1);
+        s.push_str("</enumeratedValues>\n");
+      },
+      _ => {},
+    }
+
+    indent(s, level);
+    s.push_str("</field>\n");
+  }
+}
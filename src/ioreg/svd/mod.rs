@@ -0,0 +1,521 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+/*!
+CMSIS-SVD importer.
+
+Vendors describe the peripherals of their parts in CMSIS-SVD XML
+documents. This module reads such a document and produces a `node::Reg`
+register group for each peripheral, which can then be turned into
+`ioregs!` source with `printer::print_ioregs`.
+
+Names are lowercased for registers and fields and sanitized to be valid
+identifiers. Register arrays (`dim` with a `[%s]` name) become register
+or group arrays, with a stride if their increment exceeds the element
+size. Other `dim` elements are expanded into individually named
+registers.
+
+The `export` module goes the other way, producing an SVD document from
+register groups.
+*/
+
+use std::rc::Rc;
+use syntax::ast;
+use syntax::codemap::{Spanned, DUMMY_SP, dummy_spanned};
+use syntax::parse::token;
+
+use node;
+
+pub mod xml;
+pub mod export;
+
+#[cfg(test)] mod test;
+
+/// A peripheral described by an SVD document
+pub struct Peripheral {
+  /// Name of the peripheral instance (e.g. `UART0`)
+  pub name: String,
+  /// Address of the first register of the peripheral
+  pub base_address: uint,
+  /// The name of the peripheral whose registers this one shares, if any
+  pub derived_from: Option<String>,
+  /// The register block of the peripheral
+  pub regs: Rc<node::Reg>,
+}
+
+/// Register properties which are inherited from enclosing elements
+#[deriving(Clone)]
+struct Defaults {
+  size: Option<uint>,
+  access: Option<node::Access>,
+  reset_value: Option<uint>,
+}
+
+impl Defaults {
+  fn inherit(&self, elem: &xml::Element) -> Result<Defaults, String> {
+    let size = match elem.child_text("size") {
+      Some(s) => Some(try!(number(s.as_slice()))),
+      None => self.size,
+    };
+    let access = match elem.child_text("access") {
+      Some(a) => Some(try!(parse_access(a.as_slice()))),
+      None => self.access.clone(),
+    };
+    let reset_value = match elem.child_text("resetValue") {
+      Some(r) => Some(try!(number(r.as_slice()))),
+      None => self.reset_value,
+    };
+    Ok(Defaults { size: size, access: access, reset_value: reset_value })
+  }
+}
+
+/// Parse an SVD document, returning its peripherals in document order
+pub fn import(src: &str) -> Result<Vec<Peripheral>, String> {
+  let device = try!(xml::parse(src));
+  if device.name.as_slice() != "device" {
+    return Err(format!("expected `<device>` root element, found `<{}>`",
+                       device.name));
+  }
+  let defaults = try!(Defaults { size: None, access: None, reset_value: None }
+    .inherit(&device));
+  let elems = match device.child("peripherals") {
+    Some(ps) => ps.children_named("peripheral"),
+    None => return Err("no `<peripherals>` element found".to_string()),
+  };
+
+  let mut peripherals: Vec<Peripheral> = Vec::new();
+  for elem in elems.iter() {
+    let name = try!(required_text(*elem, "name"));
+    let base_address = try!(number(try!(required_text(*elem, "baseAddress"))
+                                   .as_slice()));
+    let parent = elem.attr("derivedFrom").map(|s| s.to_string());
+
+    let (regs, derived_from) = match (elem.child("registers"), parent) {
+      (Some(_), _) => (try!(import_peripheral(*elem, &defaults)), None),
+      (None, Some(parent)) => {
+        match peripherals.iter().find(|p| p.name == parent) {
+          Some(p) => (p.regs.clone(), Some(parent)),
+          None => return Err(format!(
+            "peripheral `{}` is derived from unknown peripheral `{}`",
+            name, parent)),
+        }
+      },
+      (None, None) => return Err(format!(
+        "peripheral `{}` has no registers", name)),
+    };
+
+    peripherals.push(Peripheral {
+      name: name,
+      base_address: base_address,
+      derived_from: derived_from,
+      regs: regs,
+    });
+  }
+  Ok(peripherals)
+}
+
+/// The instances of the register block of the given peripheral, i.e. the
+/// peripheral itself and all peripherals derived from it
+pub fn instances(peripherals: &Vec<Peripheral>, p: &Peripheral)
+                 -> Vec<node::Instance> {
+  let regs = &*p.regs as *const node::Reg;
+  peripherals.iter()
+    .filter(|q| &*q.regs as *const node::Reg == regs)
+    .map(|q| node::Instance {
+      name: dummy_spanned(q.name.clone()),
+      address: dummy_spanned(q.base_address),
+      extension: None,
+    })
+    .collect()
+}
+
+fn import_peripheral(elem: &xml::Element, defaults: &Defaults)
+                     -> Result<Rc<node::Reg>, String> {
+  let defaults = try!(defaults.inherit(elem));
+  let name = match elem.child_text("headerStructName") {
+    Some(n) => n,
+    None => try!(required_text(elem, "name")),
+  };
+  let regs = try!(import_regs(elem.child("registers").unwrap(), &defaults));
+  Ok(Rc::new(node::Reg {
+    offset: 0,
+    name: dummy_spanned(ident(name.as_slice(), false)),
+    ty: node::RegUnion(Rc::new(regs)),
+    count: dummy_spanned(1),
+    docstring: docstring(elem),
+    reset: None,
+    stride: None,
+    sequence: None,
+  }))
+}
+
+/// Import the registers and clusters contained in an element
+fn import_regs(elem: &xml::Element, defaults: &Defaults)
+               -> Result<Vec<node::Reg>, String> {
+  let mut regs = Vec::new();
+  for child in elem.children.iter() {
+    let imported = match child.name.as_slice() {
+      "register" => try!(import_register(child, defaults)),
+      "cluster"  => try!(import_cluster(child, defaults)),
+      _ => continue,
+    };
+    regs.push_all_move(imported);
+  }
+  regs.sort_by(|r1, r2| r1.offset.cmp(&r2.offset));
+  Ok(regs)
+}
+
+fn import_register(elem: &xml::Element, defaults: &Defaults)
+                   -> Result<Vec<node::Reg>, String> {
+  let defaults = try!(defaults.inherit(elem));
+  let name = try!(required_text(elem, "name"));
+  let offset = try!(number(try!(required_text(elem, "addressOffset"))
+                           .as_slice()));
+  let width = match defaults.size {
+    Some(64) => node::Reg64,
+    Some(8)  => node::Reg8,
+    Some(16) => node::Reg16,
+    Some(32) => node::Reg32,
+    Some(n)  => return Err(format!(
+      "register `{}` has unsupported size of {} bits", name, n)),
+    None => return Err(format!("register `{}` has no size", name)),
+  };
+  let access = defaults.access.clone().unwrap_or(node::ReadWrite);
+
+  let mut fields = Vec::new();
+  match elem.child("fields") {
+    Some(fs) => {
+      for f in fs.children_named("field").iter() {
+        fields.push(try!(import_field(*f, &access)));
+      }
+    },
+    None => {},
+  }
+  if fields.is_empty() {
+    // A register without fields is exposed as a single full-width field
+    fields.push(node::Field {
+      name: dummy_spanned("value".to_string()),
+      low_bit: 0,
+      width: 8 * width.size(),
+      count: dummy_spanned(1),
+      bit_range_span: DUMMY_SP,
+      access: access,
+      ty: dummy_spanned(node::UIntField),
+      docstring: None,
+    });
+  }
+  fields.sort_by(|f1, f2| f1.low_bit.cmp(&f2.low_bit));
+
+  let reg = node::Reg {
+    offset: offset,
+    name: dummy_spanned(String::new()),
+    ty: node::RegPrim(width, fields),
+    count: dummy_spanned(1),
+    docstring: docstring(elem),
+    reset: defaults.reset_value.map(|r| {
+      if width.size() < 8 {
+        dummy_spanned(r & ((1 << 8 * width.size()) - 1))
+      } else {
+        dummy_spanned(r)
+      }
+    }),
+    stride: None,
+    sequence: None,
+  };
+  expand_dim(elem, name.as_slice(), reg)
+}
+
+fn import_cluster(elem: &xml::Element, defaults: &Defaults)
+                  -> Result<Vec<node::Reg>, String> {
+  let defaults = try!(defaults.inherit(elem));
+  let name = try!(required_text(elem, "name"));
+  let offset = try!(number(try!(required_text(elem, "addressOffset"))
+                           .as_slice()));
+  let regs = try!(import_regs(elem, &defaults));
+  let reg = node::Reg {
+    offset: offset,
+    name: dummy_spanned(String::new()),
+    ty: node::RegUnion(Rc::new(regs)),
+    count: dummy_spanned(1),
+    docstring: docstring(elem),
+    reset: None,
+    stride: None,
+    sequence: None,
+  };
+  expand_dim(elem, name.as_slice(), reg)
+}
+
+/// Handle the `dim` element group of a register or cluster.
+///
+/// `[%s]` arrays whose increment matches the element size become arrays,
+/// everything else is expanded into separate registers.
+fn expand_dim(elem: &xml::Element, name: &str, reg: node::Reg)
+              -> Result<Vec<node::Reg>, String> {
+  let dim = match elem.child_text("dim") {
+    None => {
+      let mut reg = reg;
+      reg.name = dummy_spanned(ident(name, true));
+      return Ok(vec!(reg));
+    },
+    Some(d) => try!(number(d.as_slice())),
+  };
+  let increment = match elem.child_text("dimIncrement") {
+    Some(i) => try!(number(i.as_slice())),
+    None => return Err(format!("`{}` has `dim` but no `dimIncrement`", name)),
+  };
+
+  if name.ends_with("[%s]") && increment >= reg.ty.size() &&
+     increment % reg.ty.align() == 0 {
+    let mut reg = reg;
+    reg.name = dummy_spanned(ident(name.slice_to(name.len() - 4), true));
+    reg.count = dummy_spanned(dim);
+    if increment != reg.ty.size() {
+      reg.stride = Some(dummy_spanned(increment));
+    }
+    return Ok(vec!(reg));
+  }
+
+  let indices = match elem.child_text("dimIndex") {
+    Some(idx) => try!(dim_indices(idx.as_slice(), dim)),
+    None => range(0, dim).map(|i| i.to_string()).collect(),
+  };
+  let mut regs = Vec::new();
+  for (n, idx) in indices.iter().enumerate() {
+    let expanded = if name.contains("%s") {
+      name.replace("[%s]", idx.as_slice()).replace("%s", idx.as_slice())
+    } else {
+      format!("{}{}", name, idx)
+    };
+    let mut r = reg.clone();
+    r.name = dummy_spanned(ident(expanded.as_slice(), true));
+    r.offset = reg.offset + n * increment;
+    regs.push(r);
+  }
+  Ok(regs)
+}
+
+/// Parse a `dimIndex` specification, either a comma separated list or a
+/// range such as `0-3`
+fn dim_indices(s: &str, dim: uint) -> Result<Vec<String>, String> {
+  let indices: Vec<String> =
+    if s.contains("-") && !s.contains(",") {
+      let bounds: Vec<&str> = s.split('-').collect();
+      match (from_str::<uint>(bounds[0].trim()),
+             from_str::<uint>(bounds[bounds.len() - 1].trim())) {
+        (Some(lo), Some(hi)) if hi >= lo =>
+          range(lo, hi + 1).map(|i| i.to_string()).collect(),
+        _ => return Err(format!("invalid `dimIndex` range `{}`", s)),
+      }
+    } else {
+      s.split(',').map(|i| i.trim().to_string()).collect()
+    };
+  if indices.len() != dim {
+    return Err(format!("`dimIndex` `{}` does not have {} entries", s, dim));
+  }
+  Ok(indices)
+}
+
+fn import_field(elem: &xml::Element, reg_access: &node::Access)
+                -> Result<node::Field, String> {
+  let name = try!(required_text(elem, "name"));
+  let (low_bit, width) = try!(bit_range(elem, name.as_slice()));
+
+  let access = match elem.child_text("access") {
+    Some(a) => try!(parse_access(a.as_slice())),
+    None => reg_access.clone(),
+  };
+  let access = match (elem.child_text("modifiedWriteValues"),
+                      elem.child_text("readAction")) {
+    (Some(ref m), _) if m.as_slice() == "oneToClear"  => node::SetToClear,
+    (Some(ref m), _) if m.as_slice() == "zeroToClear" => node::WriteZeroToClear,
+    (Some(ref m), _) if m.as_slice() == "oneToSet"    => node::WriteOneToSet,
+    (_, Some(ref r)) if r.as_slice() == "clear"       => node::ReadToClear,
+    _ => access,
+  };
+
+  let variants = match elem.child("enumeratedValues") {
+    Some(values) => try!(import_variants(values, width)),
+    None => Vec::new(),
+  };
+  let ty = if !variants.is_empty() {
+    node::EnumField { opt_name: None, variants: variants }
+  } else if width == 1 {
+    node::BoolField
+  } else {
+    node::UIntField
+  };
+
+  Ok(node::Field {
+    name: dummy_spanned(field_ident(name.as_slice())),
+    low_bit: low_bit,
+    width: width,
+    count: dummy_spanned(1),
+    bit_range_span: DUMMY_SP,
+    access: access,
+    ty: dummy_spanned(ty),
+    docstring: docstring(elem),
+  })
+}
+
+/// Returns the low bit and width of a field given in any of the three
+/// forms permitted by SVD
+fn bit_range(elem: &xml::Element, name: &str) -> Result<(uint, uint), String> {
+  match (elem.child_text("bitOffset"), elem.child_text("bitWidth")) {
+    (Some(o), w) => {
+      let width = match w {
+        Some(w) => try!(number(w.as_slice())),
+        None => 1,
+      };
+      return Ok((try!(number(o.as_slice())), width));
+    },
+    _ => {},
+  }
+  match (elem.child_text("lsb"), elem.child_text("msb")) {
+    (Some(l), Some(m)) => {
+      let (lsb, msb) = (try!(number(l.as_slice())), try!(number(m.as_slice())));
+      return Ok((lsb, msb - lsb + 1));
+    },
+    _ => {},
+  }
+  match elem.child_text("bitRange") {
+    Some(r) => {
+      let r = r.as_slice().trim_chars(['[', ']'].as_slice());
+      let bounds: Vec<&str> = r.split(':').collect();
+      if bounds.len() == 2 {
+        let (msb, lsb) = (try!(number(bounds[0])), try!(number(bounds[1])));
+        return Ok((lsb, msb - lsb + 1));
+      }
+      Err(format!("field `{}` has malformed `bitRange`", name))
+    },
+    None => Err(format!("field `{}` has no bit range", name)),
+  }
+}
+
+fn import_variants(elem: &xml::Element, width: uint)
+                   -> Result<Vec<node::Variant>, String> {
+  let mut variants: Vec<node::Variant> = Vec::new();
+  for v in elem.children_named("enumeratedValue").iter() {
+    // Default values and values with don't-care bits have no single
+    // encoding and hence can't be expressed as enum variants
+    let value = match v.child_text("value") {
+      Some(ref s) if !(s.as_slice().starts_with("#")
+                       && s.as_slice().contains("x")) =>
+        try!(number(s.as_slice())),
+      _ => continue,
+    };
+    if width < 64 && value >= (1 << width) {
+      return Err(format!("enumerated value {} does not fit in {} bits",
+                         value, width));
+    }
+    if variants.iter().any(|other| other.value.node == value) {
+      continue;
+    }
+    let name = try!(required_text(*v, "name"));
+    variants.push(node::Variant {
+      name: dummy_spanned(ident(name.as_slice(), false)),
+      value: dummy_spanned(value),
+      docstring: docstring(*v),
+    });
+  }
+  Ok(variants)
+}
+
+fn parse_access(s: &str) -> Result<node::Access, String> {
+  match s {
+    "read-write" | "read-writeOnce" => Ok(node::ReadWrite),
+    "read-only"                     => Ok(node::ReadOnly),
+    "write-only" | "writeOnce"      => Ok(node::WriteOnly),
+    other => Err(format!("unknown access type `{}`", other)),
+  }
+}
+
+/// Parse an SVD `scaledNonNegativeInteger`
+pub fn number(s: &str) -> Result<uint, String> {
+  let s = s.trim();
+  let (digits, scale) = match s.chars().last() {
+    Some('k') | Some('K') => (s.slice_to(s.len() - 1), 1024),
+    Some('M') => (s.slice_to(s.len() - 1), 1024 * 1024),
+    Some('G') => (s.slice_to(s.len() - 1), 1024 * 1024 * 1024),
+    _ => (s, 1),
+  };
+  let n = if digits.starts_with("0x") || digits.starts_with("0X") {
+    ::std::num::from_str_radix::<uint>(digits.slice_from(2), 16)
+  } else if digits.starts_with("#") {
+    ::std::num::from_str_radix::<uint>(digits.slice_from(1), 2)
+  } else {
+    from_str::<uint>(digits)
+  };
+  match n {
+    Some(n) => Ok(n * scale),
+    None => Err(format!("invalid number `{}`", s)),
+  }
+}
+
+fn required_text(elem: &xml::Element, name: &str) -> Result<String, String> {
+  match elem.child_text(name) {
+    Some(s) => Ok(s),
+    None => Err(format!("`<{}>` is missing required element `<{}>`",
+                        elem.name, name)),
+  }
+}
+
+/// The `description` of an element with whitespace collapsed
+fn docstring(elem: &xml::Element) -> Option<Spanned<ast::Ident>> {
+  elem.child_text("description").and_then(|d| {
+    let words: Vec<&str> = d.as_slice().words().collect();
+    if words.is_empty() {
+      None
+    } else {
+      Some(dummy_spanned(token::str_to_ident(words.connect(" ").as_slice())))
+    }
+  })
+}
+
+/// Keywords, which can't be used as identifiers
+static KEYWORDS: &'static [&'static str] = &[
+  "as", "box", "break", "const", "continue", "crate", "else", "enum",
+  "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop",
+  "match", "mod", "mut", "priv", "proc", "pub", "ref", "return", "self",
+  "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+  "while", "yield",
+];
+
+/// Turn an SVD field name into a valid field identifier, also escaping
+/// names which collide with generated methods
+fn field_ident(name: &str) -> String {
+  let mut s = ident(name, true);
+  if node::RESERVED_FIELD_NAMES.contains(&s.as_slice()) {
+    s.push('_');
+  }
+  s
+}
+
+/// Turn an SVD name into a valid identifier, optionally lowercasing it
+fn ident(name: &str, lowercase: bool) -> String {
+  let mut s: String = name.chars().map(|c| {
+    if c.is_alphanumeric() || c == '_' {
+      if lowercase { c.to_lowercase() } else { c }
+    } else {
+      '_'
+    }
+  }).collect();
+  if s.as_slice().chars().next().map_or(true, |c| c.is_digit()) {
+    s = format!("_{}", s);
+  }
+  if KEYWORDS.contains(&s.as_slice()) {
+    s.push('_');
+  }
+  s
+}
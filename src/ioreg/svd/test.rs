@@ -0,0 +1,277 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::codemap::{DUMMY_SP, respan};
+
+use node;
+use printer;
+use json;
+use serialize::json::{Json, List, U64};
+use super::{import, instances, number, xml, Peripheral};
+use super::export::export;
+
+static UART_SVD: &'static str = include_str!("fixtures/uart.svd");
+
+fn import_uart() -> Vec<Peripheral> {
+  match import(UART_SVD) {
+    Ok(ps) => ps,
+    Err(e) => fail!("import failed: {}", e),
+  }
+}
+
+fn subregs(reg: &node::Reg) -> Vec<node::Reg> {
+  match reg.ty {
+    node::RegUnion(ref regs) => regs.deref().clone(),
+    node::RegPrim(..) => fail!("expected a group"),
+  }
+}
+
+fn fields<'a>(reg: &'a node::Reg) -> &'a Vec<node::Field> {
+  match reg.ty {
+    node::RegPrim(_, ref fields) => fields,
+    node::RegUnion(_) => fail!("expected a primitive register"),
+  }
+}
+
+#[test]
+fn parses_numbers() {
+  assert_eq!(number("42"), Ok(42));
+  assert_eq!(number(" 0x4000C000 "), Ok(0x4000C000));
+  assert_eq!(number("0X10"), Ok(0x10));
+  assert_eq!(number("#101"), Ok(5));
+  assert_eq!(number("4k"), Ok(4096));
+  assert!(number("0xZZ").is_err());
+}
+
+#[test]
+fn parses_xml() {
+  let e = xml::parse("<?xml version=\"1.0\"?><!-- c -->\
+                      <a x='1'>t&lt;&#x41;<b/><![CDATA[<c>]]></a>").unwrap();
+  assert_eq!(e.name.as_slice(), "a");
+  assert_eq!(e.attr("x"), Some("1"));
+  assert_eq!(e.text.as_slice(), "t<A<c>");
+  assert_eq!(e.children.len(), 1);
+  assert!(e.child("b").is_some());
+}
+
+#[test]
+fn reports_xml_errors() {
+  assert!(xml::parse("<a><b></a>").is_err());
+  assert!(xml::parse("<a>").is_err());
+  assert!(xml::parse("<a>&bogus;</a>").is_err());
+}
+
+#[test]
+fn imports_peripherals() {
+  let ps = import_uart();
+  assert_eq!(ps.len(), 2);
+  assert_eq!(ps[0].name.as_slice(), "UART0");
+  assert_eq!(ps[0].base_address, 0x4000C000);
+  assert_eq!(ps[0].derived_from, None);
+  assert_eq!(ps[1].name.as_slice(), "UART1");
+  assert_eq!(ps[1].base_address, 0x4000D000);
+  assert_eq!(ps[1].derived_from, Some("UART0".to_string()));
+  assert_eq!(subregs(ps[1].regs.deref()).len(),
+             subregs(ps[0].regs.deref()).len());
+}
+
+#[test]
+fn imports_registers() {
+  let ps = import_uart();
+  let regs = subregs(ps[0].regs.deref());
+  let names: Vec<&str> = regs.iter().map(|r| r.name.node.as_slice()).collect();
+  assert_eq!(names, vec!("cr", "sr", "dr", "matcha", "matchb", "ch"));
+
+  let offsets: Vec<uint> = regs.iter().map(|r| r.offset).collect();
+  assert_eq!(offsets, vec!(0x0, 0x4, 0x8, 0x10, 0x18, 0x20));
+
+  assert_eq!(regs[0].reset.as_ref().map(|r| r.node), Some(0x40));
+  assert_eq!(regs[1].reset.as_ref().map(|r| r.node), None);
+  assert_eq!(regs[2].ty.size(), 1);
+  assert_eq!(regs[5].count.node, 4);
+  assert_eq!(subregs(&regs[5]).len(), 2);
+}
+
+#[test]
+fn imports_fields() {
+  let ps = import_uart();
+  let regs = subregs(ps[0].regs.deref());
+
+  let cr = fields(&regs[0]);
+  assert_eq!(cr.len(), 3);
+  assert_eq!(cr[1].name.node.as_slice(), "br");
+  assert_eq!((cr[1].low_bit, cr[1].width), (4, 9));
+  match cr[2].ty.node {
+    node::EnumField { ref variants, .. } => {
+      let values: Vec<uint> = variants.iter().map(|v| v.value.node).collect();
+      assert_eq!(values, vec!(0, 2, 3));
+    },
+    _ => fail!("expected an enum field"),
+  }
+
+  let sr = fields(&regs[1]);
+  assert!(sr[0].access == node::ReadOnly);
+  assert!(sr[1].access == node::SetToClear);
+  assert!(sr[2].access == node::WriteZeroToClear);
+  assert!(sr[3].access == node::ReadToClear);
+
+  // keywords are escaped
+  assert_eq!(fields(&regs[3])[0].name.node.as_slice(), "type_");
+}
+
+#[test]
+fn escapes_field_names_clashing_with_methods() {
+  let ps = import_uart();
+  let regs = subregs(ps[0].regs.deref());
+  let matcha = fields(&regs[3]);
+  assert_eq!(matcha[1].name.node.as_slice(), "addr_");
+  assert_eq!((matcha[1].low_bit, matcha[1].width), (8, 8));
+  // registers don't share a namespace with methods
+  assert_eq!(subregs(&regs[5])[0].name.node.as_slice(), "addr");
+}
+
+#[test]
+fn prints_ioregs() {
+  let ps = import_uart();
+  let expected = "\
+ioregs!(UART0 = {  //! Universal asynchronous receiver & transmitter
+  0x0 => reg32 cr @ reset=0x40 {  //! Configuration register
+    0 => rxe,  //= Receive enable
+    4..12 => br,  //= Baudrate
+    14..16 => parity {  //! Parity
+      0x0 => NoParity,  //= No parity
+      0x2 => EvenParity,
+      0x3 => OddParity,
+    }
+  }
+
+  0x4 => reg32 sr {
+    0 => rxne: ro,
+    2 => fe: set_to_clear,
+    3 => oe: w0c,
+    4 => brk: rc,
+  }
+
+  0x8 => reg8 dr {
+    0..7 => value,
+  }
+
+  0x10 => reg32 matcha {
+    0..7 => type_,
+    8..15 => addr_,
+  }
+
+  0x18 => reg32 matchb {
+    0..7 => type_,
+    8..15 => addr_,
+  }
+
+  0x20 => group ch[4] {  //! DMA channel
+    0x0 => reg32 addr {
+      0..31 => value,
+    }
+
+    0x4 => reg32 len {
+      0..31 => value,
+    }
+  }
+}
+  UART0 @ 0x4000c000,
+  UART1 @ 0x4000d000,
+)
+";
+  let printed = printer::print_ioregs(ps[0].regs.deref(),
+                                      &instances(&ps, &ps[0]));
+  assert_eq!(printed.as_slice(), expected);
+}
+
+#[test]
+fn exports_svd() {
+  let ps = import_uart();
+  let instances = instances(&ps, &ps[0]);
+  let svd = export("LPC", &vec!((ps[0].regs.deref(), &instances)));
+  let reimported = match import(svd.as_slice()) {
+    Ok(ps) => ps,
+    Err(e) => fail!("import of exported document failed: {}", e),
+  };
+  assert_eq!(reimported.len(), 2);
+  assert_eq!(reimported[1].derived_from, Some("UART0".to_string()));
+  assert_eq!(printer::print_ioregs(reimported[0].regs.deref(),
+                                   &super::instances(&reimported,
+                                                     &reimported[0])),
+             printer::print_ioregs(ps[0].regs.deref(), &instances));
+
+  // split64 registers come back as two reg32 registers, with the fields
+  // crossing words cut in two
+  let mut regs = subregs(ps[0].regs.deref());
+  let mut cnt = regs[3].clone();
+  let mut lo = fields(&regs[3])[0].clone();
+  let mut mid = fields(&regs[3])[1].clone();
+  let mut hi = lo.clone();
+  lo.name.node = "lo".to_string();
+  lo.width = 16;
+  mid.name.node = "mid".to_string();
+  mid.low_bit = 16;
+  mid.width = 32;
+  hi.name.node = "hi".to_string();
+  hi.low_bit = 48;
+  hi.width = 16;
+  cnt.name.node = "cnt".to_string();
+  cnt.offset = 0x40;
+  cnt.reset = Some(respan(DUMMY_SP, 0x1_0000_0002));
+  cnt.ty = node::RegPrim(node::Split64, vec!(lo, mid, hi));
+  regs.push(cnt);
+  let group = node::Reg { ty: node::RegUnion(Rc::new(regs)),
+                          ..ps[0].regs.deref().clone() };
+  let svd = export("LPC", &vec!((&group, &instances)));
+  let reimported = match import(svd.as_slice()) {
+    Ok(ps) => ps,
+    Err(e) => fail!("import of exported document failed: {}", e),
+  };
+  let regs = subregs(reimported[0].regs.deref());
+  let words: Vec<(&str, uint, Option<uint>)> = regs.iter().skip(6)
+    .map(|r| (r.name.node.as_slice(), r.offset,
+              r.reset.as_ref().map(|r| r.node)))
+    .collect();
+  assert_eq!(words, vec!(("cnt_lo", 0x40, Some(2)), ("cnt_hi", 0x44, Some(1))));
+  let bits = |reg: &node::Reg| -> Vec<(String, uint, uint)> {
+    fields(reg).iter()
+      .map(|f| (f.name.node.clone(), f.low_bit, f.width))
+      .collect()
+  };
+  assert_eq!(bits(&regs[6]), vec!(("lo".to_string(), 0, 16),
+                                  ("mid_lo".to_string(), 16, 16)));
+  assert_eq!(bits(&regs[7]), vec!(("mid_hi".to_string(), 0, 16),
+                                  ("hi".to_string(), 16, 16)));
+}
+
+#[test]
+fn exports_json() {
+  let ps = import_uart();
+  let map = json::to_json(ps[0].regs.deref(), &instances(&ps, &ps[0]));
+  let find = |j: &Json, key: &str| -> Json {
+    j.find(&key.to_string()).expect("missing key").clone()
+  };
+  let regs = match find(&map, "registers") {
+    List(regs) => regs,
+    _ => fail!("expected a list of registers"),
+  };
+  assert_eq!(regs.len(), 6);
+  assert_eq!(find(&regs[0], "reset"), U64(0x40));
+  assert_eq!(find(&regs[2], "width"), U64(8));
+  assert_eq!(find(&regs[5], "stride"), U64(8));
+  assert_eq!(find(&map, "size"), U64(0x40));
+}
@@ -0,0 +1,278 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal XML reader, sufficient for CMSIS-SVD documents.
+//!
+//! Only elements, attributes and character data are retained. Processing
+//! instructions, comments and doctype declarations are skipped.
+
+/// An XML element
+#[deriving(Clone, Show)]
+pub struct Element {
+  pub name: String,
+  pub attributes: Vec<(String, String)>,
+  pub children: Vec<Element>,
+  /// Concatenated character data of the element (not including children)
+  pub text: String,
+}
+
+impl Element {
+  /// The first child element with the given name
+  pub fn child<'a>(&'a self, name: &str) -> Option<&'a Element> {
+    self.children.iter().find(|e| e.name.as_slice() == name)
+  }
+
+  /// All child elements with the given name, in document order
+  pub fn children_named<'a>(&'a self, name: &str) -> Vec<&'a Element> {
+    self.children.iter().filter(|e| e.name.as_slice() == name).collect()
+  }
+
+  /// The trimmed text of the first child element with the given name
+  pub fn child_text(&self, name: &str) -> Option<String> {
+    self.child(name).map(|e| e.text.as_slice().trim().to_string())
+  }
+
+  /// The value of an attribute
+  pub fn attr<'a>(&'a self, name: &str) -> Option<&'a str> {
+    self.attributes.iter()
+      .find(|&&(ref k, _)| k.as_slice() == name)
+      .map(|&(_, ref v)| v.as_slice())
+  }
+}
+
+/// Parse a document, returning its root element
+pub fn parse(src: &str) -> Result<Element, String> {
+  let mut reader = Reader { chars: src.chars().collect(), pos: 0 };
+  try!(reader.skip_misc());
+  let root = try!(reader.parse_element());
+  try!(reader.skip_misc());
+  if reader.pos < reader.chars.len() {
+    return Err(reader.error("unexpected content after root element"));
+  }
+  Ok(root)
+}
+
+struct Reader {
+  chars: Vec<char>,
+  pos: uint,
+}
+
+impl Reader {
+  fn error(&self, msg: &str) -> String {
+    let line = self.chars.slice_to(self.pos).iter()
+      .filter(|&&c| c == '\n').count() + 1;
+    format!("line {}: {}", line, msg)
+  }
+
+  fn peek(&self) -> Option<char> {
+    if self.pos < self.chars.len() {
+      Some(self.chars[self.pos])
+    } else {
+      None
+    }
+  }
+
+  fn starts_with(&self, s: &str) -> bool {
+    let mut i = self.pos;
+    for c in s.chars() {
+      if i >= self.chars.len() || self.chars[i] != c {
+        return false;
+      }
+      i += 1;
+    }
+    true
+  }
+
+  fn skip_whitespace(&mut self) {
+    while self.peek().map_or(false, |c| c.is_whitespace()) {
+      self.pos += 1;
+    }
+  }
+
+  /// Skip everything up to and including the given terminator
+  fn skip_past(&mut self, term: &str) -> Result<(), String> {
+    while self.pos < self.chars.len() {
+      if self.starts_with(term) {
+        self.pos += term.char_len();
+        return Ok(());
+      }
+      self.pos += 1;
+    }
+    Err(self.error(format!("expected `{}` before end of input",
+                           term).as_slice()))
+  }
+
+  /// Skip whitespace, comments, processing instructions and doctype
+  /// declarations
+  fn skip_misc(&mut self) -> Result<(), String> {
+    loop {
+      self.skip_whitespace();
+      if self.starts_with("<?") {
+        try!(self.skip_past("?>"));
+      } else if self.starts_with("<!--") {
+        try!(self.skip_past("-->"));
+      } else if self.starts_with("<!DOCTYPE") {
+        try!(self.skip_past(">"));
+      } else {
+        return Ok(());
+      }
+    }
+  }
+
+  fn parse_name(&mut self) -> Result<String, String> {
+    let mut name = String::new();
+    loop {
+      match self.peek() {
+        Some(c) if c.is_alphanumeric() || c == '_' || c == ':'
+                   || c == '-' || c == '.' => {
+          name.push(c);
+          self.pos += 1;
+        },
+        _ => break,
+      }
+    }
+    if name.is_empty() {
+      Err(self.error("expected a name"))
+    } else {
+      Ok(name)
+    }
+  }
+
+  fn expect(&mut self, c: char) -> Result<(), String> {
+    if self.peek() == Some(c) {
+      self.pos += 1;
+      Ok(())
+    } else {
+      Err(self.error(format!("expected `{}`", c).as_slice()))
+    }
+  }
+
+  /// Parse character data up to the given terminator, resolving entities
+  fn parse_chars(&mut self, term: char) -> Result<String, String> {
+    let mut s = String::new();
+    loop {
+      match self.peek() {
+        None => return Err(self.error("unexpected end of input")),
+        Some(c) if c == term => return Ok(s),
+        Some('&') => s.push(try!(self.parse_entity())),
+        Some(c) => {
+          s.push(c);
+          self.pos += 1;
+        },
+      }
+    }
+  }
+
+  fn parse_entity(&mut self) -> Result<char, String> {
+    let start = self.pos;
+    try!(self.skip_past(";"));
+    let entity: String =
+      self.chars.slice(start + 1, self.pos - 1).iter().map(|&c| c).collect();
+    let c = match entity.as_slice() {
+      "lt"   => Some('<'),
+      "gt"   => Some('>'),
+      "amp"  => Some('&'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      e if e.starts_with("#x") =>
+        ::std::num::from_str_radix::<u32>(e.slice_from(2), 16)
+          .and_then(|n| ::std::char::from_u32(n)),
+      e if e.starts_with("#") =>
+        from_str::<u32>(e.slice_from(1)).and_then(|n| ::std::char::from_u32(n)),
+      _ => None,
+    };
+    match c {
+      Some(c) => Ok(c),
+      None => Err(self.error(format!("unknown entity `&{};`",
+                                     entity).as_slice())),
+    }
+  }
+
+  fn parse_attribute(&mut self) -> Result<(String, String), String> {
+    let name = try!(self.parse_name());
+    self.skip_whitespace();
+    try!(self.expect('='));
+    self.skip_whitespace();
+    let quote = match self.peek() {
+      Some(q) if q == '"' || q == '\'' => q,
+      _ => return Err(self.error("expected quoted attribute value")),
+    };
+    self.pos += 1;
+    let value = try!(self.parse_chars(quote));
+    self.pos += 1;
+    Ok((name, value))
+  }
+
+  fn parse_element(&mut self) -> Result<Element, String> {
+    try!(self.expect('<'));
+    let name = try!(self.parse_name());
+    let mut elem = Element {
+      name: name,
+      attributes: Vec::new(),
+      children: Vec::new(),
+      text: String::new(),
+    };
+
+    // attributes
+    loop {
+      self.skip_whitespace();
+      if self.starts_with("/>") {
+        self.pos += 2;
+        return Ok(elem);
+      }
+      if self.peek() == Some('>') {
+        self.pos += 1;
+        break;
+      }
+      let attr = try!(self.parse_attribute());
+      elem.attributes.push(attr);
+    }
+
+    // content
+    loop {
+      if self.pos >= self.chars.len() {
+        return Err(self.error(format!("unterminated element `{}`",
+                                      elem.name).as_slice()));
+      } else if self.starts_with("</") {
+        self.pos += 2;
+        let close = try!(self.parse_name());
+        if close != elem.name {
+          return Err(self.error(format!("expected `</{}>` but found `</{}>`",
+                                        elem.name, close).as_slice()));
+        }
+        self.skip_whitespace();
+        try!(self.expect('>'));
+        return Ok(elem);
+      } else if self.starts_with("<!--") {
+        try!(self.skip_past("-->"));
+      } else if self.starts_with("<![CDATA[") {
+        self.pos += 9;
+        let start = self.pos;
+        try!(self.skip_past("]]>"));
+        let data: String =
+          self.chars.slice(start, self.pos - 3).iter().map(|&c| c).collect();
+        elem.text.push_str(data.as_slice());
+      } else if self.starts_with("<?") {
+        try!(self.skip_past("?>"));
+      } else if self.peek() == Some('<') {
+        let child = try!(self.parse_element());
+        elem.children.push(child);
+      } else {
+        let text = try!(self.parse_chars('<'));
+        elem.text.push_str(text.as_slice());
+      }
+    }
+  }
+}
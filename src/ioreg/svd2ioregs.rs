@@ -0,0 +1,76 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Generate `ioregs!` definitions from a CMSIS-SVD file.
+//!
+//! Usage: `svd2ioregs DEVICE.svd [PERIPHERAL...]`
+//!
+//! Definitions for all peripherals of the device are printed to stdout
+//! unless a list of peripheral names is given. Peripherals derived from
+//! another peripheral share its register block and are emitted as
+//! instances of that block.
+
+#![crate_name="svd2ioregs"]
+
+extern crate ioreg;
+
+use std::io;
+use std::io::File;
+use std::os;
+
+use ioreg::printer;
+use ioreg::svd;
+
+fn main() {
+  let args = os::args();
+  if args.len() < 2 {
+    let _ = writeln!(io::stderr(), "usage: {} DEVICE.svd [PERIPHERAL...]",
+                     args[0]);
+    os::set_exit_status(1);
+    return;
+  }
+
+  let path = Path::new(args[1].as_slice());
+  let src = match File::open(&path).read_to_string() {
+    Ok(src) => src,
+    Err(e) => {
+      let _ = writeln!(io::stderr(), "{}: {}", path.display(), e);
+      os::set_exit_status(1);
+      return;
+    },
+  };
+
+  let peripherals = match svd::import(src.as_slice()) {
+    Ok(ps) => ps,
+    Err(e) => {
+      let _ = writeln!(io::stderr(), "{}: {}", path.display(), e);
+      os::set_exit_status(1);
+      return;
+    },
+  };
+
+  let wanted: Vec<&str> = args.slice_from(2).iter()
+    .map(|a| a.as_slice()).collect();
+  let mut out = io::stdout();
+  for p in peripherals.iter().filter(|p| p.derived_from.is_none()) {
+    let instances = svd::instances(&peripherals, p);
+    if !wanted.is_empty() &&
+       !instances.iter().any(|i| wanted.contains(&i.name.node.as_slice())) {
+      continue;
+    }
+    let _ = writeln!(out, "{}", printer::print_ioregs(p.regs.deref(),
+                                                      &instances));
+  }
+}
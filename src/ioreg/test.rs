@@ -22,11 +22,35 @@ extern crate core;
 
 #[path="../zinc/util/volatile_cell.rs"] mod volatile_cell;
 
+/// Stands in for the parts of zinc named by generated code
+mod zinc {
+  pub mod util {
+    pub mod support {
+      pub fn abort() -> ! { fail!("abort") }
+    }
+    pub mod volatile_cell {
+      pub use volatile_cell::VolatileCell;
+    }
+  }
+  pub mod hal {
+    pub mod cortex_m3 {
+      pub mod irq {
+        pub struct NoInterrupts;
+
+        impl NoInterrupts {
+          pub fn new() -> NoInterrupts { NoInterrupts }
+        }
+      }
+    }
+  }
+}
+
 #[cfg(test)]
 mod test {
   use std::mem::{transmute, zeroed};
   use std::ptr::RawPtr;
   use volatile_cell::VolatileCell;
+  use volatile_cell::mock::{MockRegisters, Read, Write};
 
   fn get_value<'a, T>(v: &'a T, offset: uint) -> u32 {
     unsafe {
@@ -75,6 +99,14 @@ mod test {
       assert_eq!(get_value(&test, 0), 0xde<<16)
     }
 
+    it "rejects values which don't fit in a field" {
+      test.reg1.set_field2(0x5);
+      assert!(test.reg1.try_set_field2(0x8).is_err())
+      assert_eq!(test.reg1.field2(), 0x5)
+      assert!(test.reg1.try_set_field2(0x7).is_ok())
+      assert_eq!(test.reg1.field2(), 0x7)
+    }
+
     it "sets set_to_clear fields" {
       test.reg1.clear_field4();
       assert_eq!(get_value(&test, 0), 1<<25)
@@ -86,6 +118,359 @@ mod test {
       test.wo_reg.set_field2(0xdead);
       assert_eq!(get_value(&test, 2), 0xdead<<16);
     }
+
+    it "accesses raw register values" {
+      test.wo_reg.raw_set(0x1234_5678);
+      assert_eq!(get_value(&test, 2), 0x1234_5678)
+      assert_eq!(test.wo_reg.raw_get(), 0x1234_5678)
+    }
+  )
+
+  ioregs!(RESET_TEST = {
+    0x0 => reg32 reg1 @ reset=0x0000_0f04 {
+      0..3   => field1,
+      4..7   => field2,
+      8..11  => field3,
+      12     => flag: set_to_clear,
+    }
+  })
+
+  describe!(
+    before_each {
+      let test: RESET_TEST = zeroed_safe();
+    }
+
+    it "exposes the reset value" {
+      assert_eq!(RESET_TEST_reg1_RESET, 0xf04)
+    }
+
+    it "restores the reset value" {
+      test.reg1.set_field1(0x3);
+      test.reg1.reset();
+      assert_eq!(get_value(&test, 0), 0xf04)
+    }
+
+    it "writes starting from the reset value" {
+      test.reg1.set_field2(0x5);
+      test.reg1.write().set_field1(0x2);
+      assert_eq!(get_value(&test, 0), 0xf02)
+    }
+
+    it "writes the reset value even if no field is set" {
+      test.reg1.write();
+      assert_eq!(get_value(&test, 0), 0xf04)
+    }
+
+    it "replaces the previous value of a field" {
+      test.reg1.set_field3(0xa).set_field3(0x5);
+      assert_eq!(get_value(&test, 0), 0x500)
+    }
+  )
+
+  ioregs!(ENUM_TEST = {
+    0x0 => reg32 reg1 {
+      0..1   => partial {
+        0 => PartialA,
+        2 => PartialC,
+      }
+      2      => complete {
+        0 => CompleteOff,
+        1 => CompleteOn,
+      }
+    }
+  })
+
+  describe!(
+    before_each {
+      let test: ENUM_TEST = zeroed_safe();
+    }
+
+    it "decodes known values of partial enumerations" {
+      test.reg1.set_partial(PartialC);
+      assert!(match test.reg1.partial() { Some(PartialC) => true, _ => false })
+    }
+
+    it "rejects unknown values of partial enumerations" {
+      test.reg1.value.set(0x1);
+      assert!(test.reg1.partial().is_none())
+      assert!(test.reg1.get().partial().is_none())
+    }
+
+    it "decodes complete enumerations directly" {
+      test.reg1.set_complete(CompleteOn);
+      assert!(match test.reg1.complete() { CompleteOn => true, _ => false })
+    }
+  )
+
+  ioregs!(SIGNED_TEST = {
+    0x0 => reg32 reg1 {
+      0..7   => sint: i,
+      8..15  => sfixed: i fixed(4),
+      16..23 => ufixed: fixed(2),
+    }
+  })
+
+  describe!(
+    before_each {
+      let test: SIGNED_TEST = zeroed_safe();
+    }
+
+    it "sign-extends signed fields" {
+      test.reg1.set_sint(-3);
+      assert_eq!(get_value(&test, 0), 0xfd)
+      assert_eq!(test.reg1.sint(), -3i32)
+    }
+
+    it "rejects values which don't fit in signed fields" {
+      assert!(test.reg1.try_set_sint(-129).is_err())
+      assert!(test.reg1.try_set_sint(128).is_err())
+      assert!(test.reg1.try_set_sint(-128).is_ok())
+      assert_eq!(get_value(&test, 0), 0x80)
+    }
+
+    it "scales fixed-point fields" {
+      test.reg1.set_sfixed(-1.5);
+      assert_eq!(get_value(&test, 0), 0xe800)
+      assert_eq!(test.reg1.sfixed(), -1.5f32)
+    }
+
+    it "rounds fixed-point values to the nearest step" {
+      test.reg1.set_ufixed(1.3);
+      assert_eq!(get_value(&test, 0), 0x50000)
+      assert_eq!(test.reg1.ufixed(), 1.25f32)
+    }
+
+    it "saturates out of range fixed-point values" {
+      test.reg1.set_sfixed(100.0);
+      assert_eq!(test.reg1.sfixed(), 7.9375f32)
+      test.reg1.set_sfixed(-100.0);
+      assert_eq!(test.reg1.sfixed(), -8.0f32)
+      test.reg1.set_ufixed(-1.0);
+      assert_eq!(test.reg1.ufixed(), 0.0f32)
+    }
+  )
+
+  ioregs!(FLAG_TEST = {
+    0x0 => reg32 reg1 {
+      0      => w1c_flag: w1c,
+      1      => w0c_flag: w0c,
+      2      => w1s_flag: w1s,
+      8..15  => field,
+      16..19 => w1c_array[4]: w1c,
+    }
+    0x4 => reg32 reg2 {
+      0      => rc_flag: rc,
+      1      => ro_flag: ro,
+    }
+  } FLAG0 @ 0x6000)
+
+  describe!(
+    before_each {
+      let test: FLAG_TEST = zeroed_safe();
+    }
+
+    it "does not disturb flags when updating other fields" {
+      test.reg1.set_field(0x5);
+      assert_eq!(get_value(&test, 0), 0x502)
+    }
+
+    it "does not write back set flags" {
+      test.reg1.clear_w1c_array(0);
+      assert_eq!(get_value(&test, 0), 1<<16 | 0x2)
+      test.reg1.set_field(0x3);
+      assert_eq!(get_value(&test, 0), 0x302)
+    }
+
+    it "clears write-one-to-clear flags by writing one" {
+      test.reg1.clear_w1c_flag();
+      assert_eq!(get_value(&test, 0), 0x3)
+    }
+
+    it "clears write-zero-to-clear flags by writing zero" {
+      test.reg1.set_field(0x1);
+      test.reg1.clear_w0c_flag();
+      assert_eq!(get_value(&test, 0), 0x100)
+    }
+
+    it "sets write-one-to-set flags by writing one" {
+      test.reg1.set_w1s_flag();
+      assert_eq!(get_value(&test, 0), 0x6)
+    }
+
+    it "reads read-to-clear flags" {
+      let regs = MockRegisters::new(0x6000);
+      regs.clear_on_read(0x4, 0x1);
+      regs.set(0x4, 0x3);
+      assert_eq!(FLAG0().reg2.rc_flag(), true)
+      assert_eq!(regs.get(0x4), 0x2)
+      assert_eq!(FLAG0().reg2.rc_flag(), false)
+      assert_eq!(regs.log(), vec!(Read(0x4, 0x3), Read(0x4, 0x2)))
+    }
+  )
+
+  ioregs!(MOCK_TEST = {
+    0x0 => reg32 cr {
+      0      => enable,
+      4..7   => mode,
+    }
+    0x4 => reg32 sr {
+      0      => ready: ro,
+    }
+  } MOCK0 @ 0x1000, MOCK1 @ 0x1008)
+
+  describe!(
+    before_each {
+      let regs = MockRegisters::new(0x1000);
+      let test = MOCK0();
+    }
+
+    it "places instances at their base addresses" {
+      assert_eq!(MOCK0() as *const MOCK_TEST as uint, 0x1000)
+      assert_eq!(MOCK1() as *const MOCK_TEST as uint, 0x1008)
+    }
+
+    it "records register accesses" {
+      test.cr.set_mode(0x3);
+      assert_eq!(regs.log(), vec!(Read(0x0, 0), Write(0x0, 0x30)))
+    }
+
+    it "returns scripted reads in order" {
+      regs.script_read(0x4, 0);
+      regs.script_read(0x4, 1);
+      assert_eq!(test.sr.ready(), false)
+      assert_eq!(test.sr.ready(), true)
+      assert_eq!(test.sr.ready(), false)
+      assert_eq!(regs.log(), vec!(Read(0x4, 0), Read(0x4, 1), Read(0x4, 0)))
+    }
+
+    it "reads back written values" {
+      test.cr.set_enable(true);
+      assert_eq!(test.cr.enable(), true)
+      assert_eq!(regs.get(0x0), 0x1)
+    }
+  )
+
+  #[test]
+  #[should_fail]
+  #[cfg(cfg_debug)]
+  fn aborts_on_values_which_dont_fit() {
+    let test: BASIC_TEST = zeroed_safe();
+    let value = 0x8;
+    test.reg1.set_field2(value);
+  }
+
+  #[test]
+  #[should_fail]
+  fn fails_on_accesses_below_the_mock_base() {
+    let _regs = MockRegisters::new(0x1008);
+    MOCK0().cr.set_enable(true);
+  }
+
+  ioregs!(EXT_TEST = {
+    0x0 => reg32 cr {
+      0      => enable,
+    }
+    0x4 => reg32 sr {
+      0      => ready: ro,
+    }
+  } EXT0 @ 0x3000, EXT1 @ 0x3100 {
+    0x10 => reg32 mcr {
+      0..3   => mode,
+    }
+  })
+
+  describe!(
+    before_each {
+      let regs = MockRegisters::new(0x3100);
+    }
+
+    it "places extension registers at their offsets" {
+      let base = EXT1() as *const EXT_TEST_EXT1;
+      let addr = &EXT1().mcr as *const EXT_TEST_EXT1_mcr;
+      assert_eq!(base.to_uint(), 0x3100)
+      assert_eq!(addr.to_uint() - base.to_uint(), 0x10)
+    }
+
+    it "accesses extension registers" {
+      EXT1().mcr.set_mode(0x5);
+      assert_eq!(regs.log(), vec!(Read(0x10, 0), Write(0x10, 0x5)))
+    }
+
+    it "accesses the registers of the block through the extension" {
+      EXT1().cr.set_enable(true);
+      assert_eq!(regs.log(), vec!(Read(0x0, 0), Write(0x0, 0x1)))
+      let block: &EXT_TEST = &**EXT1();
+      assert_eq!(block as *const EXT_TEST as uint, 0x3100)
+    }
+  )
+
+  ioregs!(SHARED_TEST = {
+    0x0 => reg32 cr {
+      0      => enable,
+    }
+  } (SHARED0 @ 0x3200, SHARED2 @ 0x3400) {
+    0x8 => reg32 icr {
+      0      => irdaen,
+    }
+  }, SHARED1 @ 0x3300)
+
+  describe!(
+    before_each {
+      let regs = MockRegisters::new(0x3200);
+    }
+
+    it "shares extension registers between instances" {
+      SHARED0().icr.set_irdaen(true);
+      SHARED2().icr.set_irdaen(true);
+      assert_eq!(regs.log(), vec!(Read(0x8, 0), Write(0x8, 0x1),
+                                  Read(0x208, 0), Write(0x208, 0x1)))
+      let shared: &SHARED_TEST_SHARED0_SHARED2 = SHARED2();
+      assert_eq!(shared as *const SHARED_TEST_SHARED0_SHARED2 as uint, 0x3400)
+      let block: &SHARED_TEST = &**SHARED1();
+      assert_eq!(block as *const SHARED_TEST as uint, 0x3300)
+    }
+  )
+
+  ioregs!(WIDE_TEST = {
+    0x0 => reg64 stamp {
+      0..63  => value,
+    }
+    0x8 => split64 count {
+      0..15  => low,
+      16..47 => middle,
+      48..63 => high,
+    }
+  } WIDE0 @ 0x2000)
+
+  describe!(
+    before_each {
+      let regs = MockRegisters::new(0x2000);
+      let test = WIDE0();
+    }
+
+    it "accesses 64-bit registers" {
+      test.stamp.write().set_value(0x1_0000_0002);
+      assert_eq!(regs.log(), vec!(Write(0x0, 0x1_0000_0002)))
+      assert_eq!(test.stamp.value(), 0x1_0000_0002)
+    }
+
+    it "writes the low word of split registers first" {
+      test.count.write().set_middle(0x1234_5678);
+      assert_eq!(regs.log(), vec!(Write(0x8, 0x5678_0000), Write(0xc, 0x1234)))
+    }
+
+    it "rereads split registers until the high word is stable" {
+      regs.script_read(0xc, 0x1);
+      regs.script_read(0x8, 0xffff_ffff);
+      regs.script_read(0xc, 0x2);
+      regs.script_read(0xc, 0x2);
+      regs.script_read(0x8, 0x0);
+      regs.script_read(0xc, 0x2);
+      let count = test.count.get();
+      assert_eq!(count.middle(), 0x2_0000)
+      assert_eq!(count.high(), 0x0)
+      assert_eq!(regs.log().len(), 6)
+    }
   )
 
   ioregs!(GROUP_TEST = {
@@ -116,6 +501,13 @@ mod test {
       assert_eq!(test.regs[2].reg2.field2(), 0xfeedbeef)
       assert_eq!(get_value(&test, 5), 0xfeedbeef)
     }
+
+    it "gives the addresses of group elements" {
+      let base = test.addr();
+      assert_eq!(test.regs[2].addr() - base, 0x10)
+      assert_eq!(test.regs[2].reg2.addr() - base, 0x14)
+      assert_eq!(GROUP_TEST_regs_reg2_OFFSET, 0x4)
+    }
   )
 
   ioregs!(FIELD_ARRAY_TEST = {
@@ -140,6 +532,69 @@ mod test {
     }
   )
 
+  ioregs!(STRIDE_TEST = {
+    0x0 => group chan[3] @ stride=0x10 {
+      0x0 => reg32 cr {
+        0..31 => field,
+      }
+      0x4 => reg32 sr {
+        0..31 => field,
+      }
+    }
+    0x30 => reg16 ipr[4] @ stride=0x4 {
+      0..15 => field,
+    }
+    0x40 => reg32 last {
+      0..31 => field,
+    }
+  })
+
+  describe!(
+    before_each {
+      let test: STRIDE_TEST = zeroed_safe();
+      let base = &test as *const STRIDE_TEST;
+    }
+    it "places group array elements at the stride" {
+      let addr = &test.chan[2].sr as *const STRIDE_TEST_chan_sr;
+      assert_eq!(addr.to_uint() - base.to_uint(), 0x24)
+    }
+    it "places register array elements at the stride" {
+      let addr = &test.ipr[3] as *const STRIDE_TEST_ipr;
+      assert_eq!(addr.to_uint() - base.to_uint(), 0x3c)
+    }
+    it "places registers following arrays" {
+      let addr = &test.last as *const STRIDE_TEST_last;
+      assert_eq!(addr.to_uint() - base.to_uint(), 0x40)
+    }
+    it "accesses elements by index" {
+      test.ipr[1].set_field(0xabcd);
+      assert_eq!(get_value(&test, 0x34 / 4), 0xabcd)
+    }
+  )
+
+  ioregs!(STRIDE_TAIL_TEST = {
+    0x0 => reg32 r[2] @ stride=0x10 {
+      0..31 => field,
+    }
+    0x14 => reg32 next {
+      0..31 => field,
+    }
+  })
+
+  describe!(
+    before_each {
+      let test: STRIDE_TAIL_TEST = zeroed_safe();
+      let base = &test as *const STRIDE_TAIL_TEST;
+    }
+    it "places registers after the last element of an array" {
+      let addr = &test.r[1] as *const STRIDE_TAIL_TEST_r;
+      assert_eq!(addr.to_uint() - base.to_uint(), 0x10)
+      let addr = &test.next as *const STRIDE_TAIL_TEST_next;
+      assert_eq!(addr.to_uint() - base.to_uint(), 0x14)
+      assert_eq!(STRIDE_TAIL_TEST_next_OFFSET, 0x14)
+    }
+  )
+
   ioregs!(GAP_TEST = {
     0x0 => reg32 reg1 {
       0..31 => field,
@@ -172,5 +627,174 @@ mod test {
       let addr = &test.reg4 as *const GAP_TEST_reg4;
       assert_eq!(addr.to_uint() - base.to_uint(), 0x20)
     }
+    it "gives the addresses of registers" {
+      assert_eq!(test.addr(), base.to_uint())
+      assert_eq!(test.reg3.addr() - base.to_uint(), 0x14)
+    }
+    it "exposes register offsets as statics" {
+      assert_eq!(GAP_TEST_reg2_OFFSET, 0x10)
+      assert_eq!(GAP_TEST_reg4_OFFSET, 0x20)
+    }
   )
+
+  #[cfg(cfg_arch_has_bitband)]
+  mod bitband {
+    use volatile_cell::VolatileCell;
+    use volatile_cell::mock::{MockRegisters, Write};
+
+    ioregs!(#[bitband] BITBAND_TEST = {
+      0x0 => reg32 cr {
+        0      => enable,
+        1..4   => flags[4],
+      }
+      0x4 => reg32 sr {
+        0      => busy,
+        1      => err: set_to_clear,
+      }
+    } BITBAND0 @ 0x4000_1000)
+
+    describe!(
+      before_each {
+        // bit-band alias of BITBAND0
+        let regs = MockRegisters::new(0x4202_0000);
+      }
+
+      it "sets flags through the bit-band alias" {
+        BITBAND0().cr.atomic_set_enable();
+        assert_eq!(regs.log(), vec!(Write(0x0, 1)))
+      }
+
+      it "clears flags through the bit-band alias" {
+        BITBAND0().cr.atomic_clear_flags(2);
+        assert_eq!(regs.log(), vec!(Write(0xc, 0)))
+      }
+    )
+  }
+
+  mod sequence {
+    use volatile_cell::VolatileCell;
+    use volatile_cell::mock::{MockRegisters, Read, Write};
+
+    ioregs!(SEQ_TEST = {
+      0x0 => reg16 ctrl @ unlock=unlock(0xc520, 0xd928) {
+        0      => en,
+        4      => allowupdate,
+      }
+      0x2 => reg16 unlock {
+        0..15  => key: wo,
+      }
+      0x4 => reg32 cfg @ feed=feed(0xaa, 0x55) {
+        0..14  => m,
+      }
+      0x8 => reg32 feed {
+        0..7   => key: wo,
+      }
+    } SEQ0 @ 0x5000)
+
+    describe!(
+      before_each {
+        let regs = MockRegisters::new(0x5000);
+      }
+
+      it "writes the unlock sequence before the register" {
+        SEQ0().ctrl.set_en(true);
+        assert_eq!(regs.log(), vec!(Read(0x0, 0), Write(0x2, 0xc520),
+                                    Write(0x2, 0xd928), Read(0x2, 0xd928),
+                                    Write(0x0, 0x1)))
+      }
+
+      it "writes the feed sequence after the register" {
+        SEQ0().cfg.write().set_m(0x10);
+        assert_eq!(regs.log(), vec!(Write(0x4, 0x10), Write(0x8, 0xaa),
+                                    Write(0x8, 0x55)))
+      }
+
+      it "protects raw writes" {
+        SEQ0().ctrl.raw_set(0x10);
+        assert_eq!(regs.log(), vec!(Write(0x2, 0xc520), Write(0x2, 0xd928),
+                                    Read(0x2, 0xd928), Write(0x0, 0x10)))
+      }
+    )
+  }
+
+  mod dump {
+    use std::cell::RefCell;
+    use volatile_cell::VolatileCell;
+    use super::zeroed_safe;
+
+    /// Stands in for `zinc::drivers::chario::CharIO`
+    trait CharIO {
+      fn putc(&self, value: char);
+      fn puts(&self, s: &str) {
+        for c in s.chars() {
+          self.putc(c);
+        }
+      }
+      fn puth(&self, i: u32) {
+        self.puts(format!("{:x}", i).as_slice());
+      }
+      fn puti(&self, i: u32) {
+        self.puts(format!("{}", i).as_slice());
+      }
+    }
+
+    struct StringIO {
+      buf: RefCell<String>,
+    }
+
+    impl CharIO for StringIO {
+      fn putc(&self, value: char) {
+        self.buf.borrow_mut().push(value);
+      }
+    }
+
+    ioregs!(#[dump] DUMP_TEST = {
+      0x0 => reg32 cr {
+        0      => enable,
+        1..2   => mode {
+          0 => Off,
+          1 => Slow,
+          3 => Fast,
+        }
+        4..11  => count,
+        12..15 => offset: i,
+      }
+      0x4 => reg32 sr {
+        0      => ready: ro,
+        1      => overrun: rc,
+      }
+      0x8 => group chan[2] {
+        0x0 => reg32 ctrl {
+          0..1 => pins[2],
+        }
+      }
+    })
+
+    describe!(
+      before_each {
+        let test: DUMP_TEST = zeroed_safe();
+        let io = StringIO { buf: RefCell::new(String::new()) };
+      }
+
+      it "dumps the fields of a register" {
+        test.cr.value.set(0x4);
+        test.cr.get().dump(&io);
+        assert_eq!(io.buf.borrow().as_slice(),
+                   "enable=false mode=0x2 count=0x0 offset=0")
+      }
+
+      it "dumps every register of a block" {
+        test.cr.set_enable(true).set_mode(Fast).set_count(0x2a)
+               .set_offset(-2);
+        test.chan[1].ctrl.set_pins(1, true);
+        test.dump(&io);
+        assert_eq!(io.buf.borrow().as_slice(),
+                   "cr: enable=true mode=Fast count=0x2a offset=-2\n\
+                    chan[0]:\n\
+                    \x20 ctrl: pins[0]=false pins[1]=false\n\
+                    chan[1]:\n\
+                    \x20 ctrl: pins[0]=false pins[1]=true\n")
+      }
+    )
+  }
 }
@@ -16,14 +16,18 @@
 #![crate_name="macro_ioreg"]
 #![crate_type="dylib"]
 
-#![feature(plugin_registrar, quote)]
+#![feature(plugin_registrar, quote, phase)]
 
-extern crate rustc;
+#[phase(plugin, link)] extern crate rustc;
 extern crate syntax;
 extern crate ioreg;
 
+use rustc::lint::{Context, LintPass, LintPassObject, LintArray};
+use rustc::middle::ty;
+use rustc::middle::typeck;
 use rustc::plugin::Registry;
 use syntax::ast;
+use syntax::attr::AttrMetaMethods;
 use syntax::ptr::P;
 use syntax::codemap::Span;
 use syntax::ext::base::{ExtCtxt, MacResult};
@@ -35,14 +39,28 @@ use ioreg::builder::Builder;
 #[plugin_registrar]
 pub fn plugin_registrar(reg: &mut Registry) {
   reg.register_macro("ioregs", macro_ioregs);
+  reg.register_lint_pass(box OutOfRange as LintPassObject);
 }
 
 pub fn macro_ioregs(cx: &mut ExtCtxt, _: Span, tts: &[ast::TokenTree])
                     -> Box<MacResult+'static> {
-  match Parser::new(cx, tts).parse_ioregs() {
-    Some(group) => {
+  let mut parser = Parser::new(cx, tts);
+  let options = match parser.parse_options() {
+    Some(options) => options,
+    None => fail!(),
+  };
+  let group = match parser.parse_ioregs() {
+    Some(group) => group,
+    None => fail!(),
+  };
+  match parser.parse_instances(&*group) {
+    Some(ref instances) if options.bitband &&
+                           !parser.check_bitband(&*group, instances) => {
+      fail!();
+    },
+    Some(instances) => {
       let mut builder = Builder::new();
-      let items = builder.emit_items(cx, group);
+      let items = builder.emit_items(cx, group, &instances, &options);
       MacItems::new(items)
     },
     None => {
@@ -66,3 +84,87 @@ impl MacResult for MacItems {
     Some(SmallVector::many(self.items.clone()))
   }
 }
+
+declare_lint!(IOREG_OUT_OF_RANGE, Deny,
+              "literal values which don't fit in the field set by an ioreg \
+               setter")
+
+/// Checks literal arguments of the setters generated by `ioregs!`
+/// against the range of their field, as given by the `ioreg_range`
+/// attribute of the setter
+pub struct OutOfRange;
+
+impl LintPass for OutOfRange {
+  fn get_lints(&self) -> LintArray {
+    lint_array!(IOREG_OUT_OF_RANGE)
+  }
+
+  fn check_expr(&mut self, cx: &Context, e: &ast::Expr) {
+    // the value is the last argument of a setter
+    let value = match e.node {
+      ast::ExprMethodCall(_, _, ref args) =>
+        match args.last().and_then(|arg| literal_value(&**arg)) {
+          Some(value) => value,
+          None => return,
+        },
+      _ => return,
+    };
+    let method = typeck::MethodCall::expr(e.id);
+    let did = match cx.tcx.method_map.borrow().find(&method) {
+      Some(callee) => match callee.origin {
+        typeck::MethodStatic(did) => did,
+        _ => return,
+      },
+      None => return,
+    };
+
+    let mut range = None;
+    ty::each_attr(cx.tcx, did, |attr| {
+      if attr.check_name("ioreg_range") {
+        range = parse_range(attr);
+        false
+      } else {
+        true
+      }
+    });
+    match range {
+      Some((min, max)) if value < min || value > max =>
+        cx.span_lint(IOREG_OUT_OF_RANGE, e.span,
+            format!("value {} doesn't fit in the field, allowed values: \
+                {}...{}", value, min, max).as_slice()),
+      _ => {},
+    }
+  }
+}
+
+/// The value of an integer literal, possibly negated
+fn literal_value(e: &ast::Expr) -> Option<i64> {
+  match e.node {
+    ast::ExprLit(ref lit) => match lit.node {
+      ast::LitInt(v, ast::SignedIntLit(_, ast::Minus)) |
+      ast::LitInt(v, ast::UnsuffixedIntLit(ast::Minus)) => Some(-(v as i64)),
+      ast::LitInt(v, _) => Some(v as i64),
+      _ => None,
+    },
+    ast::ExprUnary(ast::UnNeg, ref inner) =>
+      literal_value(&**inner).map(|v| -v),
+    ast::ExprParen(ref inner) => literal_value(&**inner),
+    _ => None,
+  }
+}
+
+/// Parses `#[ioreg_range(min="..", max="..")]`
+fn parse_range(attr: &ast::Attribute) -> Option<(i64, i64)> {
+  let items = match attr.meta_item_list() {
+    Some(items) => items,
+    None => return None,
+  };
+  let bound = |name: &str| items.iter()
+    .find(|item| item.check_name(name))
+    .and_then(|item| item.value_str())
+    .and_then(|value| from_str::<i64>(value.get()));
+  match (bound("min"), bound("max")) {
+    (Some(min), Some(max)) => Some((min, max)),
+    _ => None,
+  }
+}
@@ -0,0 +1,37 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Board descriptions that can be imported into platform tree.
+//!
+//! A board is imported with a `NAME@board;` root node. Its nodes are merged
+//! with the ones defined in the platform tree, which can override the board's
+//! attributes and add further nodes.
+
+static BOARDS: &'static [(&'static str, &'static str)] = &[
+  ("mbed_lpc1768", include_str!("../zinc/boards/mbed_lpc1768.pt")),
+  ("stm32f4_discovery", include_str!("../zinc/boards/stm32f4_discovery.pt")),
+  ("teensy3", include_str!("../zinc/boards/teensy3.pt")),
+  ("tiva_c_launchpad", include_str!("../zinc/boards/tiva_c_launchpad.pt")),
+];
+
+/// Returns platform tree source of the named board.
+pub fn get(name: &str) -> Option<&'static str> {
+  BOARDS.iter().find(|&&(n, _)| n == name).map(|&(_, source)| source)
+}
+
+/// Returns names of all known boards.
+pub fn names() -> Vec<&'static str> {
+  BOARDS.iter().map(|&(name, _)| name).collect()
+}
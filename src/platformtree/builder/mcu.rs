@@ -18,6 +18,8 @@ use syntax::ext::base::ExtCtxt;
 
 use lpc17xx_pt;
 use tiva_c_pt;
+use stm32f4_pt;
+use k20_pt;
 use node;
 
 use super::Builder;
@@ -28,6 +30,8 @@ pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
       match name.as_slice() {
         "lpc17xx" => lpc17xx_pt::attach(builder, cx, node.clone()),
         "tiva_c"  => tiva_c_pt::attach(builder, cx, node.clone()),
+        "stm32f4" => stm32f4_pt::attach(builder, cx, node.clone()),
+        "k20"     => k20_pt::attach(builder, cx, node.clone()),
         _ => node.materializer.set(Some(fail_build_mcu)),
       }
     },
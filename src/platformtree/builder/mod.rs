@@ -13,6 +13,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 use syntax::abi;
 use syntax::ast::TokenTree;
@@ -31,10 +33,36 @@ mod mcu;
 mod os;
 pub mod meta_args;
 
+/// A resource that can be used by a single node only.
+#[deriving(PartialEq, Eq, Hash, Clone)]
+pub enum Resource {
+  /// A physical pin, by port and pin index.
+  Pin(String, uint),
+  /// A peripheral signal a pin is muxed to, e.g. `sck0`.
+  PinFunction(String),
+  /// A peripheral instance, e.g. `UART0`.
+  Peripheral(String),
+  /// A loop function of an os task.
+  Task(String),
+}
+
+impl fmt::Show for Resource {
+  fn fmt(&self, fmt: &mut fmt::Formatter) -> Result<(), fmt::FormatError> {
+    match *self {
+      Pin(ref port, pin) => write!(fmt, "pin {}.{}", port, pin),
+      PinFunction(ref function) => write!(fmt, "pin function `{}`", function),
+      Peripheral(ref peripheral) =>
+        write!(fmt, "peripheral `{}`", peripheral),
+      Task(ref loop_fn) => write!(fmt, "task loop `{}`", loop_fn),
+    }
+  }
+}
+
 pub struct Builder {
   main_stmts: Vec<P<ast::Stmt>>,
   type_items: Vec<P<ast::Item>>,
   pt: Rc<node::PlatformTree>,
+  claims: HashMap<Resource, Span>,
 }
 
 impl Builder {
@@ -124,6 +152,7 @@ impl Builder {
       main_stmts: Vec::new(),
       type_items: Vec::new(),
       pt: pt,
+      claims: HashMap::new(),
     }
   }
 
@@ -135,6 +164,40 @@ impl Builder {
     self.pt.clone()
   }
 
+  /// Claims a resource, like a pin or a peripheral instance, for the node at
+  /// `span`.
+  ///
+  /// Reports an error pointing to both claims and returns false if the
+  /// resource is already in use.
+  pub fn claim(&mut self, cx: &ExtCtxt, resource: Resource, span: Span)
+      -> bool {
+    match self.claims.find(&resource) {
+      Some(old_span) => {
+        cx.parse_sess().span_diagnostic.span_err(span,
+            format!("{} is already in use", resource).as_slice());
+        cx.parse_sess().span_diagnostic.span_note(*old_span,
+            "previously used here");
+        return false;
+      },
+      None => (),
+    }
+    self.claims.insert(resource, span);
+    true
+  }
+
+  /// Claims the physical pin of a pin node for the node referencing it at
+  /// `span`.
+  pub fn claim_pin(&mut self, cx: &ExtCtxt, pin: &Rc<node::Node>, span: Span)
+      -> bool {
+    let port = pin.parent.clone().and_then(|weak| weak.upgrade());
+    match (port, from_str::<uint>(pin.path.as_slice())) {
+      (Some(port), Some(index)) =>
+        self.claim(cx, Pin(port.path.clone(), index), span),
+      // not a pin node, reported by its builder
+      _ => true,
+    }
+  }
+
   pub fn add_main_statement(&mut self, stmt: P<ast::Stmt>) {
     self.main_stmts.push(stmt);
   }
@@ -242,7 +305,8 @@ pub fn add_node_dependency(node: &Rc<node::Node>, dep: &Rc<node::Node>) {
 
 #[cfg(test)]
 mod test {
-  use test_helpers::fails_to_build;
+  use builder::{Builder, Pin, Peripheral};
+  use test_helpers::{fails_to_build, with_parsed};
 
   #[test]
   fn fails_to_parse_pt_with_unknown_root_node() {
@@ -253,4 +317,23 @@ mod test {
   fn fails_to_parse_pt_with_unknown_mcu() {
     fails_to_build("mcu@bad {}");
   }
+
+  #[test]
+  fn fails_to_claim_resource_twice() {
+    with_parsed("
+      a@a;
+      b@b;
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      let a = pt.get_by_name("a").unwrap();
+      let b = pt.get_by_name("b").unwrap();
+      assert!(builder.claim(cx, Pin("0".to_string(), 2), a.path_span));
+      assert!(builder.claim(cx, Pin("0".to_string(), 3), b.path_span));
+      assert!(builder.claim(cx, Peripheral("P0.2".to_string()), b.path_span));
+      assert!(unsafe{*failed} == false);
+
+      assert!(!builder.claim(cx, Pin("0".to_string(), 2), b.path_span));
+      assert!(unsafe{*failed} == true);
+    });
+  }
 }
@@ -24,9 +24,28 @@ use syntax::owned_slice::OwnedSlice;
 use syntax::parse::token::intern;
 use syntax::ptr::P;
 
-use builder::meta_args::{ToTyHash, set_ty_params_for_task};
+use builder::meta_args::{ToTyHash, get_ty_params_for_task, set_ty_params_for_task};
 use node;
-use super::{Builder, TokenString, add_node_dependency};
+use super::{Builder, Task, TokenString, add_node_dependency};
+
+/// Maximum number of tasks, mirrors `os::task::MaxTasksCount`.
+static MAX_TASKS_COUNT: uint = 4;
+
+/// Bytes of privileged stack the scheduler reserves below the main stack,
+/// mirrors `os::task::ReservedPivilegedStackSize`.
+static RESERVED_PRIVILEGED_STACK_SIZE: uint = 256;
+
+/// Bytes added to each task stack for saved registers and the `__morestack`
+/// scratch pad, see `os::task::define_task`.
+static TASK_STACK_OVERHEAD: uint = 3 * 8 * 4;
+
+/// RAM size of supported MCUs, as given in their `layout.ld`.
+static MCU_RAM_SIZES: &'static [(&'static str, uint)] = &[
+  ("lpc17xx", 8 * 1024),
+  ("k20", 8 * 1024),
+  ("stm32f4", 128 * 1024),
+  ("tiva_c", 32 * 1024),
+];
 
 pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(verify));
@@ -38,18 +57,35 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
     task_node.materializer.set(Some(build_single_task));
     add_node_dependency(&node, &task_node);
     add_node_dependency(&task_node, &mcu_node);
+    add_args_dependencies(builder, &task_node);
+  }
 
-    let maybe_args_node = task_node.get_by_path("args");
-    if maybe_args_node.is_some() {
-      let args_node = maybe_args_node.unwrap();
-      for (_, ref attr) in args_node.attributes.borrow().iter() {
-        match attr.value {
-          node::RefValue(ref refname) => {
-            let refnode = builder.pt.get_by_name(refname.as_slice()).unwrap();
-            add_node_dependency(&task_node, &refnode);
-          },
-          _ => (),
-        }
+  let maybe_tasks_node = node.get_by_path("tasks");
+  if maybe_tasks_node.is_some() {
+    let tasks_node = maybe_tasks_node.unwrap();
+    tasks_node.materializer.set(Some(build_tasks));
+    add_node_dependency(&node, &tasks_node);
+    add_node_dependency(&tasks_node, &mcu_node);
+    for task_node in tasks_node.subnodes().iter() {
+      task_node.materializer.set(Some(build_task));
+      add_node_dependency(&tasks_node, task_node);
+      add_node_dependency(task_node, &mcu_node);
+      add_args_dependencies(builder, task_node);
+    }
+  }
+}
+
+fn add_args_dependencies(builder: &Builder, task_node: &Rc<node::Node>) {
+  let maybe_args_node = task_node.get_by_path("args");
+  if maybe_args_node.is_some() {
+    let args_node = maybe_args_node.unwrap();
+    for (_, ref attr) in args_node.attributes.borrow().iter() {
+      match attr.value {
+        node::RefValue(ref refname) => {
+          let refnode = builder.pt.get_by_name(refname.as_slice()).unwrap();
+          add_node_dependency(task_node, &refnode);
+        },
+        _ => (),
       }
     }
   }
@@ -57,10 +93,17 @@ pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
 
 pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
-  node.expect_subnodes(cx, ["single_task"]);
-  if node.get_by_path("single_task").is_none() {
-    cx.parse_sess().span_diagnostic.span_err(node.name_span,
-        "subnode `single_task` must be present");
+  node.expect_subnodes(cx, ["single_task", "tasks"]);
+  match (node.get_by_path("single_task"), node.get_by_path("tasks")) {
+    (None, None) => {
+      cx.parse_sess().span_diagnostic.span_err(node.name_span,
+          "subnode `single_task` or `tasks` must be present");
+    },
+    (Some(_), Some(tasks)) => {
+      cx.parse_sess().span_diagnostic.span_err(tasks.name_span,
+          "subnode `tasks` can't be used together with subnode `single_task`");
+    },
+    _ => (),
   }
 }
 
@@ -71,12 +114,25 @@ fn build_single_task(builder: &mut Builder, cx: &mut ExtCtxt,
     Some(loop_fn) => {
       let args_node = node.get_by_path("args");
       let args = match args_node.and_then(|args| {
-        Some(build_args(builder, cx, &loop_fn, args))
+        Some(cx.expr_addr_of(DUMMY_SP, build_args(builder, cx, &loop_fn, args)))
       }) {
         None => vec!(),
         Some(arg) => vec!(arg),
       };
 
+      // the optional start function runs once before the loop, with the
+      // same args
+      match node.get_string_attr("start") {
+        Some(start_fn) => {
+          let start_expr = cx.expr_call_ident(
+              node.get_attr("start").value_span,
+              cx.ident_of(start_fn.as_slice()),
+              args.clone());
+          builder.add_main_statement(cx.stmt_expr(start_expr));
+        },
+        None => (),
+      }
+
       let call_expr = cx.expr_call_ident(
           node.get_attr("loop").value_span,
           cx.ident_of(loop_fn.as_slice()),
@@ -88,6 +144,141 @@ fn build_single_task(builder: &mut Builder, cx: &mut ExtCtxt,
   }
 }
 
+fn build_task(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  if !node.expect_subnodes(cx, ["args"]) || !node.expect_attributes(cx, [
+      ("loop", node::StrAttribute),
+      ("stack_size", node::IntAttribute)]) {
+    return;
+  }
+
+  let loop_fn = node.get_string_attr("loop").unwrap();
+  // the task name is used for both the args struct and the trampoline, so
+  // every task needs a loop function of its own
+  if !builder.claim(cx, Task(loop_fn.clone()),
+      node.get_attr("loop").value_span) {
+    return;
+  }
+
+  let loop_ident = cx.ident_of(loop_fn.as_slice());
+  let task_ident = cx.ident_of(format!("{}_task", loop_fn).as_slice());
+  let args_ident = cx.ident_of(format!("{}_args", loop_fn).as_slice());
+
+  let trampoline = match node.get_by_path("args") {
+    Some(args_node) => {
+      let args = build_args(builder, cx, &loop_fn, args_node.clone());
+      if !get_ty_params_for_task(cx, loop_fn.as_slice()).is_empty() {
+        cx.parse_sess().span_diagnostic.span_err(args_node.name_span,
+            "task args can't reference nodes with type parameters");
+        return;
+      }
+      builder.add_main_statement(cx.stmt_let(DUMMY_SP, false, args_ident, args));
+
+      quote_stmt!(&*cx,
+          fn $task_ident(args: u32) {
+            let args = unsafe { &*(args as *const pt::$args_ident) };
+            loop {
+              $loop_ident(args);
+            }
+          }
+      )
+    },
+    None => {
+      quote_stmt!(&*cx,
+          fn $task_ident(_: u32) {
+            loop {
+              $loop_ident();
+            }
+          }
+      )
+    },
+  };
+  builder.add_main_statement(trampoline);
+}
+
+fn build_tasks(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+
+  let mut ok = true;
+  let mut tasks = vec!();
+  for sub in node.subnodes().iter() {
+    match from_str::<uint>(sub.path.as_slice()) {
+      Some(index) => tasks.push((index, sub.clone())),
+      None => {
+        ok = false;
+        cx.parse_sess().span_diagnostic.span_err(sub.path_span,
+            format!("task path `{}` must be an integer", sub.path).as_slice());
+      },
+    }
+  }
+
+  if node.subnodes().is_empty() {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "at least one task must be defined");
+    return;
+  }
+  if node.subnodes().len() > MAX_TASKS_COUNT {
+    ok = false;
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        format!("{} tasks defined, the scheduler supports at most {}",
+            node.subnodes().len(), MAX_TASKS_COUNT).as_slice());
+  }
+
+  // the task with the lowest path is started first
+  tasks.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
+
+  let mut stacks_size = RESERVED_PRIVILEGED_STACK_SIZE;
+  let mut task_exprs = vec!();
+  for &(_, ref sub) in tasks.iter() {
+    let (loop_fn, stack_size) = match (sub.get_string_attr("loop"),
+        sub.get_int_attr("stack_size")) {
+      (Some(loop_fn), Some(stack_size)) => (loop_fn, stack_size),
+      _ => {
+        // already reported by build_task
+        ok = false;
+        continue;
+      },
+    };
+    stacks_size += (stack_size + TASK_STACK_OVERHEAD) & !0b1111;
+
+    let task_ident = cx.ident_of(format!("{}_task", loop_fn).as_slice());
+    let arg = if sub.get_by_path("args").is_some() {
+      let args_ident = cx.ident_of(format!("{}_args", loop_fn).as_slice());
+      quote_expr!(&*cx, &$args_ident as *const pt::$args_ident as u32)
+    } else {
+      quote_expr!(&*cx, 0u32)
+    };
+    let stack: u32 = stack_size as u32;
+    task_exprs.push(quote_expr!(&*cx, ($task_ident, $arg, $stack)));
+  }
+
+  let ram_size = builder.pt.get_by_path("mcu")
+      .and_then(|mcu| mcu.name.clone())
+      .and_then(|name| MCU_RAM_SIZES.iter()
+          .find(|&&(mcu, _)| mcu == name.as_slice())
+          .map(|&(_, size)| size));
+  match ram_size {
+    Some(ram_size) if stacks_size > ram_size => {
+      ok = false;
+      cx.parse_sess().span_diagnostic.span_err(node.name_span,
+          format!("task stacks need {} bytes, including {} bytes of \
+              privileged stack, but the mcu has only {} bytes of RAM",
+              stacks_size, RESERVED_PRIVILEGED_STACK_SIZE,
+              ram_size).as_slice());
+    },
+    _ => (),
+  }
+
+  if !ok {
+    return;
+  }
+
+  let tasks_expr = cx.expr_vec_slice(DUMMY_SP, task_exprs);
+  let st = quote_stmt!(&*cx,
+      zinc::os::task::setup_tasks($tasks_expr);
+  );
+  builder.add_main_statement(st);
+}
+
 fn build_args(builder: &mut Builder, cx: &mut ExtCtxt,
     struct_name: &String, node: Rc<node::Node>) -> P<ast::Expr> {
   let mut fields = vec!();
@@ -105,9 +296,18 @@ fn build_args(builder: &mut Builder, cx: &mut ExtCtxt,
     let v = &(*node_attr)[*k];
 
     let (ty, val) = match v.value {
-      node::IntValue(i) =>
+      node::IntValue(i) | node::FrequencyValue(i) | node::BaudRateValue(i) |
+      node::DurationValue(i) =>
         (cx.ty_ident(DUMMY_SP, cx.ident_of("u32")),
             quote_expr!(&*cx, $i)),
+      node::BoolValue(b) =>
+        (cx.ty_ident(DUMMY_SP, cx.ident_of("bool")),
+            cx.expr_bool(DUMMY_SP, b)),
+      node::ListValue(_) => {
+        cx.parse_sess().span_diagnostic.span_err(v.value_span,
+            "list attributes can't be passed as task args");
+        continue;
+      },
       node::StrValue(ref string)  => {
         let static_lifetime = cx.lifetime(DUMMY_SP, intern("'static"));
         let val_slice = string.as_slice();
@@ -184,11 +384,10 @@ fn build_args(builder: &mut Builder, cx: &mut ExtCtxt,
   });
   builder.add_type_item(struct_item);
 
-  cx.expr_addr_of(DUMMY_SP,
-      cx.expr_struct(
-          DUMMY_SP,
-          cx.path(DUMMY_SP, vec!(cx.ident_of("pt"), name_ident)),
-          expr_fields))
+  cx.expr_struct(
+      DUMMY_SP,
+      cx.path(DUMMY_SP, vec!(cx.ident_of("pt"), name_ident)),
+      expr_fields)
 }
 
 fn type_name_as_path(cx: &ExtCtxt, ty: &str, params: Vec<String>) -> ast::Path {
@@ -216,7 +415,7 @@ mod test {
   use syntax::ext::build::AstBuilder;
 
   use builder::Builder;
-  use super::build_single_task;
+  use super::{build_single_task, build_task, build_tasks};
   use test_helpers::{assert_equal_source, with_parsed};
 
   #[test]
@@ -237,6 +436,26 @@ mod test {
     });
   }
 
+  #[test]
+  fn builds_single_task_start() {
+    with_parsed("
+      single_task {
+        start = \"init\";
+        loop = \"run\";
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_single_task(&mut builder, cx, pt.get_by_path("single_task").unwrap().clone());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts.len() == 2);
+
+      assert_equal_source(builder.main_stmts[0].deref(), "init();");
+      assert_equal_source(builder.main_stmts[1].deref(),
+          "loop {
+            run();
+          }");
+    });
+  }
+
   #[test]
   fn builds_single_task_with_args() {
     with_parsed("
@@ -276,4 +495,115 @@ mod test {
           }");
     });
   }
+
+  #[test]
+  fn builds_tasks() {
+    with_parsed("
+      lpc17xx@mcu {}
+
+      tasks {
+        report@2 {
+          loop = \"report\";
+          stack_size = 256;
+        }
+        blink@1 {
+          loop = \"blink\";
+          stack_size = 512;
+          args {
+            led = &led1;
+          }
+        }
+      }
+
+      led1@ref;
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      pt.get_by_path("ref").unwrap().set_type_name("hello::world::Struct".to_string());
+
+      let tasks = pt.get_by_path("tasks").unwrap();
+      build_task(&mut builder, cx, tasks.get_by_path("1").unwrap());
+      build_task(&mut builder, cx, tasks.get_by_path("2").unwrap());
+      build_tasks(&mut builder, cx, tasks);
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts.len() == 4);
+      assert!(builder.type_items.len() == 1);
+
+      assert_equal_source(builder.main_stmts[0].deref(),
+          "let blink_args = pt::blink_args {
+            led: &led1,
+          };");
+      assert_equal_source(builder.main_stmts[1].deref(),
+          "fn blink_task(args: u32) {
+            let args = unsafe { &*(args as *const pt::blink_args) };
+            loop {
+              blink(args);
+            }
+          }");
+      assert_equal_source(builder.main_stmts[2].deref(),
+          "fn report_task(_: u32) {
+            loop {
+              report();
+            }
+          }");
+      assert_equal_source(builder.main_stmts[3].deref(),
+          "zinc::os::task::setup_tasks(&[
+            (blink_task, &blink_args as *const pt::blink_args as u32, 512u32),
+            (report_task, 0u32, 256u32),
+          ]);");
+    });
+  }
+
+  #[test]
+  fn fails_to_build_tasks_sharing_loop() {
+    with_parsed("
+      tasks {
+        a@1 {
+          loop = \"run\";
+          stack_size = 256;
+        }
+        b@2 {
+          loop = \"run\";
+          stack_size = 256;
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      let tasks = pt.get_by_path("tasks").unwrap();
+      build_task(&mut builder, cx, tasks.get_by_path("1").unwrap());
+      build_task(&mut builder, cx, tasks.get_by_path("2").unwrap());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn fails_to_build_too_many_tasks() {
+    with_parsed("
+      tasks {
+        a@1 { loop = \"a\"; stack_size = 256; }
+        b@2 { loop = \"b\"; stack_size = 256; }
+        c@3 { loop = \"c\"; stack_size = 256; }
+        d@4 { loop = \"d\"; stack_size = 256; }
+        e@5 { loop = \"e\"; stack_size = 256; }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_tasks(&mut builder, cx, pt.get_by_path("tasks").unwrap());
+      assert!(unsafe{*failed} == true);
+      assert!(builder.main_stmts.len() == 0);
+    });
+  }
+
+  #[test]
+  fn fails_to_build_tasks_over_ram_size() {
+    with_parsed("
+      lpc17xx@mcu {}
+
+      tasks {
+        a@1 { loop = \"a\"; stack_size = 4096; }
+        b@2 { loop = \"b\"; stack_size = 4096; }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      build_tasks(&mut builder, cx, pt.get_by_path("tasks").unwrap());
+      assert!(unsafe{*failed} == true);
+      assert!(builder.main_stmts.len() == 0);
+    });
+  }
 }
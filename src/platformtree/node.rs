@@ -24,12 +24,20 @@ use builder::Builder;
 
 /// Holds a value for an attribute.
 ///
-/// The value can be an unsigned integer, string or reference.
+/// The value can be an unsigned integer, string, reference, boolean, a number
+/// with unit or a list of values. Numbers with units are stored in the unit's
+/// base: Hz for frequencies, baud for baud rates and microseconds for
+/// durations.
 #[deriving(Clone)]
 pub enum AttributeValue {
   IntValue(uint),
   StrValue(String),
   RefValue(String),
+  BoolValue(bool),
+  FrequencyValue(uint),
+  BaudRateValue(uint),
+  DurationValue(uint),
+  ListValue(Vec<AttributeValue>),
 }
 
 /// Expected attribute type.
@@ -40,6 +48,11 @@ pub enum AttributeType {
   IntAttribute,
   StrAttribute,
   RefAttribute,
+  BoolAttribute,
+  FrequencyAttribute,
+  BaudRateAttribute,
+  DurationAttribute,
+  ListAttribute,
 }
 
 /// Attribute value and metadata.
@@ -292,6 +305,66 @@ impl Node {
     })
   }
 
+  /// Returns a boolean attribute by name or None, if it's not present or not
+  /// of a BoolAttribute type.
+  pub fn get_bool_attr(&self, key: &str) -> Option<bool> {
+    self.attributes.borrow().find(&key.to_string()).and_then(|av| match av.value {
+      BoolValue(b) => Some(b),
+      _ => None,
+    })
+  }
+
+  /// Returns a frequency attribute in Hz by name or None, if it's not present
+  /// or not of a FrequencyAttribute type. Integers are taken as Hz.
+  pub fn get_frequency_attr(&self, key: &str) -> Option<uint> {
+    self.attributes.borrow().find(&key.to_string()).and_then(|av| match av.value {
+      IntValue(u) | FrequencyValue(u) => Some(u),
+      _ => None,
+    })
+  }
+
+  /// Returns a baud rate attribute by name or None, if it's not present or not
+  /// of a BaudRateAttribute type. Integers are taken as baud.
+  pub fn get_baud_rate_attr(&self, key: &str) -> Option<uint> {
+    self.attributes.borrow().find(&key.to_string()).and_then(|av| match av.value {
+      IntValue(u) | BaudRateValue(u) => Some(u),
+      _ => None,
+    })
+  }
+
+  /// Returns a duration attribute in microseconds by name or None, if it's not
+  /// present or not of a DurationAttribute type.
+  pub fn get_duration_attr(&self, key: &str) -> Option<uint> {
+    self.attributes.borrow().find(&key.to_string()).and_then(|av| match av.value {
+      DurationValue(u) => Some(u),
+      _ => None,
+    })
+  }
+
+  /// Returns a list attribute by name or None, if it's not present or not of a
+  /// ListAttribute type.
+  pub fn get_list_attr(&self, key: &str) -> Option<Vec<AttributeValue>> {
+    self.attributes.borrow().find(&key.to_string()).and_then(|av| match av.value {
+      ListValue(ref l) => Some(l.clone()),
+      _ => None,
+    })
+  }
+
+  /// Returns a list of references by name or None, if it's not present, not of
+  /// a ListAttribute type or any of the values is not a reference.
+  pub fn get_ref_list_attr(&self, key: &str) -> Option<Vec<String>> {
+    self.get_list_attr(key).and_then(|l| {
+      let mut refs = vec!();
+      for v in l.iter() {
+        match *v {
+          RefValue(ref s) => refs.push(s.clone()),
+          _ => return None,
+        }
+      }
+      Some(refs)
+    })
+  }
+
   /// Returns a string attribute by name or None, if it's not present or not of
   /// a StrAttribute type. Reports a parser error if an attribute is
   /// missing.
@@ -340,6 +413,86 @@ impl Node {
     }
   }
 
+  /// Returns a boolean attribute by name or None, if it's not present or not
+  /// of a BoolAttribute type. Reports a parser error if an attribute is
+  /// missing.
+  pub fn get_required_bool_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<bool> {
+    match self.get_bool_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required boolean attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns a frequency attribute by name or None, if it's not present or not
+  /// of a FrequencyAttribute type. Reports a parser error if an attribute is
+  /// missing.
+  pub fn get_required_frequency_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<uint> {
+    match self.get_frequency_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required frequency attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns a baud rate attribute by name or None, if it's not present or not
+  /// of a BaudRateAttribute type. Reports a parser error if an attribute is
+  /// missing.
+  pub fn get_required_baud_rate_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<uint> {
+    match self.get_baud_rate_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required baud rate attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns a duration attribute by name or None, if it's not present or not
+  /// of a DurationAttribute type. Reports a parser error if an attribute is
+  /// missing.
+  pub fn get_required_duration_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<uint> {
+    match self.get_duration_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required duration attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
+  /// Returns a list attribute by name or None, if it's not present or not
+  /// of a ListAttribute type. Reports a parser error if an attribute is
+  /// missing.
+  pub fn get_required_list_attr(&self, cx: &ExtCtxt, key: &str)
+      -> Option<Vec<AttributeValue>> {
+    match self.get_list_attr(key) {
+      Some(val) => Some(val),
+      None => {
+        cx.parse_sess().span_diagnostic.span_err(self.name_span,
+            format!("required list attribute `{}` is missing", key)
+            .as_slice());
+        None
+      }
+    }
+  }
+
   /// Returns true if node has no attributes. Returs false and reports a parser
   /// error for each found attribute otherwise.
   pub fn expect_no_attributes(&self, cx: &ExtCtxt) -> bool {
@@ -380,6 +533,21 @@ impl Node {
         &RefAttribute => {
           if self.get_required_ref_attr(cx, n).is_none() {ok = false}
         },
+        &BoolAttribute => {
+          if self.get_required_bool_attr(cx, n).is_none() {ok = false}
+        },
+        &FrequencyAttribute => {
+          if self.get_required_frequency_attr(cx, n).is_none() {ok = false}
+        },
+        &BaudRateAttribute => {
+          if self.get_required_baud_rate_attr(cx, n).is_none() {ok = false}
+        },
+        &DurationAttribute => {
+          if self.get_required_duration_attr(cx, n).is_none() {ok = false}
+        },
+        &ListAttribute => {
+          if self.get_required_list_attr(cx, n).is_none() {ok = false}
+        },
       }
     }
     ok
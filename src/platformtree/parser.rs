@@ -14,12 +14,16 @@
 // limitations under the License.
 
 use std::collections::hashmap::HashMap;
+use std::num::CheckedMul;
 use std::rc::{Rc, Weak};
+use std::u32;
 use syntax::ast::{TokenTree, LitInt, UnsuffixedIntLit};
 use syntax::codemap::{Span, mk_sp};
 use syntax::ext::base::ExtCtxt;
 use syntax::parse::{token, ParseSess, lexer, integer_lit};
+use syntax::parse;
 
+use boards;
 use node;
 
 pub struct Parser<'a> {
@@ -34,7 +38,10 @@ pub struct Parser<'a> {
 
 impl<'a> Parser<'a> {
   pub fn new<'a>(cx: &'a ExtCtxt, tts: &[TokenTree]) -> Parser<'a> {
-    let sess = cx.parse_sess();
+    Parser::new_with_sess(cx.parse_sess(), tts)
+  }
+
+  fn new_with_sess<'a>(sess: &'a ParseSess, tts: &[TokenTree]) -> Parser<'a> {
     let ttsvec = tts.iter().map(|x| (*x).clone()).collect();
     let mut reader = box lexer::new_tt_reader(
         &sess.span_diagnostic, None, ttsvec) as Box<lexer::Reader>;
@@ -57,6 +64,50 @@ impl<'a> Parser<'a> {
 
   /// Parse the platform tree from passed in tokens.
   pub fn parse_platformtree(&mut self) -> Option<Rc<node::PlatformTree>> {
+    let nodes = match self.parse_root_nodes().and_then(|nodes| {
+      self.import_board(nodes)
+    }) {
+      Some(nodes) => nodes,
+      None => return None,
+    };
+
+    let mut map = HashMap::new();
+    if !self.collect_node_names(&mut map, &nodes) {
+      return None;
+    }
+    let roots = nodes.values().map(|node| node.clone()).collect();
+    if self.check_refs(&map, &roots) {
+      Some(Rc::new(node::PlatformTree::new(nodes, map)))
+    } else {
+      None
+    }
+  }
+
+  /// Verifies that every reference, including the ones in lists, names a
+  /// node.
+  fn check_refs(&self, map: &HashMap<String, Weak<node::Node>>,
+      nodes: &Vec<Rc<node::Node>>) -> bool {
+    let mut ok = true;
+    for n in nodes.iter() {
+      for (_, attr) in n.attributes.borrow().iter() {
+        let mut refs = vec!();
+        collect_refs(&attr.value, &mut refs);
+        for name in refs.iter() {
+          if !map.contains_key(name) {
+            ok = false;
+            self.sess.span_diagnostic.span_err(attr.value_span,
+                format!("unknown node `{}`", name).as_slice());
+          }
+        }
+      }
+      if !self.check_refs(map, &n.subnodes()) {
+        ok = false;
+      }
+    }
+    ok
+  }
+
+  fn parse_root_nodes(&mut self) -> Option<HashMap<String, Rc<node::Node>>> {
     let mut nodes: HashMap<String, Rc<node::Node>> = HashMap::new();
     let mut failed = false;
     loop {
@@ -89,13 +140,62 @@ impl<'a> Parser<'a> {
     if failed {
       None
     } else {
-      let mut map = HashMap::new();
-      if self.collect_node_names(&mut map, &nodes) {
-        Some(Rc::new(node::PlatformTree::new(nodes, map)))
-      } else {
-        None
+      Some(nodes)
+    }
+  }
+
+  /// Replaces the `board` root node, if present, with nodes of the named board
+  /// merged with the other root nodes.
+  fn import_board(&self, mut nodes: HashMap<String, Rc<node::Node>>)
+      -> Option<HashMap<String, Rc<node::Node>>> {
+    let board_node = match nodes.pop(&"board".to_string()) {
+      Some(node) => node,
+      None => return Some(nodes),
+    };
+
+    let name = match board_node.name {
+      Some(ref name) => name.clone(),
+      None => {
+        self.sess.span_diagnostic.span_err(board_node.name_span,
+            "board node must have a name");
+        return None;
+      },
+    };
+    if !board_node.attributes.borrow().is_empty() ||
+       !board_node.subnodes().is_empty() {
+      self.sess.span_diagnostic.span_err(board_node.path_span,
+          "board node can't have attributes or subnodes");
+      return None;
+    }
+
+    let source = match boards::get(name.as_slice()) {
+      Some(source) => source,
+      None => {
+        self.sess.span_diagnostic.span_err(board_node.name_span,
+            format!("unknown board `{}`, allowed values: {}", name,
+                boards::names().as_slice().connect(", ")).as_slice());
+        return None;
+      },
+    };
+    let tts = parse::parse_tts_from_source_str(format!("<board {}>", name),
+        source.to_string(), vec!(), self.sess);
+    let board_nodes = match Parser::new_with_sess(self.sess, tts.as_slice())
+        .parse_root_nodes() {
+      Some(nodes) => nodes,
+      None => return None,
+    };
+
+    let mut merged = HashMap::new();
+    for (path, node) in board_nodes.iter() {
+      merged.insert(path.clone(),
+          merge_node(node, nodes.find(path), None));
+    }
+    for (path, node) in nodes.into_iter() {
+      if !merged.contains_key(&path) {
+        merged.insert(path, node);
       }
     }
+    Some(merged)
   }
 
   fn collect_node_names(&self, map: &mut HashMap<String, Weak<node::Node>>,
@@ -340,7 +440,10 @@ impl<'a> Parser<'a> {
         match lit {
           LitInt(i, UnsuffixedIntLit(_)) => {
             self.bump();
-            Some(node::IntValue(i as uint))
+            match self.token {
+              token::IDENT(_, _) => self.parse_unit(i as uint),
+              _ => Some(node::IntValue(i as uint)),
+            }
           },
           _ => {
             self.error(format!("expected unsuffixed integer but found `{}`",
@@ -357,6 +460,41 @@ impl<'a> Parser<'a> {
         };
         Some(node::RefValue(name))
       },
+      token::IDENT(_, _) => {
+        let value = match token::to_string(&self.token).as_slice() {
+          "true" => true,
+          "false" => false,
+          other => {
+            self.error(format!("expected attribute value but found `{}`",
+                other));
+            return None;
+          }
+        };
+        self.bump();
+        Some(node::BoolValue(value))
+      },
+      token::LBRACKET => {
+        self.bump();
+        let mut values = vec!();
+        loop {
+          if self.token == token::RBRACKET {
+            break;
+          }
+          match self.parse_attribute_value() {
+            Some(value) => values.push(value),
+            None => return None,
+          }
+          if self.token == token::COMMA {
+            self.bump();
+          } else {
+            break;
+          }
+        }
+        if !self.expect(&token::RBRACKET) {
+          return None;
+        }
+        Some(node::ListValue(values))
+      },
       ref other => {
         self.error(format!("expected attribute value but found `{}`",
             token::to_string(other)));
@@ -365,6 +503,36 @@ impl<'a> Parser<'a> {
     }
   }
 
+  /// Parses the unit of a number, converting the value to the unit's base.
+  fn parse_unit(&mut self, value: uint) -> Option<node::AttributeValue> {
+    let unit = token::to_string(&self.token);
+    let scale = match unit.as_slice() {
+      "Hz" | "baud" | "us" => 1,
+      "kHz" | "ms"         => 1_000,
+      "MHz" | "s"          => 1_000_000,
+      other => {
+        self.error(format!("unknown unit `{}`, allowed values: `Hz`, `kHz`, \
+            `MHz`, `baud`, `s`, `ms`, `us`", other));
+        return None;
+      }
+    };
+    // values end up in u32 registers and task args
+    let scaled = match value.checked_mul(&scale) {
+      Some(scaled) if scaled <= u32::MAX as uint => scaled,
+      _ => {
+        self.error(format!("`{}{}` doesn't fit in 32 bits", value, unit));
+        return None;
+      }
+    };
+    let attr_value = match unit.as_slice() {
+      "Hz" | "kHz" | "MHz" => node::FrequencyValue(scaled),
+      "baud"               => node::BaudRateValue(scaled),
+      _                    => node::DurationValue(scaled),
+    };
+    self.bump();
+    Some(attr_value)
+  }
+
   fn error(&self, m: String) {
     self.sess.span_diagnostic.span_err(self.span, m.as_slice());
   }
@@ -417,3 +585,57 @@ impl<'a> Parser<'a> {
     }
   }
 }
+
+/// Builds a copy of `base` with attributes and subnodes of `over` merged in.
+///
+/// Attributes of `over` take precedence, subnodes with the same path are
+/// merged recursively.
+fn merge_node(base: &Rc<node::Node>, over: Option<&Rc<node::Node>>,
+    parent: Option<Weak<node::Node>>) -> Rc<node::Node> {
+  let (name, name_span, path_span) = match over {
+    Some(over) if over.name.is_some() =>
+      (over.name.clone(), over.name_span, over.path_span),
+    Some(over) => (base.name.clone(), base.name_span, over.path_span),
+    None => (base.name.clone(), base.name_span, base.path_span),
+  };
+  let node = Rc::new(node::Node::new(
+      name, name_span, base.path.clone(), path_span, parent));
+  let weak_node = node.downgrade();
+
+  let mut attributes = base.attributes.borrow().clone();
+  let mut subnodes = node::Subnodes::new();
+  for sub in base.subnodes().iter() {
+    let over_sub = over.and_then(|over| over.get_by_path(sub.path.as_slice()));
+    subnodes.push(merge_node(sub, over_sub.as_ref(), Some(weak_node.clone())));
+  }
+  match over {
+    Some(over) => {
+      for (k, v) in over.attributes.borrow().iter() {
+        attributes.insert(k.clone(), v.clone());
+      }
+      for sub in over.subnodes().iter() {
+        if base.get_by_path(sub.path.as_slice()).is_none() {
+          subnodes.push(merge_node(sub, None, Some(weak_node.clone())));
+        }
+      }
+    },
+    None => (),
+  }
+
+  node.attributes.borrow_mut().clone_from(&attributes);
+  node.set_subnodes(subnodes);
+  node
+}
+
+/// Collects the names of the nodes referenced by an attribute value.
+fn collect_refs(value: &node::AttributeValue, refs: &mut Vec<String>) {
+  match *value {
+    node::RefValue(ref name) => refs.push(name.clone()),
+    node::ListValue(ref values) => {
+      for v in values.iter() {
+        collect_refs(v, refs);
+      }
+    },
+    _ => (),
+  }
+}
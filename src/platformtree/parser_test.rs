@@ -75,7 +75,7 @@ fn parse_node_with_numeric_path() {
 
 #[test]
 fn parse_attributes() {
-  with_parsed_node("root", "test@root { a = \"value\"; b = 1; c = &ref; }", |node| {
+  with_parsed_node("root", "test@root { a = \"value\"; b = 1; c = &ref; } ref@r;", |node| {
     assert!(node.get_string_attr("a") == Some("value".to_string()));
     assert!(node.get_int_attr("b")    == Some(1));
     assert!(node.get_ref_attr("c")    == Some("ref".to_string()));
@@ -98,11 +98,75 @@ fn parse_integer_attribute() {
 
 #[test]
 fn parse_ref_attribute() {
-  with_parsed_node("root", "test@root { key = &ref; }", |node| {
+  with_parsed_node("root", "test@root { key = &ref; } ref@r;", |node| {
     assert!(node.get_ref_attr("key") == Some("ref".to_string()));
   });
 }
 
+#[test]
+fn parse_bool_attribute() {
+  with_parsed_node("root", "test@root { a = true; b = false; }", |node| {
+    assert!(node.get_bool_attr("a") == Some(true));
+    assert!(node.get_bool_attr("b") == Some(false));
+  });
+}
+
+#[test]
+fn parse_unit_attributes() {
+  with_parsed_node("root", "test@root {
+      hz = 32768Hz; khz = 32kHz; mhz = 12MHz; plain = 1000;
+      baud = 115200baud;
+      s = 2s; ms = 10ms; us = 50us;
+    }", |node| {
+    assert!(node.get_frequency_attr("hz")     == Some(32768));
+    assert!(node.get_frequency_attr("khz")    == Some(32_000));
+    assert!(node.get_frequency_attr("mhz")    == Some(12_000_000));
+    assert!(node.get_frequency_attr("plain")  == Some(1000));
+    assert!(node.get_baud_rate_attr("baud")   == Some(115200));
+    assert!(node.get_baud_rate_attr("plain")  == Some(1000));
+    assert!(node.get_duration_attr("s")       == Some(2_000_000));
+    assert!(node.get_duration_attr("ms")      == Some(10_000));
+    assert!(node.get_duration_attr("us")      == Some(50));
+
+    assert!(node.get_int_attr("mhz")          == None);
+    assert!(node.get_frequency_attr("baud")   == None);
+    assert!(node.get_duration_attr("plain")   == None);
+  });
+}
+
+#[test]
+fn parse_list_attribute() {
+  with_parsed_node("root", "test@root { a = [&x, &y]; b = [1, 2,]; c = []; }
+      x@x; y@y;",
+      |node| {
+    assert!(node.get_ref_list_attr("a") ==
+        Some(vec!("x".to_string(), "y".to_string())));
+    assert!(node.get_ref_list_attr("b") == None);
+    assert!(node.get_list_attr("b").unwrap().len() == 2);
+    assert!(node.get_list_attr("c").unwrap().len() == 0);
+  });
+}
+
+#[test]
+fn fails_to_parse_bad_typed_attributes() {
+  fails_to_parse("test@root { k = 10GHz; }");
+  fails_to_parse("test@root { k = True; }");
+  fails_to_parse("test@root { k = [1 2]; }");
+}
+
+#[test]
+fn fails_to_parse_values_overflowing_units() {
+  fails_to_parse("test@root { k = 5000MHz; }");
+  fails_to_parse("test@root { k = 4295s; }");
+  fails_to_parse("test@root { k = 4294967296Hz; }");
+}
+
+#[test]
+fn fails_to_parse_unknown_refs() {
+  fails_to_parse("test@root { k = &missing; }");
+  fails_to_parse("test@root { k = [&test, &missing]; }");
+}
+
 #[test]
 fn fails_to_parse_duplicate_attributes() {
   fails_to_parse("test@root { a = 1; a = \"2\"; }");
@@ -153,3 +217,46 @@ fn tracks_nodes_by_name() {
 fn fails_to_parse_duplicate_node_names() {
   fails_to_parse("duplicate@root { duplicate@child; }");
 }
+
+#[test]
+fn imports_board() {
+  with_parsed("
+    mbed_lpc1768@board;
+
+    mcu {
+      gpio {
+        0 {
+          lcd_cs@18 { direction = \"out\"; }
+        }
+        1 {
+          led1@18 { direction = \"in\"; }
+        }
+      }
+    }
+    os;", |_, failed, pt| {
+    assert!(unsafe{*failed} == false);
+    assert!(pt.get_by_path("board").is_none());
+    assert!(pt.get_by_path("os").is_some());
+
+    let mcu = pt.get_by_path("mcu").unwrap();
+    assert!(mcu.name == Some("lpc17xx".to_string()));
+    assert!(mcu.get_by_path("clock").unwrap()
+        .get_frequency_attr("source_frequency") == Some(12_000_000));
+
+    let led1 = pt.get_by_name("led1").unwrap();
+    assert!(led1.get_string_attr("direction") == Some("in".to_string()));
+    assert!(pt.get_by_name("led4").is_some());
+
+    let lcd_cs = pt.get_by_name("lcd_cs").unwrap();
+    let port = lcd_cs.parent.clone().unwrap().upgrade().unwrap();
+    assert!(port.path == "0".to_string());
+    assert!(port.get_by_path("2").is_some());
+  });
+}
+
+#[test]
+fn fails_to_parse_bad_board() {
+  fails_to_parse("board;");
+  fails_to_parse("unknown@board;");
+  fails_to_parse("mbed_lpc1768@board { key = 1; }");
+}
@@ -32,9 +32,14 @@ pub mod builder;
 pub mod node;
 pub mod parser;
 
+mod boards;
+
 #[path="../zinc/hal/lpc17xx/platformtree.rs"] mod lpc17xx_pt;
 #[path="../zinc/hal/tiva_c/platformtree.rs"] mod tiva_c_pt;
+#[path="../zinc/hal/stm32f4/platformtree.rs"] mod stm32f4_pt;
+#[path="../zinc/hal/k20/platformtree.rs"] mod k20_pt;
 #[path="../zinc/drivers/drivers_pt.rs"] mod drivers_pt;
+#[path="../zinc/hal/uart_pt.rs"] mod uart_pt;
 
 #[cfg(test)] mod test_helpers;
 #[cfg(test)] mod parser_test;
@@ -37,7 +37,7 @@ mod reg {
     0xc        => reg32 rbar {     //! Region base address register
       0..3     => region,
       4        => valid,
-      5..31    => addr,
+      5..31    => base,
     }
     0x10       => reg32 rasr {     //! Region attribute and size register
       0        => enable,
@@ -50,10 +50,5 @@ mod reg {
       24..26   => ap,
       28       => xn,
     }
-  })
-
-  #[allow(dead_code)]
-  extern {
-    #[link_name="armmem_MPU"] pub static MPU: MPU;
-  }
+  } MPU @ 0xE000_ED90)
 }
@@ -18,42 +18,42 @@
 
 /// Enable an interrupt
 pub fn enable_irq(irqn: uint) {
-  reg::NVIC.iser[irqn / 32].clear_iser(irqn % 32);
+  reg::NVIC().iser[irqn / 32].clear_iser(irqn % 32);
 }
 
 /// Disable an interrupt
 pub fn disable_irq(irqn: uint) {
-  reg::NVIC.icer[irqn / 32].clear_icer(irqn % 32);
+  reg::NVIC().icer[irqn / 32].clear_icer(irqn % 32);
 }
 
 /// Return whether the given interrupt is enabled
 pub fn is_enabled(irqn: uint) -> bool {
-  reg::NVIC.iser[irqn / 32].iser(irqn % 32)
+  reg::NVIC().iser[irqn / 32].iser(irqn % 32)
 }
 
 /// Clear the pending flag for the given interrupt
 pub fn clear_pending(irqn: uint) {
-  reg::NVIC.icpr[irqn / 32].clear_icpr(irqn % 32);
+  reg::NVIC().icpr[irqn / 32].clear_icpr(irqn % 32);
 }
 
 /// Return whether the given interrupt is pending
 pub fn is_pending(irqn: uint) -> bool {
-  reg::NVIC.ispr[irqn / 32].ispr(irqn % 32)
+  reg::NVIC().ispr[irqn / 32].ispr(irqn % 32)
 }
 
 /// Return whether the given interrupt is active
 pub fn is_active(irqn: uint) -> bool {
-  reg::NVIC.iabr[irqn / 32].iabr(irqn % 32)
+  reg::NVIC().iabr[irqn / 32].iabr(irqn % 32)
 }
 
 /// Set the priority for the given interrupt
 pub fn set_priority(irqn: uint, prio: u8) {
-  reg::NVIC.ipr[irqn / 4].set_ipr(irqn % 4, prio as u32);
+  reg::NVIC().ipr[irqn / 4].set_ipr(irqn % 4, prio as u32);
 }
 
 /// Return the priority for the given interrupt
 pub fn get_priority(irqn: uint) -> u8 {
-  reg::NVIC.ipr[irqn / 4].ipr(irqn % 4) as u8
+  reg::NVIC().ipr[irqn / 4].ipr(irqn % 4) as u8
 }
 
 mod reg {
@@ -76,16 +76,11 @@ mod reg {
     0x300     => reg32 iabr[8] {      //! Interrupt active bit register
       0..31   => iabr[32]: ro,
     }
-    0x400     => reg32 ipr[8] {       //! Interrupt priority register
+    0x400     => reg32 ipr[60] {      //! Interrupt priority register
       0..31   => ipr[4],
     }
     0xF00     => reg32 stir[8] {      //! Software triggered interrupt register
       0..8    => stir,
     }
-  })
-
-  #[allow(dead_code)]
-  extern {
-    #[link_name="armmem_NVIC"] pub static NVIC: NVIC;
-  }
+  } NVIC @ 0xE000_E000)
 }
@@ -19,15 +19,15 @@
 /// Returns the CPUID.
 #[allow(dead_code)]
 pub fn cpuid() -> reg::SCB_cpuid_Get {
-  reg::SCB.cpuid.get()
+  reg::SCB().cpuid.get()
 }
 
 /// Sets the pending state of the PendSV interrupt.
 pub fn set_pendsv(val: bool) {
   if val {
-    reg::SCB.icsr.set_pendsvset(true);
+    reg::SCB().icsr.set_pendsvset(true);
   } else {
-    reg::SCB.icsr.set_pendsvclr(true);
+    reg::SCB().icsr.set_pendsvclr(true);
   }
 }
 
@@ -126,10 +126,5 @@ mod reg {
     0x88      => reg32 cpacr { //! Coprocessor access control register
       0..23   => cp[24],
     }
-  })
-
-  #[allow(dead_code)]
-  extern {
-    #[link_name="armmem_SCB"] pub static SCB: SCB;
-  }
+  } SCB @ 0xE000_ED00)
 }
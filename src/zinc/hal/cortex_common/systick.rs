@@ -26,15 +26,15 @@ use core::option::{Option,None,Some};
 ///
 ///  * reload: Reload value for the timer
 pub fn setup(reload: u32) {
-  reg::SYSTICK.csr.set_enable(false).set_tickint(false).set_clksource(reg::CPU);
+  reg::SYSTICK().csr.set_enable(false).set_tickint(false).set_clksource(reg::CPU);
 
-  reg::SYSTICK.rvr.set_reload(reload);
-  reg::SYSTICK.cvr.set_current(0);
+  reg::SYSTICK().rvr.set_reload(reload);
+  reg::SYSTICK().cvr.set_current(0);
 }
 
 /// Read ten millisecond calibration value from hardware
 pub fn ten_ms() -> Option<u32> {
-  let calib = reg::SYSTICK.calib.tenms();
+  let calib = reg::SYSTICK().calib.tenms();
   match calib {
     0 => None,
     val => Some(val)
@@ -43,33 +43,33 @@ pub fn ten_ms() -> Option<u32> {
 
 /// Enables the timer.
 pub fn enable() {
-  reg::SYSTICK.csr.set_enable(true);
+  reg::SYSTICK().csr.set_enable(true);
 }
 
 /// Disable the timer.
 pub fn disable() {
-  reg::SYSTICK.csr.set_enable(false);
+  reg::SYSTICK().csr.set_enable(false);
 }
 
 /// Enables interrupts generation for timer.
 pub fn enable_irq() {
-  reg::SYSTICK.csr.set_tickint(true);
+  reg::SYSTICK().csr.set_tickint(true);
 }
 
 /// Disables interrupts generation for timer, which is still ticking.
 pub fn disable_irq() {
-  reg::SYSTICK.csr.set_tickint(false);
+  reg::SYSTICK().csr.set_tickint(false);
 }
 
 /// Gets the current 24bit systick value.
 pub fn get_current() -> u32 {
-  reg::SYSTICK.cvr.current()
+  reg::SYSTICK().cvr.current()
 }
 
 /// Checks if the timer has been triggered since last call.
 /// The flag is cleared when this is called.
 pub fn tick() -> bool {
-  reg::SYSTICK.csr.countflag()
+  reg::SYSTICK().csr.countflag()
 }
 
 #[allow(dead_code)]
@@ -107,9 +107,5 @@ mod reg {
       23..0 => tenms : ro,    //= An optional Reload value for 10ms (100Hz) timing
                               //= If zero calibration value not known
     },
-  })
-
-  extern {
-    #[link_name="armmem_SYSTICK"] pub static SYSTICK: SYSTICK;
-  }
+  } SYSTICK @ 0xE000_E010)
 }
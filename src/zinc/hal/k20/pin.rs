@@ -82,12 +82,26 @@ impl Pin {
   /// Create and setup a Pin.
   pub fn new(port: Port, pin_index: u8, function: Function,
       gpiodir: Option<::hal::pin::GPIODirection>) -> Pin {
+    // enable port clock
+    sim::enable_PORT(port);
+
+    Pin::new_with_conf(port, pin_index, function, gpiodir, PullNone,
+                       DriveStrengthHigh, SlewSlow)
+  }
+
+  /// Create and setup a Pin with the given pad configuration.
+  ///
+  /// Unlike `new` this doesn't enable the clock of the port, which must have
+  /// been done with `sim::enable_PORT`.
+  pub fn new_with_conf(port: Port, pin_index: u8, function: Function,
+      gpiodir: Option<::hal::pin::GPIODirection>, pull: PullConf,
+      drive_strength: DriveStrength, slew_rate: SlewRate) -> Pin {
     let pin = Pin {
       port: port,
       pin: pin_index,
     };
-    pin.setup_regs(function, gpiodir, PullNone,
-                   DriveStrengthHigh, SlewSlow, false, false);
+    pin.setup_regs(function, gpiodir, pull,
+                   drive_strength, slew_rate, false, false);
 
     pin
   }
@@ -96,9 +110,6 @@ impl Pin {
       gpiodir: Option<::hal::pin::GPIODirection>,
       pull: PullConf, drive_strength: DriveStrength,
       slew_rate: SlewRate, filter: bool, open_drain: bool) {
-    // enable port clock
-    sim::enable_PORT(self.port);
-
     let (pe, ps) = match pull {
       PullNone => (false, reg::PULL_DOWN),
       PullDown => (true,  reg::PULL_DOWN),
@@ -129,21 +140,21 @@ impl Pin {
 
   fn gpioreg(&self) -> &'static reg::GPIO {
     match self.port {
-      PortA => &reg::GPIOA,
-      PortB => &reg::GPIOB,
-      PortC => &reg::GPIOC,
-      PortD => &reg::GPIOD,
-      PortE => &reg::GPIOE,
+      PortA => reg::GPIOA(),
+      PortB => reg::GPIOB(),
+      PortC => reg::GPIOC(),
+      PortD => reg::GPIOD(),
+      PortE => reg::GPIOE(),
     }
   }
 
   fn pcr(&self) -> &'static reg::PORT_pcr {
     let port: &reg::PORT = match self.port {
-      PortA => &reg::PORTA,
-      PortB => &reg::PORTB,
-      PortC => &reg::PORTC,
-      PortD => &reg::PORTD,
-      PortE => &reg::PORTE,
+      PortA => reg::PORTA(),
+      PortB => reg::PORTB(),
+      PortC => reg::PORTC(),
+      PortD => reg::PORTD(),
+      PortE => reg::PORTE(),
     };
     return &port.pcr[self.pin as uint];
   }
@@ -233,15 +244,13 @@ pub mod reg {
     0x88   => reg32 isfr {    //= Interrupt status
       0..31  => isf
     }
-  })
-
-  extern {
-    #[link_name="k20_iomem_PORTA"] pub static PORTA: PORT;
-    #[link_name="k20_iomem_PORTB"] pub static PORTB: PORT;
-    #[link_name="k20_iomem_PORTC"] pub static PORTC: PORT;
-    #[link_name="k20_iomem_PORTD"] pub static PORTD: PORT;
-    #[link_name="k20_iomem_PORTE"] pub static PORTE: PORT;
   }
+    PORTA @ 0x4004_9000,
+    PORTB @ 0x4004_A000,
+    PORTC @ 0x4004_B000,
+    PORTD @ 0x4004_C000,
+    PORTE @ 0x4004_D000,
+  )
 
   ioregs!(GPIO = {
     0x0     => reg32 pdo {  //! port data output register
@@ -270,13 +279,11 @@ pub mod reg {
         1 => OUTPUT,
       }
     }
-  })
-
-  extern {
-    #[link_name="k20_iomem_GPIOA"] pub static GPIOA: GPIO;
-    #[link_name="k20_iomem_GPIOB"] pub static GPIOB: GPIO;
-    #[link_name="k20_iomem_GPIOC"] pub static GPIOC: GPIO;
-    #[link_name="k20_iomem_GPIOD"] pub static GPIOD: GPIO;
-    #[link_name="k20_iomem_GPIOE"] pub static GPIOE: GPIO;
   }
+    GPIOA @ 0x400F_F000,
+    GPIOB @ 0x400F_F040,
+    GPIOC @ 0x400F_F080,
+    GPIOD @ 0x400F_F0C0,
+    GPIOE @ 0x400F_F100,
+  )
 }
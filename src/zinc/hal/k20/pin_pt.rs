@@ -0,0 +1,346 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString, PinFunction, add_node_dependency};
+use node;
+use super::uart_pt::UART_PINS;
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+  for port_node in node.subnodes().iter() {
+    port_node.materializer.set(Some(build_port));
+    add_node_dependency(&node, port_node);
+    super::add_node_dependency_on_clock(builder, port_node);
+    for pin_node in port_node.subnodes().iter() {
+      pin_node.materializer.set(Some(build_pin));
+      add_node_dependency(pin_node, port_node);
+    }
+  }
+}
+
+pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+}
+
+fn port_name(path: &str) -> Option<String> {
+  match path {
+    "A" | "B" | "C" | "D" | "E" => Some(format!("Port{}", path)),
+    _ => None,
+  }
+}
+
+/// Enables the clock of a port, once for all of its pins.
+fn build_port(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+
+  let port = match port_name(node.path.as_slice()) {
+    Some(port) => TokenString(port),
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(node.path_span,
+          format!("unknown port `{}`, allowed values: A...E",
+              node.path).as_slice());
+      return;
+    }
+  };
+
+  let st = quote_stmt!(&*cx,
+      zinc::hal::k20::sim::enable_PORT(zinc::hal::k20::pin::$port);
+  );
+  builder.add_main_statement(st);
+}
+
+/// Returns the variant for an optional string attribute, `default` if it's
+/// missing. Reports an error and returns None on unknown values.
+fn get_choice(cx: &ExtCtxt, node: &Rc<node::Node>, key: &str,
+    default: &'static str, choices: &[(&str, &'static str)])
+    -> Option<&'static str> {
+  let value = match node.get_string_attr(key) {
+    Some(value) => value,
+    None => return Some(default),
+  };
+  for &(name, variant) in choices.iter() {
+    if value.as_slice() == name {
+      return Some(variant);
+    }
+  }
+  let allowed: Vec<String> =
+      choices.iter().map(|&(name, _)| format!("`{}`", name)).collect();
+  cx.parse_sess().span_diagnostic.span_err(node.get_attr(key).value_span,
+      format!("unknown {} `{}`, allowed values: {}",
+          key, value, allowed.connect(", ")).as_slice());
+  None
+}
+
+fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  let port_node = node.parent.clone().unwrap().upgrade().unwrap();
+  let port = match port_name(port_node.path.as_slice()) {
+    Some(port) => TokenString(port),
+    // already reported by build_port
+    None => return,
+  };
+
+  if node.name.is_none() {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "pin node must have a name");
+    return;
+  }
+
+  if !node.expect_no_subnodes(cx) {
+    return;
+  }
+
+  let pin_str = match from_str::<uint>(node.path.as_slice()) {
+    Some(pin) if pin < 32 => &node.path,
+    _ => {
+      cx.parse_sess().span_diagnostic.span_err(node.path_span,
+          format!("unknown pin `{}`, allowed values: 0...31",
+              node.path).as_slice());
+      return;
+    }
+  };
+
+  let (function_str, direction_str) = match node.get_int_attr("function") {
+    None | Some(1) => {
+      let direction = match node.get_required_string_attr(cx, "direction") {
+        Some(direction) => direction,
+        None => return,
+      };
+      ("GPIO".to_string(), match direction.as_slice() {
+        "out" => "core::option::Some(zinc::hal::pin::Out)",
+        "in"  => "core::option::Some(zinc::hal::pin::In)",
+        other => {
+          cx.parse_sess().span_diagnostic.span_err(
+              node.get_attr("direction").value_span,
+              format!("unknown direction `{}`, allowed values: `in`, `out`",
+                  other).as_slice());
+          return;
+        }
+      })
+    },
+    Some(0) => ("Analog".to_string(), "core::option::None"),
+    Some(f) if f <= 7 =>
+      (format!("AltFunction{}", f), "core::option::None"),
+    Some(f) => {
+      cx.parse_sess().span_diagnostic.span_err(
+          node.get_attr("function").value_span,
+          format!("unknown pin function `{}`, allowed values: 0...7",
+              f).as_slice());
+      return;
+    }
+  };
+
+  let some_pull = get_choice(cx, &node, "pull", "PullNone", [
+      ("none", "PullNone"),
+      ("up",   "PullUp"),
+      ("down", "PullDown")]);
+  let some_drive = get_choice(cx, &node, "drive_strength", "DriveStrengthHigh", [
+      ("high", "DriveStrengthHigh"),
+      ("low",  "DriveStrengthLow")]);
+  let some_slew = get_choice(cx, &node, "slew_rate", "SlewSlow", [
+      ("fast", "SlewFast"),
+      ("slow", "SlewSlow")]);
+  if some_pull.is_none() || some_drive.is_none() || some_slew.is_none() {
+    return;
+  }
+
+  // only one pin can be routed to a UART signal
+  match node.get_int_attr("function") {
+    Some(function) => {
+      let pin_idx = from_str::<uint>(pin_str.as_slice()).unwrap();
+      let signal = UART_PINS.iter().find(|&&(_, _, port, pin, f)|
+          port == port_node.path.as_slice() && pin == pin_idx &&
+          f == function);
+      match signal {
+        Some(&(uart, tx, _, _, _)) => {
+          let name = format!("UART{}_{}", uart, if tx {"TX"} else {"RX"});
+          if !builder.claim(cx, PinFunction(name), node.path_span) {
+            return;
+          }
+        },
+        None => (),
+      }
+    },
+    None => (),
+  }
+
+  let function = TokenString(function_str);
+  let direction = TokenString(direction_str.to_string());
+  let pull = TokenString(some_pull.unwrap().to_string());
+  let drive_strength = TokenString(some_drive.unwrap().to_string());
+  let slew_rate = TokenString(some_slew.unwrap().to_string());
+  let pin = TokenString(format!("{}u8", pin_str));
+  let pin_name = TokenString(node.name.clone().unwrap());
+
+  node.set_type_name("zinc::hal::k20::pin::Pin".to_string());
+
+  let st = quote_stmt!(&*cx,
+      let $pin_name = zinc::hal::k20::pin::Pin::new_with_conf(
+          zinc::hal::k20::pin::$port,
+          $pin,
+          zinc::hal::k20::pin::$function,
+          $direction,
+          zinc::hal::k20::pin::$pull,
+          zinc::hal::k20::pin::$drive_strength,
+          zinc::hal::k20::pin::$slew_rate);
+  );
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_port_clock() {
+    with_parsed("
+      gpio {
+        B {
+          led@16 { direction = \"out\"; }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_port(&mut builder, cx,
+          pt.get_by_path("gpio").unwrap().get_by_path("B").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "zinc::hal::k20::sim::enable_PORT(zinc::hal::k20::pin::PortB);");
+    });
+  }
+
+  #[test]
+  fn builds_output_gpio() {
+    with_parsed("
+      gpio {
+        B {
+          led@16 { direction = \"out\"; }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_pin(&mut builder, cx, pt.get_by_name("led").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let led = zinc::hal::k20::pin::Pin::new_with_conf(
+               zinc::hal::k20::pin::PortB,
+               16u8,
+               zinc::hal::k20::pin::GPIO,
+               core::option::Some(zinc::hal::pin::Out),
+               zinc::hal::k20::pin::PullNone,
+               zinc::hal::k20::pin::DriveStrengthHigh,
+               zinc::hal::k20::pin::SlewSlow);");
+    });
+  }
+
+  #[test]
+  fn builds_configured_input_gpio() {
+    with_parsed("
+      gpio {
+        D {
+          button@7 {
+            direction = \"in\";
+            pull = \"up\";
+            drive_strength = \"low\";
+            slew_rate = \"fast\";
+          }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_pin(&mut builder, cx, pt.get_by_name("button").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let button = zinc::hal::k20::pin::Pin::new_with_conf(
+               zinc::hal::k20::pin::PortD,
+               7u8,
+               zinc::hal::k20::pin::GPIO,
+               core::option::Some(zinc::hal::pin::In),
+               zinc::hal::k20::pin::PullUp,
+               zinc::hal::k20::pin::DriveStrengthLow,
+               zinc::hal::k20::pin::SlewFast);");
+    });
+  }
+
+  #[test]
+  fn builds_altfn_gpio() {
+    with_parsed("
+      gpio {
+        C {
+          sck@5 { function = 2; }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_pin(&mut builder, cx, pt.get_by_name("sck").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let sck = zinc::hal::k20::pin::Pin::new_with_conf(
+               zinc::hal::k20::pin::PortC,
+               5u8,
+               zinc::hal::k20::pin::AltFunction2,
+               core::option::None,
+               zinc::hal::k20::pin::PullNone,
+               zinc::hal::k20::pin::DriveStrengthHigh,
+               zinc::hal::k20::pin::SlewSlow);");
+    });
+  }
+
+  #[test]
+  fn fails_to_mux_two_pins_to_one_function() {
+    with_parsed("
+      gpio {
+        B {
+          tx_b@17 { function = 3; }
+        }
+        D {
+          tx_d@7 { function = 3; }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_pin(&mut builder, cx, pt.get_by_name("tx_b").unwrap());
+      assert!(unsafe{*failed} == false);
+      super::build_pin(&mut builder, cx, pt.get_by_name("tx_d").unwrap());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_pins() {
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      gpio { F { p@1 { direction = \"out\"; } } }
+    }");
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      gpio { A { p@32 { direction = \"out\"; } } }
+    }");
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      gpio { A { p@1 { direction = \"out\"; pull = \"sideways\"; } } }
+    }");
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      gpio { A { p@1 { function = 8; } } }
+    }");
+  }
+}
@@ -0,0 +1,171 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, add_node_dependency};
+use node;
+
+mod system_clock_pt;
+mod watchdog_pt;
+mod pin_pt;
+mod uart_pt;
+
+pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+
+  // the watchdog is attached first so that it's configured right after the
+  // clock, before any other peripheral
+  match node.get_by_path("watchdog") {
+    Some(sub) => watchdog_pt::attach(builder, cx, sub),
+    None => (),
+  }
+
+  for sub in node.subnodes().iter() {
+    add_node_dependency(&node, sub);
+
+    match sub.path.as_slice() {
+      "clock" => system_clock_pt::attach(builder, cx, sub.clone()),
+      "gpio"  => pin_pt::attach(builder, cx, sub.clone()),
+      "uart"  => uart_pt::attach(builder, cx, sub.clone()),
+      _ => (),
+    }
+  }
+}
+
+fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+  node.expect_subnodes(cx, ["clock", "watchdog", "gpio", "uart"]);
+}
+
+pub fn add_node_dependency_on_clock(builder: &mut Builder,
+    node: &Rc<node::Node>) {
+  let mcu_node = builder.pt().get_by_path("mcu").unwrap();
+  let clock_node = mcu_node.get_by_path("clock").unwrap();
+  add_node_dependency(node, &clock_node);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_items, with_parsed, fails_to_build};
+
+  #[test]
+  fn fails_to_parse_garbage_attrs() {
+    fails_to_build("k20@mcu { key = 1; }");
+  }
+
+  #[test]
+  fn builds_k20_pt() {
+    with_parsed("
+      k20@mcu {
+        clock {
+          source_frequency = 48_000_000;
+          flash_divisor = 2;
+        }
+
+        gpio {
+          B {
+            uart_rx@16;
+            uart_tx@17;
+          }
+          C {
+            led1@5 { direction = \"out\"; drive_strength = \"low\"; }
+          }
+        }
+
+        uart {
+          uart@0 {
+            baud_rate = 115200;
+            mode = \"8N1\";
+            tx = &uart_tx;
+            rx = &uart_rx;
+          }
+        }
+
+        watchdog {
+          state = \"disabled\";
+        }
+      }
+
+      os {
+        single_task {
+          loop = \"run\";
+          args {
+            led1 = &led1;
+            uart = &uart;
+          }
+        }
+      }", |cx, failed, pt| {
+      let items = Builder::build(cx, pt)
+        .expect(format!("Unexpected failure on {}", line!()).as_slice())
+        .emit_items(cx);
+
+      assert!(unsafe{*failed} == false);
+      assert!(items.len() == 3);
+
+      assert_equal_items(items[1].deref(), "
+          #[no_mangle]
+          #[allow(unused_variable)]
+          pub unsafe fn main() {
+            zinc::hal::mem_init::init_stack();
+            zinc::hal::mem_init::init_data();
+            zinc::hal::k20::sim::set_clock_dividers(1u8, 1u8, 2u8);
+            zinc::hal::k20::watchdog::init(
+                zinc::hal::k20::watchdog::Disabled);
+            zinc::hal::k20::sim::enable_PORT(zinc::hal::k20::pin::PortB);
+            let uart_rx = zinc::hal::k20::pin::Pin::new_with_conf(
+                zinc::hal::k20::pin::PortB,
+                16u8,
+                zinc::hal::k20::pin::AltFunction3,
+                core::option::None,
+                zinc::hal::k20::pin::PullNone,
+                zinc::hal::k20::pin::DriveStrengthHigh,
+                zinc::hal::k20::pin::SlewSlow);
+            let uart_tx = zinc::hal::k20::pin::Pin::new_with_conf(
+                zinc::hal::k20::pin::PortB,
+                17u8,
+                zinc::hal::k20::pin::AltFunction3,
+                core::option::None,
+                zinc::hal::k20::pin::PullNone,
+                zinc::hal::k20::pin::DriveStrengthHigh,
+                zinc::hal::k20::pin::SlewSlow);
+            zinc::hal::k20::sim::enable_PORT(zinc::hal::k20::pin::PortC);
+            let led1 = zinc::hal::k20::pin::Pin::new_with_conf(
+                zinc::hal::k20::pin::PortC,
+                5u8,
+                zinc::hal::k20::pin::GPIO,
+                core::option::Some(zinc::hal::pin::Out),
+                zinc::hal::k20::pin::PullNone,
+                zinc::hal::k20::pin::DriveStrengthLow,
+                zinc::hal::k20::pin::SlewSlow);
+            let uart = zinc::hal::k20::uart::UART::new_with_divisors(
+                zinc::hal::k20::uart::UART0,
+                26u16,
+                1u8,
+                8u8,
+                zinc::hal::uart::Disabled,
+                1u8);
+            loop {
+              run(&pt::run_args{
+                led1: &led1,
+                uart: &uart,
+              });
+            }
+          }");
+    });
+  }
+}
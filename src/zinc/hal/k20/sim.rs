@@ -16,19 +16,39 @@
 //! HAL for Kinetis SIM module.
 
 use super::pin;
+use super::uart;
 
 /// Enable clock to a PORTx peripheral
 #[allow(non_snake_case)]
 pub fn enable_PORT(port: pin::Port) {
   match port {
-    pin::PortA => {reg::SIM.scgc5.set_porta(true);},
-    pin::PortB => {reg::SIM.scgc5.set_portb(true);},
-    pin::PortC => {reg::SIM.scgc5.set_portc(true);},
-    pin::PortD => {reg::SIM.scgc5.set_portd(true);},
-    pin::PortE => {reg::SIM.scgc5.set_porte(true);},
+    pin::PortA => {reg::SIM().scgc5.set_porta(true);},
+    pin::PortB => {reg::SIM().scgc5.set_portb(true);},
+    pin::PortC => {reg::SIM().scgc5.set_portc(true);},
+    pin::PortD => {reg::SIM().scgc5.set_portd(true);},
+    pin::PortE => {reg::SIM().scgc5.set_porte(true);},
   }
 }
 
+/// Enable clock to a UARTx peripheral
+#[allow(non_snake_case)]
+pub fn enable_UART(uart: uart::UARTPeripheral) {
+  match uart {
+    uart::UART0 => {reg::SIM().scgc4.set_uart0(true);},
+    uart::UART1 => {reg::SIM().scgc4.set_uart1(true);},
+    uart::UART2 => {reg::SIM().scgc4.set_uart2(true);},
+  }
+}
+
+/// Set the core, bus and flash clock divisors, each 1 to 16, applied to the
+/// MCG output clock
+pub fn set_clock_dividers(core: u8, bus: u8, flash: u8) {
+  reg::SIM().clkdiv1
+    .set_outdiv1(core as u32 - 1)
+    .set_outdiv2(bus as u32 - 1)
+    .set_outdiv4(flash as u32 - 1);
+}
+
 /// Registers
 #[allow(dead_code)]
 pub mod reg {
@@ -173,9 +193,5 @@ pub mod reg {
     0x1060 => reg32 uidl {
       0..31  => uid,
     },
-  })
-
-  extern {
-    #[link_name="k20_iomem_SIM"] pub static SIM: SIM;
-  }
+  } SIM @ 0x4004_7000)
 }
@@ -0,0 +1,181 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::Builder;
+use node;
+
+static MAX_CORE_FREQUENCY: uint = 72_000_000;
+static MAX_BUS_FREQUENCY: uint = 50_000_000;
+static MAX_FLASH_FREQUENCY: uint = 25_000_000;
+
+pub fn attach(_: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(build_clock));
+}
+
+/// Returns the value of an optional divisor attribute, 1 if it's missing.
+/// Reports an error and returns None if it's out of the 1...16 range.
+fn get_divisor(cx: &ExtCtxt, node: &Rc<node::Node>, key: &str)
+    -> Option<uint> {
+  if node.attributes.borrow().find(&key.to_string()).is_none() {
+    return Some(1);
+  }
+  match node.get_required_int_attr(cx, key) {
+    Some(divisor) if divisor >= 1 && divisor <= 16 => Some(divisor),
+    Some(divisor) => {
+      cx.parse_sess().span_diagnostic.span_err(node.get_attr(key).value_span,
+          format!("invalid {} `{}`, allowed values: 1...16",
+              key, divisor).as_slice());
+      None
+    },
+    None => None,
+  }
+}
+
+fn build_clock(builder: &mut Builder, cx: &mut ExtCtxt,
+    node: Rc<node::Node>) {
+  if !node.expect_no_subnodes(cx) ||
+     !node.expect_attributes(cx, [
+         ("source_frequency", node::FrequencyAttribute)]) {
+    return;
+  }
+
+  // the MCG output clock, as configured at reset or by the bootloader
+  let source_freq = node.get_frequency_attr("source_frequency").unwrap();
+  let some_core = get_divisor(cx, &node, "core_divisor");
+  let some_bus = get_divisor(cx, &node, "bus_divisor");
+  let some_flash = get_divisor(cx, &node, "flash_divisor");
+  if some_core.is_none() || some_bus.is_none() || some_flash.is_none() {
+    return;
+  }
+  let core_divisor = some_core.unwrap();
+  let bus_divisor = some_bus.unwrap();
+  let flash_divisor = some_flash.unwrap();
+
+  // bus and flash clocks must be integer divisions of the core clock
+  if bus_divisor % core_divisor != 0 || flash_divisor % core_divisor != 0 {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "`bus_divisor` and `flash_divisor` must be multiples of \
+         `core_divisor`");
+    return;
+  }
+  if flash_divisor < bus_divisor {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "flash clock must not be faster than the bus clock");
+    return;
+  }
+
+  let core_freq = source_freq / core_divisor;
+  let bus_freq = source_freq / bus_divisor;
+  let flash_freq = source_freq / flash_divisor;
+  let checks = [
+    ("core", core_freq, MAX_CORE_FREQUENCY),
+    ("bus", bus_freq, MAX_BUS_FREQUENCY),
+    ("flash", flash_freq, MAX_FLASH_FREQUENCY),
+  ];
+  for &(name, freq, max) in checks.iter() {
+    if freq > max {
+      cx.parse_sess().span_diagnostic.span_err(node.name_span,
+          format!("{} clock frequency {} Hz is over {} Hz, increase `{}_divisor`",
+              name, freq, max, name).as_slice());
+      return;
+    }
+  }
+
+  node.attributes.borrow_mut().insert("system_frequency".to_string(),
+      Rc::new(node::Attribute::new_nosp(node::IntValue(core_freq))));
+  node.attributes.borrow_mut().insert("bus_frequency".to_string(),
+      Rc::new(node::Attribute::new_nosp(node::IntValue(bus_freq))));
+
+  let core: u8 = core_divisor as u8;
+  let bus: u8 = bus_divisor as u8;
+  let flash: u8 = flash_divisor as u8;
+  let st = quote_stmt!(&*cx,
+      zinc::hal::k20::sim::set_clock_dividers($core, $bus, $flash);
+  );
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_clock_init() {
+    with_parsed("
+      clock {
+        source_frequency = 96_000_000;
+        core_divisor = 2;
+        bus_divisor = 2;
+        flash_divisor = 4;
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_clock(&mut builder, cx, pt.get_by_path("clock").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "zinc::hal::k20::sim::set_clock_dividers(2u8, 2u8, 4u8);");
+    });
+  }
+
+  #[test]
+  fn clock_provides_out_frequency() {
+    with_parsed("
+      clock {
+        source_frequency = 96_000_000;
+        core_divisor = 2;
+        bus_divisor = 2;
+        flash_divisor = 4;
+      }", |cx, _, pt| {
+      let mut builder = Builder::new(pt.clone());
+      let node = pt.get_by_path("clock").unwrap();
+      super::build_clock(&mut builder, cx, node.clone());
+
+      assert!(node.get_int_attr("system_frequency") == Some(48_000_000));
+      assert!(node.get_int_attr("bus_frequency") == Some(48_000_000));
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_clock_conf() {
+    fails_to_build("k20@mcu { clock {
+      core_divisor = 1;
+    }}");
+    fails_to_build("k20@mcu { clock {
+      source_frequency = 48_000_000;
+      flash_divisor = 17;
+    }}");
+    fails_to_build("k20@mcu { clock {
+      source_frequency = 96_000_000;
+      core_divisor = 2;
+      bus_divisor = 3;
+      flash_divisor = 4;
+    }}");
+  }
+
+  #[test]
+  fn fails_to_parse_fast_clocks() {
+    fails_to_build("k20@mcu { clock {
+      source_frequency = 96_000_000;
+    }}");
+    fails_to_build("k20@mcu { clock {
+      source_frequency = 48_000_000;
+    }}");
+  }
+}
@@ -21,6 +21,7 @@ use core::intrinsics::abort;
 
 use drivers::chario::CharIO;
 use hal::uart;
+use super::sim;
 
 #[path="../../util/wait_for.rs"] mod wait_for;
 
@@ -57,9 +58,9 @@ impl StopBit {
 impl UARTPeripheral {
   fn reg(self) -> &'static reg::UART {
     match self {
-      UART0 => &reg::UART0,
-      UART1 => &reg::UART1,
-      UART2 => &reg::UART2,
+      UART0 => reg::UART0(),
+      UART1 => reg::UART1(),
+      UART2 => reg::UART2(),
     }
   }
 }
@@ -68,6 +69,8 @@ impl UART {
   /// Returns platform-specific UART object that implements CharIO trait.
   pub fn new(peripheral: UARTPeripheral, baudrate:  u32, word_len: u8,
       parity: uart::Parity, stop_bits: u8) -> UART {
+    sim::enable_UART(peripheral);
+
     let uart = UART {
       reg: peripheral.reg()
     };
@@ -78,6 +81,25 @@ impl UART {
     uart
   }
 
+  /// Returns platform-specific UART object with precomputed baud rate
+  /// divisors.
+  ///
+  /// The baud rate is `clock / (16 * (sbr + brfa / 32))`, where clock is the
+  /// system clock for UART0 and UART1 and the bus clock for UART2.
+  pub fn new_with_divisors(peripheral: UARTPeripheral, sbr: u16, brfa: u8,
+      word_len: u8, parity: uart::Parity, stop_bits: u8) -> UART {
+    sim::enable_UART(peripheral);
+
+    let uart = UART {
+      reg: peripheral.reg()
+    };
+    uart.set_divisors(sbr as u32, brfa as u32);
+    uart.set_mode(reg::UART_c1_m::from_u8(word_len), parity, StopBit::from_u8(stop_bits));
+    uart.set_fifo_enabled(true);
+
+    uart
+  }
+
   fn uart_clock(&self) -> u32 {
     48000000 // FIXME(bgamari): Use peripheral clocks
   }
@@ -85,6 +107,10 @@ impl UART {
   fn set_baud_rate(&self, baud_rate: u32) {
     let sbr: u32 = self.uart_clock() / 16 / baud_rate;
     let brfa: u32 = (2 * self.uart_clock() / baud_rate) % 32;
+    self.set_divisors(sbr, brfa);
+  }
+
+  fn set_divisors(&self, sbr: u32, brfa: u32) {
     (*self.reg).bdh.set_sbr((sbr >> 8) as u8);
     (*self.reg).bdl.set_sbr((sbr & 0xff) as u8);
     (*self.reg).c4.set_brfa(brfa as u8);
@@ -291,7 +317,11 @@ pub mod reg {
     },
 
     // FIXME(bgamari): Specialized registers omitted
-  })
+  }
+    UART0 @ 0x4006_A000,
+    UART1 @ 0x4006_B000,
+    UART2 @ 0x4006_C000,
+  )
 
   impl UART_c1_m {
     /// UART data word length flag value from bit count
@@ -303,10 +333,4 @@ pub mod reg {
       }
     }
   }
-
-  extern {
-    #[link_name="k20_iomem_UART0"] pub static UART0: UART;
-    #[link_name="k20_iomem_UART1"] pub static UART1: UART;
-    #[link_name="k20_iomem_UART2"] pub static UART2: UART;
-  }
 }
@@ -0,0 +1,316 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
+use node;
+
+/// UART pins with their multiplexer function: (uart, is tx, port, pin, mux)
+pub static UART_PINS: &'static [(uint, bool, &'static str, uint, uint)] = &[
+  (0, false, "A", 1,  2), (0, true, "A", 2,  2),
+  (0, true,  "A", 14, 3), (0, false, "A", 15, 3),
+  (0, false, "B", 16, 3), (0, true, "B", 17, 3),
+  (0, false, "D", 6,  3), (0, true, "D", 7,  3),
+  (1, false, "C", 3,  3), (1, true, "C", 4,  3),
+  (1, true,  "E", 0,  3), (1, false, "E", 1,  3),
+  (2, false, "D", 2,  3), (2, true, "D", 3,  3),
+];
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+
+  for sub in node.subnodes().iter() {
+    add_node_dependency(&node, sub);
+    for attr in ["tx", "rx"].iter() {
+      let pin_node = sub.get_ref_attr(*attr).and_then(|name|
+          builder.pt().get_by_name(name.as_slice()));
+      match pin_node {
+        Some(pin_node) => add_node_dependency(sub, &pin_node),
+        None => (),
+      }
+    }
+    super::add_node_dependency_on_clock(builder, sub);
+
+    sub.materializer.set(Some(build_uart));
+    sub.mutator.set(Some(mutate_pins));
+  }
+}
+
+pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+}
+
+/// Sets the multiplexer function of the pins used by the UART.
+pub fn mutate_pins(builder: &mut Builder, cx: &mut ExtCtxt,
+    sub: Rc<node::Node>) {
+  let uart_idx = match from_str::<uint>(sub.path.as_slice()) {
+    Some(idx) => idx,
+    // reported by build_uart
+    None => return,
+  };
+  build_uart_gpio(builder, cx, &sub, uart_idx, "tx", true);
+  build_uart_gpio(builder, cx, &sub, uart_idx, "rx", false);
+}
+
+fn build_uart_gpio(builder: &mut Builder, cx: &ExtCtxt, sub: &Rc<node::Node>,
+    uart_idx: uint, attr: &str, istx: bool) {
+  let name = match sub.get_ref_attr(attr) {
+    Some(name) => name,
+    // reported by build_uart
+    None => return,
+  };
+  let node = match builder.pt().get_by_name(name.as_slice()) {
+    Some(node) => node,
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(sub.get_attr(attr).value_span,
+          format!("unknown pin `{}`", name).as_slice());
+      return;
+    }
+  };
+  if !builder.claim_pin(cx, &node, sub.get_attr(attr).value_span) {
+    return;
+  }
+  let port_node = node.parent.clone().unwrap().upgrade().unwrap();
+  let pin_idx: Option<uint> = from_str(node.path.as_slice());
+
+  let mux = UART_PINS.iter().find(|&&(uart, tx, port, pin, _)|
+      uart == uart_idx && tx == istx &&
+      port == port_node.path.as_slice() && Some(pin) == pin_idx);
+  match mux {
+    Some(&(_, _, _, _, function)) => {
+      node.attributes.borrow_mut().insert("function".to_string(),
+          Rc::new(node::Attribute::new_nosp(node::IntValue(function))));
+    },
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(sub.get_attr(attr).value_span,
+          format!("pin `{}` can't be used as {} of UART{}",
+              name, attr, uart_idx).as_slice());
+    },
+  }
+}
+
+/// Computes the baud rate divisor and fine adjust value for the given UART
+/// clock, as `(sbr, brfa, actual baud rate)`.
+fn calculate_divisors(clock: uint, baud_rate: uint)
+    -> Option<(uint, uint, uint)> {
+  if baud_rate == 0 {
+    return None;
+  }
+  // the divisor in 1/32 units, rounded to the nearest
+  let divisor = (2 * clock + baud_rate / 2) / baud_rate;
+  let sbr = divisor / 32;
+  if sbr < 1 || sbr > 8191 {
+    return None;
+  }
+  Some((sbr, divisor % 32, (2 * clock + divisor / 2) / divisor))
+}
+
+pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
+    sub: Rc<node::Node>) {
+  let uart_peripheral_str = match from_str::<uint>(sub.path.as_slice()) {
+    Some(idx) if idx <= 2 => format!("UART{}", idx),
+    _ => {
+      cx.parse_sess().span_diagnostic.span_err(sub.path_span,
+          format!("unknown UART `{}`, allowed values: 0, 1, 2",
+              sub.path).as_slice());
+      return
+    }
+  };
+  if !builder.claim(cx, Peripheral(uart_peripheral_str.clone()),
+      sub.path_span) {
+    return
+  }
+  let uart_peripheral = TokenString(uart_peripheral_str);
+
+  if sub.name.is_none() {
+    cx.parse_sess().span_diagnostic.span_err(sub.name_span,
+        "UART node must have a name");
+    return
+  }
+
+  if !sub.expect_no_subnodes(cx) || !sub.expect_attributes(cx, [
+      ("baud_rate", node::BaudRateAttribute),
+      ("mode", node::StrAttribute),
+      ("tx", node::RefAttribute),
+      ("rx", node::RefAttribute)]) {
+    return
+  }
+
+  let baud_rate = sub.get_baud_rate_attr("baud_rate").unwrap();
+  let mode = sub.get_string_attr("mode").unwrap();
+  let mode_error = || {
+    cx.parse_sess().span_diagnostic.span_err(sub.get_attr("mode").value_span,
+        format!("unsupported UART mode `{}`, the K20 supports 8 or 9 data \
+            bits, `N`, `O` or `E` parity and 1 stop bit", mode).as_slice());
+  };
+  if mode.len() != 3 {
+    mode_error();
+    return
+  }
+
+  let word_len: u8 = match mode.as_slice().char_at(0) {
+    '8' => 8,
+    '9' => 9,
+    _ => {
+      mode_error();
+      return
+    }
+  };
+  let parity = TokenString(match mode.as_slice().char_at(1) {
+    'N' => "Disabled",
+    'O' => "Odd",
+    'E' => "Even",
+    _ => {
+      mode_error();
+      return
+    }
+  }.to_string());
+  let stop_bits: u8 = match mode.as_slice().char_at(2) {
+    '1' => 1,
+    _ => {
+      mode_error();
+      return
+    }
+  };
+
+  // UART0 and UART1 run from the system clock, UART2 from the bus clock
+  let clock_node = builder.pt().get_by_path("mcu").unwrap()
+      .get_by_path("clock").unwrap();
+  let clock_attr = if sub.path.as_slice() == "2" {
+    "bus_frequency"
+  } else {
+    "system_frequency"
+  };
+  let clock = match clock_node.get_int_attr(clock_attr) {
+    Some(freq) => freq,
+    // the clock node failed to build
+    None => return,
+  };
+  let (sbr, brfa) = match calculate_divisors(clock, baud_rate) {
+    Some((sbr, brfa, actual)) => {
+      if !::uart_pt::check_baud_rate(cx, &sub, "baud_rate", baud_rate,
+          actual) {
+        return
+      }
+      (sbr as u16, brfa as u8)
+    },
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(
+          sub.get_attr("baud_rate").value_span,
+          format!("baud rate {} can't be derived from the {} Hz clock",
+              baud_rate, clock).as_slice());
+      return
+    },
+  };
+
+  sub.set_type_name("zinc::hal::k20::uart::UART".to_string());
+  let uart_name = TokenString(sub.name.clone().unwrap());
+
+  let st = quote_stmt!(&*cx,
+      let $uart_name = zinc::hal::k20::uart::UART::new_with_divisors(
+          zinc::hal::k20::uart::$uart_peripheral,
+          $sbr,
+          $brfa,
+          $word_len,
+          zinc::hal::uart::$parity,
+          $stop_bits)
+  );
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_uart() {
+    with_parsed("
+      mcu {
+        clock {
+          system_frequency = 48_000_000;
+        }
+      }
+      uart {
+        uart@1 {
+          baud_rate = 9600;
+          mode = \"8E1\";
+          tx = &uart_tx;
+          rx = &uart_rx;
+        }
+      }
+      gpio {
+        C {
+          uart_rx@3;
+          uart_tx@4;
+        }
+      }
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::mutate_pins(&mut builder, cx, pt.get_by_name("uart").unwrap());
+      super::build_uart(&mut builder, cx, pt.get_by_name("uart").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let uart = zinc::hal::k20::uart::UART::new_with_divisors(
+               zinc::hal::k20::uart::UART1,
+               312u16,
+               16u8,
+               8u8,
+               zinc::hal::uart::Even,
+               1u8);");
+
+      let tx_node = pt.get_by_name("uart_tx").unwrap();
+      assert!(tx_node.get_int_attr("function") == Some(3));
+
+      let rx_node = pt.get_by_name("uart_rx").unwrap();
+      assert!(rx_node.get_int_attr("function") == Some(3));
+    });
+  }
+
+  #[test]
+  fn calculates_divisors() {
+    assert!(super::calculate_divisors(48_000_000, 9600) ==
+        Some((312, 16, 9600)));
+    assert!(super::calculate_divisors(48_000_000, 115200) ==
+        Some((26, 1, 115246)));
+    assert!(super::calculate_divisors(48_000_000, 4_000_000) == None);
+  }
+
+  #[test]
+  fn fails_to_parse_bad_uart_pins() {
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      gpio { C { uart_tx@5; uart_rx@3; } }
+      uart { uart@1 {
+        baud_rate = 9600; mode = \"8N1\"; tx = &uart_tx; rx = &uart_rx;
+      } }
+    }");
+  }
+
+  #[test]
+  fn fails_to_parse_bad_uart_mode() {
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      gpio { C { uart_tx@4; uart_rx@3; } }
+      uart { uart@1 {
+        baud_rate = 9600; mode = \"8N2\"; tx = &uart_tx; rx = &uart_rx;
+      } }
+    }");
+  }
+}
@@ -15,10 +15,6 @@
 
 //! Watchdog for Kinetis SIM module.
 
-use util::support::nop;
-
-#[path="../../util/ioreg.rs"] mod ioreg;
-
 /// Watchdog state
 #[allow(missing_doc)]
 pub enum State {
@@ -28,29 +24,20 @@ pub enum State {
 
 /// Init watchdog
 pub fn init(state : State) {
-  unlock();
   match state {
     Disabled => {
-      reg::WDOG.stctrlh.set_en(false);
+      reg::WDOG().stctrlh.set_en(false);
     },
     Enabled => {
-      reg::WDOG.stctrlh.set_allowupdate(true);
+      reg::WDOG().stctrlh.set_allowupdate(true);
     },
   }
 }
 
-fn unlock() {
-  reg::WDOG.unlock.set_unlock(reg::UnlockSeq1);
-  reg::WDOG.unlock.set_unlock(reg::UnlockSeq2);
-
-  // Enforce one cycle delay
-  nop();
-}
-
 /// Write refresh sequence to refresh watchdog
 pub fn refresh() {
-  reg::WDOG.refresh.set_refresh(reg::RefreshSeq1);
-  reg::WDOG.refresh.set_refresh(reg::RefreshSeq2);
+  reg::WDOG().refresh.set_refresh(reg::RefreshSeq1);
+  reg::WDOG().refresh.set_refresh(reg::RefreshSeq2);
 }
 
 #[allow(dead_code)]
@@ -59,8 +46,9 @@ mod reg {
   use core::ops::Drop;
 
   ioregs!(WDOG = {
-  /// Status and Control Register High
-    0x0 => reg16 stctrlh
+    /// Status and Control Register High, writable within 256 bus
+    /// clocks of the unlock sequence
+    0x0 => reg16 stctrlh @ unlock=unlock(0xc520, 0xd928)
     {
       0 => en,             //= Watchdog enable
       4 => allowupdate     //= Enables updates to watchdog write-once registers,
@@ -78,17 +66,8 @@ mod reg {
 
     /// Unlock Register
     0xe => reg16 unlock {
-      0..15 => unlock: wo
-      {
-        0xc520 => UnlockSeq1,
-        0xd928 => UnlockSeq2,
-      },
+      0..15 => unlock: wo,
     },
 
-  })
-
-
-  extern {
-    #[link_name="k20_iomem_WDOG"] pub static WDOG: WDOG;
-  }
+  } WDOG @ 0x4005_2000)
 }
@@ -0,0 +1,83 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Ben Gamari <bgamari@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString};
+use node;
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(build_watchdog));
+  super::add_node_dependency_on_clock(builder, &node);
+}
+
+fn build_watchdog(builder: &mut Builder, cx: &mut ExtCtxt,
+    node: Rc<node::Node>) {
+  if !node.expect_no_subnodes(cx) ||
+     !node.expect_attributes(cx, [("state", node::StrAttribute)]) {
+    return;
+  }
+
+  let state = TokenString(
+      match node.get_string_attr("state").unwrap().as_slice() {
+        "disabled" => "Disabled",
+        "enabled"  => "Enabled",
+        other => {
+          cx.parse_sess().span_diagnostic.span_err(
+              node.get_attr("state").value_span,
+              format!("unknown watchdog state `{}`, allowed values: \
+                  `disabled`, `enabled`", other).as_slice());
+          return;
+        }
+      }.to_string());
+
+  let st = quote_stmt!(&*cx,
+      zinc::hal::k20::watchdog::init(zinc::hal::k20::watchdog::$state);
+  );
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_watchdog() {
+    with_parsed("
+      watchdog {
+        state = \"disabled\";
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_watchdog(&mut builder, cx,
+          pt.get_by_path("watchdog").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "zinc::hal::k20::watchdog::init(
+               zinc::hal::k20::watchdog::Disabled);");
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_state() {
+    fails_to_build("k20@mcu {
+      clock { source_frequency = 48_000_000; flash_divisor = 2; }
+      watchdog { state = \"sleepy\"; }
+    }");
+  }
+}
@@ -18,6 +18,6 @@
 pub mod system_clock;
 pub mod peripheral_clock;
 pub mod pin;
-// pub mod ssp;
+pub mod ssp;
 pub mod timer;
 pub mod uart;
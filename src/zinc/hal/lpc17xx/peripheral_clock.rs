@@ -23,8 +23,6 @@ use core::intrinsics::abort;
 
 use super::system_clock::system_clock;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
-
 /// Configures the state of peripheral clock.
 #[allow(missing_doc)]
 pub enum PeripheralClock {
@@ -103,16 +101,12 @@ pub enum PeripheralDivisor {
 impl PeripheralClock {
   /// Enables the given peripheral clock.
   pub fn enable(self) {
-    let bit: u32 = (1u32 << (self as uint)) as u32;
-    let val: u32 = reg::PCONP.value();
-    reg::PCONP.set_value(val | bit);
+    reg::PCONP().pconp.set_pcon(self as uint, true);
   }
 
   /// Disables the given peripheral clock.
   pub fn disable(self) {
-    let bit: u32 = !((1u32 << (self as uint)) as u32);
-    let val: u32 = reg::PCONP.value();
-    reg::PCONP.set_value(val & bit);
+    reg::PCONP().pconp.set_pcon(self as uint, false);
   }
 
   /// Returns the clock frequency based on active divisor.
@@ -122,9 +116,8 @@ impl PeripheralClock {
 
   /// Returns the given peripheral clock divisor.
   pub fn get_divisor(self) -> u8 {
-    let (reg, offset) = self.divisor_reg_and_offset();
-    let val = (reg.value() >> (offset as uint)) & 3;
-    match val {
+    let (reg, index) = self.divisor_reg_and_index();
+    match reg.pclksel.pclk(index) {
       1 => 1,
       2 => 2,
       0 => 4,
@@ -139,7 +132,7 @@ impl PeripheralClock {
   /// Sets the given peripheral clock divisor.
   pub fn set_divisor(self, divisor: u8) {
     self.verify_divisor(divisor);
-    let (reg, offset) = self.divisor_reg_and_offset();
+    let (reg, index) = self.divisor_reg_and_index();
     let divisor_value: u8 = match divisor {
       1   => 1,
       2   => 2,
@@ -148,10 +141,7 @@ impl PeripheralClock {
       _   => unsafe { abort() },
     };
 
-    let bits: u32 = divisor_value as u32 << (offset as uint);
-    let mask: u32 = !((3u32 << (offset as uint)) as u32);
-    let val: u32 = reg.value();
-    reg.set_value(val & mask | bits);
+    reg.pclksel.set_pclk(index, divisor_value as u32);
   }
 
   fn verify_divisor(self, divisor: u8) {
@@ -197,32 +187,38 @@ impl PeripheralClock {
     }
   }
 
-  fn divisor_reg_and_offset(self) -> (&'static reg::PCLKSEL, u32) {
-    match self.to_divisor() {
+  fn divisor_reg_and_index(self) -> (&'static reg::PCLKSEL, uint) {
+    let divisor = self.to_divisor();
+    match divisor {
       WDTDivisor|TIMER0Divisor|TIMER1Divisor|UART0Divisor|UART1Divisor|
       PWM1Divisor|I2C0Divisor|SPIDivisor|SSP1Divisor|DACDivisor|ADCDivisor|
-      CAN1Divisor|CAN2Divisor|ACFDivisor => (&reg::PCLKSEL0, self as u32),
+      CAN1Divisor|CAN2Divisor|ACFDivisor =>
+        (reg::PCLKSEL0(), divisor as uint / 2),
 
       QEIDivisor|GPIOINTDivisor|PCBDivisor|I2C1Divisor|SSP0Divisor|
       TIMER2Divisor|TIMER3Divisor|UART2Divisor|UART3Divisor|I2C2Divisor|
       I2SDivisor|RITDivisor|SYSCONDivisor|
-      MCDivisor => (&reg::PCLKSEL1, self as u32 - 32),
+      MCDivisor => (reg::PCLKSEL1(), (divisor as uint - 32) / 2),
     }
   }
 }
 
 mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
 
-  ioreg_old!(PCONP: u32, value)
-  reg_rw!(PCONP, u32, value, set_value, value)
-
-  ioreg_old!(PCLKSEL: u32, value)
-  reg_rw!(PCLKSEL, u32, value, set_value, value)
+  ioregs!(PCONP = {
+    0x0    => reg32 pconp {       //! Power control for peripherals
+      0..31  => pcon[32],         //= clock enable of each peripheral
+    }
+  } PCONP @ 0x400F_C0C4)
 
-  extern {
-    #[link_name="lpc17xx_iomem_PCONP"] pub static PCONP: PCONP;
-    #[link_name="lpc17xx_iomem_PCLKSEL0"] pub static PCLKSEL0: PCLKSEL;
-    #[link_name="lpc17xx_iomem_PCLKSEL1"] pub static PCLKSEL1: PCLKSEL;
+  ioregs!(PCLKSEL = {
+    0x0    => reg32 pclksel {     //! Peripheral clock selection
+      0..31  => pclk[16],         //= clock divisor of each peripheral
+    }
   }
+    PCLKSEL0 @ 0x400F_C1A8,
+    PCLKSEL1 @ 0x400F_C1AC,
+  )
 }
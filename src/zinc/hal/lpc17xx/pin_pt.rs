@@ -16,7 +16,7 @@
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 
-use builder::{Builder, TokenString, add_node_dependency};
+use builder::{Builder, TokenString, PinFunction, add_node_dependency};
 use node;
 use super::pinmap;
 
@@ -110,6 +110,11 @@ fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
               return;
             },
             Some(func_idx) => {
+              // only one pin can be routed to a peripheral function
+              if !builder.claim(cx, PinFunction(fun.clone()),
+                  node.path_span) {
+                return;
+              }
               format!("AltFunction{}", func_idx)
             }
           }
@@ -23,6 +23,7 @@ mod system_clock_pt;
 mod timer_pt;
 mod pin_pt;
 mod uart_pt;
+mod ssp_pt;
 
 mod pinmap;
 
@@ -35,6 +36,7 @@ pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
       "clock" => system_clock_pt::attach(builder, cx, sub.clone()),
       "timer" => timer_pt::attach(builder, cx, sub.clone()),
       "uart"  => uart_pt::attach(builder, cx, sub.clone()),
+      "spi"   => ssp_pt::attach(builder, cx, sub.clone()),
       "gpio"  => pin_pt::attach(builder, cx, sub.clone()),
       _ => (),
     }
@@ -43,7 +45,7 @@ pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
 
 fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
-  node.expect_subnodes(cx, ["clock", "timer", "uart", "gpio"]);
+  node.expect_subnodes(cx, ["clock", "timer", "uart", "spi", "gpio"]);
 }
 
 pub fn add_node_dependency_on_clock(builder: &mut Builder,
@@ -63,6 +65,21 @@ mod test {
     fails_to_build("lpc17xx@mcu { key = 1; }");
   }
 
+  #[test]
+  fn fails_to_build_pins_sharing_function() {
+    fails_to_build("lpc17xx@mcu {
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        pll { m = 50; n = 3; divisor = 4; }
+      }
+      gpio {
+        0 { sck_a@15 { function = \"sck0\"; } }
+        1 { sck_b@20 { function = \"sck0\"; } }
+      }
+    }");
+  }
+
   #[test]
   fn builds_lpc17xx_pt() {
     with_parsed("
@@ -132,7 +149,7 @@ mod test {
                 system_clock::init_clock(&system_clock::Clock{
                   source: system_clock::Main(12000000),
                   pll: core::option::Some(system_clock::PLL0{
-                    m: 50u8,
+                    m: 50u16,
                     n: 3u8,
                     divisor: 4u8,
                   }),
@@ -150,9 +167,11 @@ mod test {
                 3u8,
                 zinc::hal::lpc17xx::pin::AltFunction1,
                 core::option::None);
-            let uart = zinc::hal::lpc17xx::uart::UART::new(
+            let uart = zinc::hal::lpc17xx::uart::UART::new_with_divisors(
                 zinc::hal::lpc17xx::uart::UART0,
-                115200u32,
+                10u16,
+                5u8,
+                14u8,
                 8u8,
                 zinc::hal::uart::Disabled,
                 1u8);
@@ -16,92 +16,61 @@
 /*!
 SSP configuration.
 
-Currently supports only SPI mode. Note that `SPI` is not the same peripheral and
-it's currently not supported at all.
+Currently supports only SPI master mode. Note that `SPI` is not the same
+peripheral and it's currently not supported at all.
 */
 
 use core::intrinsics::abort;
 
-use hal::lpc17xx::peripheral_clock::{PeripheralClock, SSP0Clock, SSP1Clock};
-use hal::lpc17xx::system_clock::system_clock;
-use hal::pin::PinConf_;
+use hal::lpc17xx::peripheral_clock::{SSP0Clock, SSP1Clock};
 use hal::spi;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
+/// Available SSP peripherals.
+#[allow(missing_doc)]
+pub enum SSPPeripheral {
+  SSP0,
+  SSP1,
+}
 
-/// SPI configuration.
+/// Structure describing an SSP instance, that implements SPI trait.
 ///
-/// This configuration doesn't manage the chip-select pin, it must be configured
-/// and used externally via GPIOConf.
-pub struct SPIConf {
-  /// Peripheral to use, mcu-specific.
-  pub peripheral: SSPPeripheral,
-  /// Number of bits per transfer, commonly 8.
-  pub bits: u8,
-  /// SPI mode, see http://en.wikipedia.org/wiki/Serial_Peripheral_Interface_Bus#Mode_numbers for explanation.
-  pub mode: u8,
-  /// SPI bus frequency, obiviously must be lover than core clock.
-  ///
-  /// The divisor is currently hardcoded and is equal to 1.
-  pub frequency: u32,
-
-  /// MOSI pin to use, supports NotConnected pin.
-  pub mosi: PinConf_,
-  /// MISO pin to use, supports NotConnected pin.
-  pub miso: PinConf_,
-  /// SCLK pin to use, supports NotConnected pin.
-  pub sclk: PinConf_,
+/// The SSP doesn't manage its pins, they must be configured with `pin::Pin`
+/// using the SSP functions. The chip-select pin must be configured as GPIO
+/// and used externally.
+pub struct SSP {
+  reg: &'static reg::SSP,
 }
 
-impl SPIConf {
-  /// Returns a platform-specific object, that implements SPI trait.
-  pub fn setup(&self) -> SSP {
-    let ssp = SSP {
-      peripheral: self.peripheral,
-      reg: self.peripheral.reg(),
+impl SSP {
+  /// Create and setup an SSP in SPI master mode.
+  ///
+  /// `bits` is the number of bits per transfer, 4 to 16, and `mode` is the SPI
+  /// mode, see
+  /// http://en.wikipedia.org/wiki/Serial_Peripheral_Interface_Bus#Mode_numbers
+  /// for explanation.
+  ///
+  /// The peripheral clock divisor is set to 1, so the bus frequency is the core
+  /// clock divided by `prescaler`, an even number between 2 and 254, and by
+  /// `divider`, between 1 and 256.
+  pub fn new(peripheral: SSPPeripheral, bits: u8, mode: u8, prescaler: u8,
+      divider: u16) -> SSP {
+    let (clock, reg) = match peripheral {
+      SSP0 => (SSP0Clock, reg::SSP0()),
+      SSP1 => (SSP1Clock, reg::SSP1()),
     };
 
-    let clock = self.peripheral.peripheral_clock();
     clock.enable();
     clock.set_divisor(1);
-    ssp.set_format(self.bits, self.mode);
-    ssp.set_frequency(self.frequency);
 
-    self.mosi.setup();
-    self.miso.setup();
-    self.sclk.setup();
+    let ssp = SSP {
+      reg: reg,
+    };
+    ssp.set_format(bits, mode);
+    ssp.set_clock_rate(prescaler, divider);
 
     ssp
   }
-}
-
-/// Opaque object that manages the configured peripheral.
-#[allow(dead_code)]
-pub struct SSP {
-  peripheral: SSPPeripheral, // TODO(farcaller): clean up the warning
-  reg: &'static reg::SSP,
-}
-
-pub enum SSPPeripheral {SSP0, SSP1}
-
-impl SSPPeripheral {
-  fn reg(self) -> &reg::SSP {
-    match self {
-      SSP0 => &reg::SSP0,
-      SSP1 => &reg::SSP1,
-    }
-  }
-
-  fn peripheral_clock(self) -> PeripheralClock {
-    match self {
-      SSP0 => SSP0Clock,
-      SSP1 => SSP1Clock,
-    }
-  }
-}
 
-impl SSP {
-  #[allow(non_snake_case)]
   fn set_format(&self, bits: u8, mode: u8) {
     let slave = false;
 
@@ -113,90 +82,49 @@ impl SSP {
     let polarity = mode & 0x2 != 0;
     let phase = mode & 0x1 != 0;
 
-    let DSS: u32 = bits as u32 - 1;            // DSS (data select size)
-    let SPO: u32 = if polarity { 1 } else { 0 };  // SPO - clock out polarity
-    let SPH: u32 = if phase { 1 } else { 0 };     // SPH - clock out phase
-
-    let FRF: u32 = 0;                   // FRF (frame format) = SPI
-    let old_reg0: u32 = self.reg.CR0();
-    let new_reg0: u32 = old_reg0 & 0xffffff00 |
-      (DSS << 0) |
-      (FRF << 4) |
-      (SPO << 6) |
-      (SPH << 7) ;
-    self.reg.set_CR0(new_reg0);
-
-    let LBM: u32 = 0;
-    let SSE: u32 = 0;
-    let MS:  u32 = if slave { 1 } else { 0 };
-    let SOD: u32 = 0;
-    let new_reg1: u32 =
-      (LBM << 0) |
-      (SSE << 1) |
-      (MS  << 2) |
-      (SOD << 0);
-    self.reg.set_CR1(new_reg1);
+    self.reg.cr0
+      .set_dss(bits as u32 - 1)
+      .set_frf(reg::SPI)
+      .set_cpol(polarity)
+      .set_cpha(phase);
+
+    self.reg.cr1.write()
+      .set_ms(slave);
 
     self.enable();
   }
 
-  fn set_frequency(&self, freq: u32) {
-    self.disable();
-
-    let mut prescaler: u32 = 2;
-
-    while prescaler <= 254 {
-      let prescale_hz: u32 = system_clock() / prescaler;
+  fn set_clock_rate(&self, prescaler: u8, divider: u16) {
+    if prescaler < 2 || prescaler & 1 != 0 || divider < 1 || divider > 256 {
+      unsafe { abort() };
+    }
 
-      // calculate the divider
-      let divider: u32 = ((prescale_hz as f32 / freq as f32) + 0.5f32) as u32;
+    self.disable();
 
-      // check we can support the divider
-      if divider < 256 {
-          // prescaler
-          self.reg.set_CPSR(prescaler);
+    self.reg.cpsr.write().set_cpsdvsr(prescaler as u32);
+    self.reg.cr0.set_scr(divider as u32 - 1);
 
-          // divider
-          let old_reg: u32 = self.reg.CR0();
-          let new_reg: u32 = old_reg & 0xff |
-            ((divider-1) << 8);
-          self.reg.set_CR0(new_reg);
-          self.enable();
-          return
-      }
-      prescaler += 2;
-    }
-    unsafe { abort() };
+    self.enable();
   }
 
   fn disable(&self) {
-    let old_reg: u32 = self.reg.CR1();
-    let new_reg: u32 = old_reg & 0b1101;
-    self.reg.set_CR1(new_reg);
+    self.reg.cr1.set_sse(false);
   }
 
   fn enable(&self) {
-    let old_reg: u32 = self.reg.CR1();
-    let new_reg: u32 = old_reg | 0b0010;
-    self.reg.set_CR1(new_reg);
+    self.reg.cr1.set_sse(true);
   }
 
   fn readable(&self) -> bool {
-    let val: u32 = self.reg.SR();
-
-    (val & 0b00100) == 0b00100
+    self.reg.sr.rne()
   }
 
   fn writeable(&self) -> bool {
-    let val: u32 = self.reg.SR();
-
-    (val & 0b00010) == 0b00010
+    self.reg.sr.tnf()
   }
 
   fn written(&self) -> bool {
-    let val: u32 = self.reg.SR();
-
-    (val & 0b10000) == 0
+    !self.reg.sr.bsy()
   }
 }
 
@@ -205,7 +133,7 @@ impl spi::SPI for SSP {
     loop { if self.writeable() {
       break;
     } }
-    self.reg.set_DR(value as u32);
+    self.reg.dr.write().set_data(value as u32);
     loop { if self.written() {
         break;
     } }
@@ -217,27 +145,82 @@ impl spi::SPI for SSP {
         break;
       }
     }
-    (self.reg.DR() & 0xff) as u8
+    (self.reg.dr.data() & 0xff) as u8
   }
 }
 
 mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(SSP = {
+    0x00   => reg32 cr0 {         //! Control 0
+      0..3   => dss,              //= data size select, bits per transfer - 1
+      4..5   => frf {             //= frame format
+        0 => SPI,
+        1 => TI,
+        2 => Microwire,
+      }
+      6      => cpol,             //= clock out polarity
+      7      => cpha,             //= clock out phase
+      8..15  => scr,              //= serial clock rate
+    }
+
+    0x04   => reg32 cr1 {         //! Control 1
+      0      => lbm,              //= loop back mode
+      1      => sse,              //= SSP enable
+      2      => ms,               //= slave mode
+      3      => sod,              //= slave output disable
+    }
+
+    0x08   => reg32 dr {          //! Data
+      0..15  => data,
+    }
+
+    0x0c   => reg32 sr {          //! Status
+      0      => tfe: ro,          //= transmit FIFO empty
+      1      => tnf: ro,          //= transmit FIFO not full
+      2      => rne: ro,          //= receive FIFO not empty
+      3      => rff: ro,          //= receive FIFO full
+      4      => bsy: ro,          //= busy
+    }
+
+    0x10   => reg32 cpsr {        //! Clock prescale
+      0..7   => cpsdvsr,          //= even prescaler, 2 to 254
+    }
+
+    0x14   => reg32 imsc {        //! Interrupt mask set and clear
+      0      => rorim,            //= receive overrun
+      1      => rtim,             //= receive time-out
+      2      => rxim,             //= RX FIFO at least half full
+      3      => txim,             //= TX FIFO at least half empty
+    }
 
-  ioreg_old!(SSP: u32, CR0, CR1, DR, SR, CPSR, IMSC, RIS, MIS, ICR, DMACR)
-  reg_rw!(SSP, u32, CR0,   set_CR0,   CR0)
-  reg_rw!(SSP, u32, CR1,   set_CR1,   CR1)
-  reg_rw!(SSP, u32, DR,    set_DR,    DR)
-  reg_r!( SSP, u32, SR,               SR)
-  reg_rw!(SSP, u32, CPSR,  set_CPSR,  CPSR)
-  reg_rw!(SSP, u32, IMSC,  set_IMSC,  IMSC)
-  reg_rw!(SSP, u32, RIS,   set_RIS,   RIS)
-  reg_rw!(SSP, u32, MIS,   set_MIS,   MIS)
-  reg_rw!(SSP, u32, ICR,   set_ICR,   ICR)
-  reg_rw!(SSP, u32, DMACR, set_DMACR, DMACR)
-
-  extern {
-    #[link_name="lpc17xx_iomem_SSP0"] pub static SSP0: SSP;
-    #[link_name="lpc17xx_iomem_SSP1"] pub static SSP1: SSP;
+    0x18   => reg32 ris {         //! Raw interrupt status
+      0      => rorris: ro,
+      1      => rtris: ro,
+      2      => rxris: ro,
+      3      => txris: ro,
+    }
+
+    0x1c   => reg32 mis {         //! Masked interrupt status
+      0      => rormis: ro,
+      1      => rtmis: ro,
+      2      => rxmis: ro,
+      3      => txmis: ro,
+    }
+
+    0x20   => reg32 icr {         //! Interrupt clear
+      0      => roric: wo,        //= clear receive overrun interrupt
+      1      => rtic: wo,         //= clear receive time-out interrupt
+    }
+
+    0x24   => reg32 dmacr {       //! DMA control
+      0      => rxdmae,           //= receive DMA enable
+      1      => txdmae,           //= transmit DMA enable
+    }
   }
+    SSP1 @ 0x4003_0000,
+    SSP0 @ 0x4008_8000,
+  )
 }
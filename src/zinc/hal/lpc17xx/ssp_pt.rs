@@ -0,0 +1,302 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
+use node;
+use super::pinmap;
+
+/// Pin attributes of an SSP node and the pinmap function they use.
+static SSP_PINS: &'static [(&'static str, &'static str)] = &[
+  ("mosi", "mosi"),
+  ("miso", "miso"),
+  ("sclk", "sck"),
+];
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+
+  for sub in node.subnodes().iter() {
+    add_node_dependency(&node, sub);
+    for &(attr, _) in SSP_PINS.iter() {
+      let pin_node = sub.get_ref_attr(attr).and_then(|name|
+          builder.pt().get_by_name(name.as_slice()));
+      match pin_node {
+        Some(pin_node) => add_node_dependency(sub, &pin_node),
+        None => (),
+      }
+    }
+    super::add_node_dependency_on_clock(builder, sub);
+
+    sub.materializer.set(Some(build_ssp));
+    sub.mutator.set(Some(mutate_pins));
+  }
+}
+
+pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+}
+
+/// Sets the function of the pins used by the SSP, checking that they support
+/// it.
+pub fn mutate_pins(builder: &mut Builder, cx: &mut ExtCtxt,
+    sub: Rc<node::Node>) {
+  let ssp_idx = match from_str::<uint>(sub.path.as_slice()) {
+    Some(idx) => idx,
+    // reported by build_ssp
+    None => return,
+  };
+  for &(attr, function) in SSP_PINS.iter() {
+    build_ssp_gpio(builder, cx, &sub, attr,
+        format!("{}{}", function, ssp_idx));
+  }
+}
+
+fn build_ssp_gpio(builder: &mut Builder, cx: &ExtCtxt, sub: &Rc<node::Node>,
+    attr: &str, function: String) {
+  let name = match sub.get_ref_attr(attr) {
+    Some(name) => name,
+    None => return,
+  };
+  let node = match builder.pt().get_by_name(name.as_slice()) {
+    Some(node) => node,
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(sub.get_attr(attr).value_span,
+          format!("unknown pin `{}`", name).as_slice());
+      return;
+    }
+  };
+  if !builder.claim_pin(cx, &node, sub.get_attr(attr).value_span) {
+    return;
+  }
+
+  let port_node = node.parent.clone().unwrap().upgrade().unwrap();
+  let port_def = pinmap::port_def();
+  let pin_funcs = port_def.find(&port_node.path).and_then(|pins|
+      from_str::<uint>(node.path.as_slice()).and_then(|idx|
+          if idx < pins.len() { pins[idx].clone() } else { None }));
+  let supported = match pin_funcs {
+    Some(ref funcs) => funcs.contains_key(&function),
+    None => false,
+  };
+  if !supported {
+    cx.parse_sess().span_diagnostic.span_err(sub.get_attr(attr).value_span,
+        format!("pin `{}` doesn't support function `{}`",
+            name, function).as_slice());
+    return;
+  }
+
+  node.attributes.borrow_mut().insert("function".to_string(),
+      Rc::new(node::Attribute::new_nosp(node::StrValue(function))));
+}
+
+/// Finds the SSP prescaler (CPSR) and divider (SCR + 1) for the fastest bus
+/// clock that doesn't exceed the requested frequency.
+fn clock_rate(pclk: uint, frequency: uint) -> Option<(uint, uint)> {
+  if frequency == 0 || frequency > pclk / 2 {
+    return None;
+  }
+  let mut prescaler = 2;
+  while prescaler <= 254 {
+    let divider = (pclk + prescaler * frequency - 1) / (prescaler * frequency);
+    if divider <= 256 {
+      return Some((prescaler, divider));
+    }
+    prescaler += 2;
+  }
+  None
+}
+
+pub fn build_ssp(builder: &mut Builder, cx: &mut ExtCtxt,
+    sub: Rc<node::Node>) {
+  let ssp_peripheral_str = match from_str::<uint>(sub.path.as_slice()) {
+    Some(idx) if idx <= 1 => format!("SSP{}", idx),
+    _ => {
+      cx.parse_sess().span_diagnostic.span_err(sub.path_span,
+          format!("unknown SSP `{}`, allowed values: 0, 1",
+              sub.path).as_slice());
+      return
+    }
+  };
+  if !builder.claim(cx, Peripheral(ssp_peripheral_str.clone()),
+      sub.path_span) {
+    return
+  }
+  let ssp_peripheral = TokenString(ssp_peripheral_str);
+
+  if sub.name.is_none() {
+    cx.parse_sess().span_diagnostic.span_err(sub.name_span,
+        "SSP node must have a name");
+    return
+  }
+
+  if !sub.expect_no_subnodes(cx) || !sub.expect_attributes(cx, [
+      ("frequency", node::FrequencyAttribute),
+      ("bits", node::IntAttribute),
+      ("mode", node::IntAttribute),
+      ("sclk", node::RefAttribute)]) {
+    return
+  }
+
+  let bits = sub.get_int_attr("bits").unwrap();
+  if bits < 4 || bits > 16 {
+    cx.parse_sess().span_diagnostic.span_err(sub.get_attr("bits").value_span,
+        format!("unsupported bits per transfer `{}`, allowed values: 4...16",
+            bits).as_slice());
+    return
+  }
+  let mode = sub.get_int_attr("mode").unwrap();
+  if mode > 3 {
+    cx.parse_sess().span_diagnostic.span_err(sub.get_attr("mode").value_span,
+        format!("unknown SPI mode `{}`, allowed values: 0...3",
+            mode).as_slice());
+    return
+  }
+
+  let clock_node = builder.pt().get_by_path("mcu").unwrap()
+      .get_by_path("clock").unwrap();
+  let pclk = match clock_node.get_int_attr(
+      format!("pclk_ssp{}", sub.path).as_slice()) {
+    Some(freq) => freq,
+    // the clock node failed to build
+    None => return,
+  };
+  let frequency = sub.get_frequency_attr("frequency").unwrap();
+  let (prescaler, divider) = match clock_rate(pclk, frequency) {
+    Some(rate) => rate,
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(
+          sub.get_attr("frequency").value_span,
+          format!("SPI frequency `{}` can't be derived from the {} Hz \
+              peripheral clock, allowed values: {}...{}",
+              frequency, pclk, pclk / 254 / 256, pclk / 2).as_slice());
+      return
+    }
+  };
+
+  sub.set_type_name("zinc::hal::lpc17xx::ssp::SSP".to_string());
+  let ssp_name = TokenString(sub.name.clone().unwrap());
+  let bits: u8 = bits as u8;
+  let mode: u8 = mode as u8;
+  let prescaler: u8 = prescaler as u8;
+  let divider: u16 = divider as u16;
+
+  let st = quote_stmt!(&*cx,
+      let $ssp_name = zinc::hal::lpc17xx::ssp::SSP::new(
+          zinc::hal::lpc17xx::ssp::$ssp_peripheral,
+          $bits,
+          $mode,
+          $prescaler,
+          $divider)
+  );
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn computes_clock_rate() {
+    assert!(super::clock_rate(100_000_000, 6_000_000) == Some((2, 9)));
+    assert!(super::clock_rate(100_000_000, 50_000_000) == Some((2, 1)));
+    assert!(super::clock_rate(100_000_000, 100_000) == Some((2, 250)));
+    assert!(super::clock_rate(100_000_000, 10_000) == Some((40, 250)));
+    assert!(super::clock_rate(100_000_000, 60_000_000) == None);
+    assert!(super::clock_rate(100_000_000, 1_000) == None);
+  }
+
+  #[test]
+  fn builds_ssp() {
+    with_parsed("
+      mcu {
+        clock {
+          pclk_ssp1 = 100_000_000;
+        }
+      }
+      spi {
+        ssp@1 {
+          frequency = 6_000_000;
+          bits = 8;
+          mode = 3;
+          mosi = &lcd_mosi;
+          sclk = &lcd_sclk;
+        }
+      }
+      gpio {
+        0 {
+          lcd_sclk@7;
+          lcd_mosi@9;
+        }
+      }
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::mutate_pins(&mut builder, cx, pt.get_by_name("ssp").unwrap());
+      super::build_ssp(&mut builder, cx, pt.get_by_name("ssp").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let ssp = zinc::hal::lpc17xx::ssp::SSP::new(
+               zinc::hal::lpc17xx::ssp::SSP1,
+               8u8,
+               3u8,
+               2u8,
+               9u16);");
+
+      let mosi_node = pt.get_by_name("lcd_mosi").unwrap();
+      assert!(mosi_node.get_string_attr("function").unwrap() ==
+          "mosi1".to_string());
+
+      let sclk_node = pt.get_by_name("lcd_sclk").unwrap();
+      assert!(sclk_node.get_string_attr("function").unwrap() ==
+          "sck1".to_string());
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_ssp_pins() {
+    fails_to_build("lpc17xx@mcu {
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        pll { m = 50; n = 3; divisor = 4; }
+      }
+      spi { ssp@1 {
+        frequency = 6_000_000; bits = 8; mode = 3; sclk = &sclk;
+      } }
+      gpio { 0 { sclk@8; } }
+    }");
+  }
+
+  #[test]
+  fn fails_to_parse_bad_ssp_frequency() {
+    fails_to_build("lpc17xx@mcu {
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        pll { m = 50; n = 3; divisor = 4; }
+      }
+      spi { ssp@1 {
+        frequency = 60_000_000; bits = 8; mode = 3; sclk = &sclk;
+      } }
+      gpio { 0 { sclk@7; } }
+    }");
+  }
+}
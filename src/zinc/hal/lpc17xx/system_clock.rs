@@ -22,7 +22,6 @@ performing initial peripheral configuration.
 
 use core::option::{Option, Some, None};
 
-#[path="../../util/ioreg.rs"] mod ioreg;
 #[path="../../util/wait_for.rs"] mod wait_for;
 
 /// PLL clock source.
@@ -45,7 +44,7 @@ pub enum ClockSource {
 /// ```
 pub struct PLL0 {
   /// PLL multiplier.
-  pub m: u8,
+  pub m: u16,
   /// PLL divisor.
   pub n: u8,
   /// PLL output divisor.
@@ -97,12 +96,11 @@ pub fn init_clock(clock: &Clock) {
 
 #[inline(always)]
 fn init_main_oscillator(freq: u32) {
-  let val: u32 = if freq > 15_000_000 { 1 << 4 } else { 0 } |
-                 (1 << 5);
+  reg::SYSCON().scs.write()
+    .set_oscrange(freq > 15_000_000)
+    .set_oscen(true);
 
-  reg::SCS.set_value(val);
-
-  wait_for!(reg::SCS.value() & (1 << 6) == (1 << 6));
+  wait_for!(reg::SYSCON().scs.oscstat());
 }
 
 #[inline(always)]
@@ -113,70 +111,74 @@ fn init_flash_access(freq: u32) {
                         if freq > 40_000_000 { 3 } else
                         if freq > 20_000_000 { 2 } else
                         { 1 };
-  let val = (num_clocks - 1) << 12;
-  reg::FLASHCFG.set_value(val);
-}
-
-#[inline(always)]
-fn wait_for_pll0stat_bit(bit: uint) {
-  wait_for!(reg::PLL0STAT.value() & (1 << bit) == (1 << bit));
-}
-
-#[inline(always)]
-fn write_pll0_changes() {
-  reg::PLL0FEED.set_value(0xaa);
-  reg::PLL0FEED.set_value(0x55);
+  reg::SYSCON().flashcfg.write().set_flashtim(num_clocks - 1);
 }
 
 #[inline(always)]
 fn init_pll(pll: &PLL0, source: ClockSource) {
+  let syscon = reg::SYSCON();
   match source {
-    Internal => reg::CLKSRCSEL.set_value(0),
-    Main(_)  => reg::CLKSRCSEL.set_value(1),
-    RTC =>      reg::CLKSRCSEL.set_value(2),
-  }
+    Internal => syscon.clksrcsel.write().set_clksrc(0),
+    Main(_)  => syscon.clksrcsel.write().set_clksrc(1),
+    RTC =>      syscon.clksrcsel.write().set_clksrc(2),
+  };
 
-  let val: u32 = ((pll.n as u32 - 1) << 16) | ((pll.m as u32 - 1) << 0);
-  reg::PLL0CFG.set_value(val);
-  write_pll0_changes();
-  reg::PLL0CON.set_value(1);
-  write_pll0_changes();
-  wait_for_pll0stat_bit(24);
-  reg::CCLKCFG.set_value((pll.divisor - 1) as u32);
-  wait_for_pll0stat_bit(26);
-  reg::PLL0CON.set_value(3);
-  write_pll0_changes();
-  wait_for_pll0stat_bit(25);
+  syscon.pll0cfg.write()
+    .set_msel(pll.m as u32 - 1)
+    .set_nsel(pll.n as u32 - 1);
+  syscon.pll0con.write().set_plle(true);
+  wait_for!(syscon.pll0stat.plle());
+  syscon.cclkcfg.write().set_cclksel((pll.divisor - 1) as u32);
+  wait_for!(syscon.pll0stat.plock());
+  syscon.pll0con.write().set_plle(true).set_pllc(true);
+  wait_for!(syscon.pll0stat.pllc());
 }
 
 mod reg {
   use util::volatile_cell::VolatileCell;
-
-  ioreg_old!(SCS: u32, value)
-  reg_rw!(SCS, u32, value, set_value, value)
-  ioreg_old!(FLASHCFG: u32, value)
-  reg_w!(FLASHCFG, u32, set_value, value)
-  ioreg_old!(PLL0CFG: u32, value)
-  reg_w!(PLL0CFG, u32, set_value, value)
-  ioreg_old!(PLL0CON: u32, value)
-  reg_w!(PLL0CON, u32, set_value, value)
-  ioreg_old!(PLL0FEED: u32, value)
-  reg_w!(PLL0FEED, u32, set_value, value)
-  ioreg_old!(PLL0STAT: u32, value)
-  reg_r!(PLL0STAT, u32, value, value)
-  ioreg_old!(CCLKCFG: u32, value)
-  reg_w!(CCLKCFG, u32, set_value, value)
-  ioreg_old!(CLKSRCSEL: u32, value)
-  reg_w!(CLKSRCSEL, u32, set_value, value)
-
-  extern {
-    #[link_name="lpc17xx_iomem_SCS"] pub static SCS: SCS;
-    #[link_name="lpc17xx_iomem_FLASHCFG"] pub static FLASHCFG: FLASHCFG;
-    #[link_name="lpc17xx_iomem_PLL0CFG"] pub static PLL0CFG: PLL0CFG;
-    #[link_name="lpc17xx_iomem_PLL0CON"] pub static PLL0CON: PLL0CON;
-    #[link_name="lpc17xx_iomem_CCLKCFG"] pub static CCLKCFG: CCLKCFG;
-    #[link_name="lpc17xx_iomem_PLL0FEED"] pub static PLL0FEED: PLL0FEED;
-    #[link_name="lpc17xx_iomem_CLKSRCSEL"] pub static CLKSRCSEL: CLKSRCSEL;
-    #[link_name="lpc17xx_iomem_PLL0STAT"] pub static PLL0STAT: PLL0STAT;
-  }
+  use core::ops::Drop;
+
+  ioregs!(SYSCON = {
+    0x0    => reg32 flashcfg {    //! Flash accelerator configuration
+      12..15 => flashtim,         //= flash access time, in CPU clocks - 1
+    }
+
+    /// PLL0 control, takes effect after the feed sequence
+    0x80   => reg32 pll0con @ feed=pll0feed(0xaa, 0x55) {
+      0      => plle,             //= PLL0 enable
+      1      => pllc,             //= PLL0 connect
+    }
+
+    /// PLL0 configuration, takes effect after the feed sequence
+    0x84   => reg32 pll0cfg @ feed=pll0feed(0xaa, 0x55) {
+      0..14  => msel,             //= PLL0 multiplier - 1
+      16..23 => nsel,             //= PLL0 pre-divider - 1
+    }
+
+    0x88   => reg32 pll0stat {    //! PLL0 status
+      0..14  => msel: ro,         //= PLL0 multiplier - 1 in use
+      16..23 => nsel: ro,         //= PLL0 pre-divider - 1 in use
+      24     => plle: ro,         //= PLL0 enabled
+      25     => pllc: ro,         //= PLL0 connected
+      26     => plock: ro,        //= PLL0 locked
+    }
+
+    0x8c   => reg32 pll0feed {    //! PLL0 feed sequence
+      0..7   => pll0feed: wo,
+    }
+
+    0x104  => reg32 cclkcfg {     //! CPU clock configuration
+      0..7   => cclksel,          //= divides PLL0 output for the CPU clock
+    }
+
+    0x10c  => reg32 clksrcsel {   //! Clock source select
+      0..1   => clksrc,           //= PLL0 clock source
+    }
+
+    0x1a0  => reg32 scs {         //! System controls and status
+      4      => oscrange,         //= main oscillator range is 15-25MHz
+      5      => oscen,            //= main oscillator enable
+      6      => oscstat: ro,      //= main oscillator ready
+    }
+  } SYSCON @ 0x400F_C000)
 }
@@ -13,6 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::iter::range_inclusive;
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 use syntax::ext::build::AstBuilder;
@@ -20,13 +21,84 @@ use syntax::ext::build::AstBuilder;
 use builder::{Builder, TokenString};
 use node;
 
+// PLL0 and CPU clock limits, see UM10360 chapter 4.5.
+static MIN_PLL_INPUT_FREQUENCY: uint = 32_000;
+static MAX_PLL_INPUT_FREQUENCY: uint = 50_000_000;
+static MIN_CCO_FREQUENCY: uint = 275_000_000;
+static MAX_CCO_FREQUENCY: uint = 550_000_000;
+static MAX_SYSTEM_FREQUENCY: uint = 120_000_000;
+
+/// Peripheral clocks with the divisor their drivers set up.
+///
+/// Timers aren't listed, as their divisor is configured in the timer node.
+static PERIPHERAL_CLOCKS: &'static [(&'static str, uint)] = &[
+  ("uart0", 4), ("uart1", 4), ("uart2", 4), ("uart3", 4),
+  ("ssp0",  1), ("ssp1",  1),
+];
+
 pub fn attach(_: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(build_clock));
 }
 
+/// Reports an error and returns false if an integer attribute is out of the
+/// given inclusive range.
+fn check_range(cx: &ExtCtxt, node: &Rc<node::Node>, key: &str, value: uint,
+    min: uint, max: uint) -> bool {
+  if value < min || value > max {
+    cx.parse_sess().span_diagnostic.span_err(node.get_attr(key).value_span,
+        format!("invalid {} `{}`, allowed values: {}...{}",
+            key, value, min, max).as_slice());
+    false
+  } else {
+    true
+  }
+}
+
+/// Finds PLL0 `(m, n, divisor)` that produce exactly `target` Hz from
+/// `source` Hz, preferring the lowest pre-divider and CCO frequency.
+fn solve_pll(source: uint, target: uint) -> Option<(uint, uint, uint)> {
+  for n in range_inclusive(1u, 32) {
+    if source < MIN_PLL_INPUT_FREQUENCY * n ||
+       source > MAX_PLL_INPUT_FREQUENCY * n {
+      continue;
+    }
+    for m in range_inclusive(6u, 512) {
+      // CCO frequency multiplied by n, to keep the division exact
+      let cco_n = 2 * m * source;
+      if cco_n < MIN_CCO_FREQUENCY * n || cco_n > MAX_CCO_FREQUENCY * n ||
+         cco_n % (target * n) != 0 {
+        continue;
+      }
+      let divisor = cco_n / (target * n);
+      if divisor >= 3 && divisor <= 255 {
+        return Some((m, n, divisor));
+      }
+    }
+  }
+  None
+}
+
+/// Number of CPU clocks a flash access takes at the given frequency.
+fn flash_access_clocks(freq: uint) -> uint {
+  match freq {
+    0...20_000_000 => 1,
+    20_000_001...40_000_000 => 2,
+    40_000_001...60_000_000 => 3,
+    60_000_001...80_000_000 => 4,
+    80_000_001...100_000_000 => 5,
+    _ => 6,
+  }
+}
+
+fn set_int_attr(node: &Rc<node::Node>, key: String, value: uint) {
+  node.attributes.borrow_mut().insert(key,
+      Rc::new(node::Attribute::new_nosp(node::IntValue(value))));
+}
+
 fn build_clock(builder: &mut Builder, cx: &mut ExtCtxt,
     node: Rc<node::Node>) {
-  if !node.expect_attributes(cx, [("source", node::StrAttribute)]) {
+  if !node.expect_attributes(cx, [("source", node::StrAttribute)]) ||
+     !node.expect_subnodes(cx, ["pll"]) {
     return;
   }
 
@@ -43,7 +115,7 @@ fn build_clock(builder: &mut Builder, cx: &mut ExtCtxt,
     },
     "main-oscillator"     => {
       let some_source_frequency =
-          node.get_required_int_attr(cx, "source_frequency");
+          node.get_required_frequency_attr(cx, "source_frequency");
       if some_source_frequency == None {
         source_freq = 0;
         "BAD".to_string()
@@ -60,37 +132,100 @@ fn build_clock(builder: &mut Builder, cx: &mut ExtCtxt,
       "BAD".to_string()
     },
   });
+  if source_freq == 0 {
+    return;
+  }
 
-  let some_pll_conf = node.get_by_path("pll").and_then(|sub|
-      -> Option<(uint, uint, uint)> {
-    if !sub.expect_no_subnodes(cx) || !sub.expect_attributes(cx, [
-        ("m", node::IntAttribute),
-        ("n", node::IntAttribute),
-        ("divisor", node::IntAttribute)]) {
-      None
-    } else {
+  let some_pll_conf = match (node.get_by_path("pll"),
+      node.get_frequency_attr("target_frequency")) {
+    (Some(sub), None) => {
+      if !sub.expect_no_subnodes(cx) || !sub.expect_attributes(cx, [
+          ("m", node::IntAttribute),
+          ("n", node::IntAttribute),
+          ("divisor", node::IntAttribute)]) {
+        return;
+      }
       let m = sub.get_int_attr("m").unwrap();
       let n = sub.get_int_attr("n").unwrap();
       let divisor = sub.get_int_attr("divisor").unwrap();
+      if !check_range(cx, &sub, "m", m, 6, 512) ||
+         !check_range(cx, &sub, "n", n, 1, 32) ||
+         !check_range(cx, &sub, "divisor", divisor, 3, 255) {
+        return;
+      }
+
+      let pll_in = source_freq / n;
+      if pll_in < MIN_PLL_INPUT_FREQUENCY || pll_in > MAX_PLL_INPUT_FREQUENCY {
+        cx.parse_sess().span_diagnostic.span_err(sub.get_attr("n").value_span,
+            format!("PLL input frequency {} Hz is out of the 32 kHz-50 MHz \
+                range", pll_in).as_slice());
+        return;
+      }
+      let cco = 2 * m * source_freq / n;
+      if cco < MIN_CCO_FREQUENCY || cco > MAX_CCO_FREQUENCY {
+        cx.parse_sess().span_diagnostic.span_err(sub.get_attr("m").value_span,
+            format!("PLL CCO frequency {} Hz is out of the 275-550 MHz range",
+                cco).as_slice());
+        return;
+      }
       Some((m, n, divisor))
-    }
-  });
+    },
+    (None, Some(target)) => {
+      if target > MAX_SYSTEM_FREQUENCY {
+        cx.parse_sess().span_diagnostic.span_err(
+            node.get_attr("target_frequency").value_span,
+            format!("target frequency {} Hz is over {} Hz",
+                target, MAX_SYSTEM_FREQUENCY).as_slice());
+        return;
+      }
+      match solve_pll(source_freq, target) {
+        Some(conf) => Some(conf),
+        None => {
+          cx.parse_sess().span_diagnostic.span_err(
+              node.get_attr("target_frequency").value_span,
+              format!("no PLL configuration produces exactly {} Hz from the \
+                  {} Hz source, use a different `target_frequency` or an \
+                  explicit `pll`", target, source_freq).as_slice());
+          return;
+        },
+      }
+    },
+    (Some(_), Some(_)) => {
+      cx.parse_sess().span_diagnostic.span_err(
+          node.get_attr("target_frequency").value_span,
+          "`target_frequency` can't be used together with subnode `pll`");
+      return;
+    },
+    (None, None) => None,
+  };
   if some_pll_conf.is_none() {
     cx.parse_sess().span_diagnostic.span_err(node.name_span,
-        "required subnode `pll` is missing");
+        "required subnode `pll` or attribute `target_frequency` is missing");
     return;
   }
 
   let (m, n, divisor) = some_pll_conf.unwrap();
-  let pll_m: u8 = m as u8;
+  let sysfreq = 2 * m * source_freq / n / divisor;
+  if sysfreq > MAX_SYSTEM_FREQUENCY {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        format!("system clock frequency {} Hz is over {} Hz",
+            sysfreq, MAX_SYSTEM_FREQUENCY).as_slice());
+    return;
+  }
+
+  set_int_attr(&node, "system_frequency".to_string(), sysfreq);
+  set_int_attr(&node, "pll_frequency".to_string(), 2 * m * source_freq / n);
+  set_int_attr(&node, "flash_access_clocks".to_string(),
+      flash_access_clocks(sysfreq));
+  for &(peripheral, pclk_divisor) in PERIPHERAL_CLOCKS.iter() {
+    set_int_attr(&node, format!("pclk_{}", peripheral),
+        sysfreq / pclk_divisor);
+  }
+
+  let pll_m: u16 = m as u16;
   let pll_n: u8 = n as u8;
   let pll_divisor: u8 = divisor as u8;
 
-  let sysfreq = source_freq * 2 * pll_m as uint / pll_n as uint
-      / pll_divisor as uint;
-  node.attributes.borrow_mut().insert("system_frequency".to_string(),
-      Rc::new(node::Attribute::new_nosp(node::IntValue(sysfreq))));
-
   let ex = quote_expr!(&*cx,
       {
         use zinc::hal::lpc17xx::system_clock;
@@ -138,7 +273,7 @@ mod test {
                 &system_clock::Clock {
                   source: system_clock::Main(12000000),
                   pll: core::option::Some(system_clock::PLL0 {
-                    m: 50u8,
+                    m: 50u16,
                     n: 3u8,
                     divisor: 4u8,
                   }),
@@ -170,6 +305,88 @@ mod test {
     });
   }
 
+  #[test]
+  fn solves_pll_for_target_frequency() {
+    with_parsed("
+      clock {
+        source = \"main-oscillator\";
+        source_frequency = 12_000_000;
+        target_frequency = 100_000_000;
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      let node = pt.get_by_path("clock").unwrap();
+      super::build_clock(&mut builder, cx, node.clone());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "{
+            use zinc::hal::lpc17xx::system_clock;
+            system_clock::init_clock(
+                &system_clock::Clock {
+                  source: system_clock::Main(12000000),
+                  pll: core::option::Some(system_clock::PLL0 {
+                    m: 25u16,
+                    n: 2u8,
+                    divisor: 3u8,
+                  }),
+                }
+            );
+          };");
+
+      assert!(node.get_int_attr("system_frequency") == Some(100_000_000));
+      assert!(node.get_int_attr("pll_frequency") == Some(300_000_000));
+      assert!(node.get_int_attr("flash_access_clocks") == Some(5));
+      assert!(node.get_int_attr("pclk_uart0") == Some(25_000_000));
+      assert!(node.get_int_attr("pclk_ssp1") == Some(100_000_000));
+    });
+  }
+
+  #[test]
+  fn solves_pll() {
+    assert!(super::solve_pll(12_000_000, 100_000_000) == Some((25, 2, 3)));
+    assert!(super::solve_pll(12_000_000, 96_000_000) == Some((12, 1, 3)));
+    assert!(super::solve_pll(4_000_000, 72_000_000) == Some((36, 1, 4)));
+    assert!(super::solve_pll(12_000_000, 100_000_007) == None);
+    assert!(super::solve_pll(32_000, 100_000_000) == None);
+  }
+
+  #[test]
+  fn fails_to_parse_bad_pll_conf() {
+    // CCO below 275 MHz
+    fails_to_build("lpc17xx@mcu { clock {
+      source = \"main-oscillator\";
+      source_frequency = 12_000_000;
+      pll { m = 10; n = 1; divisor = 3; }
+    }}");
+    // system clock over 120 MHz
+    fails_to_build("lpc17xx@mcu { clock {
+      source = \"main-oscillator\";
+      source_frequency = 12_000_000;
+      pll { m = 40; n = 2; divisor = 3; }
+    }}");
+    fails_to_build("lpc17xx@mcu { clock {
+      source = \"main-oscillator\";
+      source_frequency = 12_000_000;
+      target_frequency = 100_000_000;
+      pll { m = 25; n = 2; divisor = 3; }
+    }}");
+  }
+
+  #[test]
+  fn fails_to_parse_unreachable_target_frequency() {
+    fails_to_build("lpc17xx@mcu { clock {
+      source = \"main-oscillator\";
+      source_frequency = 12_000_000;
+      target_frequency = 100_000_007;
+    }}");
+    fails_to_build("lpc17xx@mcu { clock {
+      source = \"main-oscillator\";
+      source_frequency = 12_000_000;
+      target_frequency = 200_000_000;
+    }}");
+  }
+
   #[test]
   fn fails_to_parse_bad_clock_conf() {
     fails_to_build("lpc17xx@mcu { clock {
@@ -22,8 +22,6 @@ This code supports all four primary timers of the MCU.
 use super::peripheral_clock;
 use hal::timer;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
-
 /// Available timer peripherals.
 #[allow(missing_doc)]
 pub enum TimerPeripheral {
@@ -52,19 +50,19 @@ impl Timer {
   /// Create an start a timer.
   pub fn new(peripheral: TimerPeripheral, counter: u32, divisor: u8) -> Timer {
     let (clock, reg) = match peripheral {
-      Timer0 => (peripheral_clock::TIM0Clock, &reg::TIMER0),
-      Timer1 => (peripheral_clock::TIM1Clock, &reg::TIMER1),
-      Timer2 => (peripheral_clock::TIM2Clock, &reg::TIMER2),
-      Timer3 => (peripheral_clock::TIM3Clock, &reg::TIMER3),
+      Timer0 => (peripheral_clock::TIM0Clock, reg::TIMER0()),
+      Timer1 => (peripheral_clock::TIM1Clock, reg::TIMER1()),
+      Timer2 => (peripheral_clock::TIM2Clock, reg::TIMER2()),
+      Timer3 => (peripheral_clock::TIM3Clock, reg::TIMER3()),
     };
 
     clock.enable();
     clock.set_divisor(divisor);
 
-    reg.set_CTCR(0);
-    reg.set_TCR(2);
-    reg.set_PR(counter - 1);
-    reg.set_TCR(1);
+    reg.ctcr.write().set_mode(reg::TimerMode);
+    reg.tcr.write().set_crst(true);
+    reg.pr.write().set_value(counter - 1);
+    reg.tcr.write().set_cen(true);
 
     Timer {
       reg: reg,
@@ -75,34 +73,70 @@ impl Timer {
 impl timer::Timer for Timer {
   #[inline(always)]
   fn get_counter(&self) -> u32 {
-    self.reg.TC()
+    self.reg.tc.value()
   }
 }
 
 mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(TIMER = {
+    0x00   => reg32 ir {          //! Interrupt
+      0..3   => mr[4]: set_to_clear, //= match channel interrupts
+      4..5   => cr[2]: set_to_clear, //= capture channel interrupts
+    }
+
+    0x04   => reg32 tcr {         //! Timer control
+      0      => cen,              //= counter enable
+      1      => crst,             //= counter reset
+    }
+
+    0x08   => reg32 tc {          //! Timer counter
+      0..31  => value,
+    }
+
+    0x0c   => reg32 pr {          //! Prescale
+      0..31  => value,
+    }
+
+    0x10   => reg32 pc {          //! Prescale counter
+      0..31  => value,
+    }
 
-  ioreg_old!(TIMER: u32, IR, TCR, TC, PR, PC, MCR, MR0, MR1, MR2, MR3, CCR, CR0, CR1, EMR, CTCR)
-  reg_rw!(TIMER, u32, IR,  set_IR,  IR)
-  reg_rw!(TIMER, u32, TCR, set_TCR, TCR)
-  reg_rw!(TIMER, u32, TC, set_TC, TC)
-  reg_rw!(TIMER, u32, PR, set_PR, PR)
-  reg_rw!(TIMER, u32, PC, set_PC, PC)
-  reg_rw!(TIMER, u32, MCR, set_MCR, MCR)
-  reg_rw!(TIMER, u32, MR0, set_MR0, MR0)
-  reg_rw!(TIMER, u32, MR1, set_MR1, MR1)
-  reg_rw!(TIMER, u32, MR2, set_MR2, MR2)
-  reg_rw!(TIMER, u32, MR3, set_MR3, MR3)
-  reg_rw!(TIMER, u32, CCR, set_CCR, CCR)
-  reg_rw!(TIMER, u32, CR0, set_CR0, CR0)
-  reg_rw!(TIMER, u32, CR1, set_CR1, CR1)
-  reg_rw!(TIMER, u32, EMR, set_EMR, EMR)
-  reg_rw!(TIMER, u32, CTCR, set_CTCR, CTCR)
-
-  extern {
-    #[link_name="lpc17xx_iomem_TIMER0"] pub static TIMER0: TIMER;
-    #[link_name="lpc17xx_iomem_TIMER1"] pub static TIMER1: TIMER;
-    #[link_name="lpc17xx_iomem_TIMER2"] pub static TIMER2: TIMER;
-    #[link_name="lpc17xx_iomem_TIMER3"] pub static TIMER3: TIMER;
+    0x14   => reg32 mcr {         //! Match control
+      0..11  => value,            //= interrupt, reset and stop on match
+    }
+
+    0x18   => reg32 mr[4] {       //! Match
+      0..31  => value,
+    }
+
+    0x28   => reg32 ccr {         //! Capture control
+      0..5   => value,            //= edges and interrupt of each capture
+    }
+
+    0x2c   => reg32 cr[2] {       //! Capture
+      0..31  => value: ro,
+    }
+
+    0x3c   => reg32 emr {         //! External match
+      0..11  => value,            //= external match states and controls
+    }
+
+    0x70   => reg32 ctcr {        //! Count control
+      0..1   => mode {            //= timer or counter mode
+        0 => TimerMode,
+        1 => CounterRising,
+        2 => CounterFalling,
+        3 => CounterBoth,
+      }
+      2..3   => cinsel,           //= count input select
+    }
   }
+    TIMER0 @ 0x4000_4000,
+    TIMER1 @ 0x4000_8000,
+    TIMER2 @ 0x4009_0000,
+    TIMER3 @ 0x4009_4000,
+  )
 }
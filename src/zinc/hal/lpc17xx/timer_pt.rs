@@ -16,7 +16,7 @@
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 
-use builder::{Builder, TokenString, add_node_dependency};
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
 use node;
 
 pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
@@ -60,6 +60,10 @@ fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
       return
     }
   };
+  if !builder.claim(cx, Peripheral(format!("TIMER{}", timer_index)),
+      node.path_span) {
+    return
+  }
 
   node.set_type_name("zinc::hal::lpc17xx::timer::Timer".to_string());
 
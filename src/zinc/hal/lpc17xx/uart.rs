@@ -16,23 +16,26 @@
 /*!
 UART configuration.
 
-This code doesn't support UART1, while it really should (UART1 has more features
-than other UARTs in MCU).
+UART1 is configured like the other UARTs. Its modem control and RS-485
+registers, absent from the other UARTs, are available through
+`reg::UART1()`, while the IrDA control register UART1 lacks is shared by
+`reg::UART0()`, `reg::UART2()` and `reg::UART3()`.
 */
 
 use core::intrinsics::abort;
 
-use hal::lpc17xx::peripheral_clock::{PeripheralClock, UART0Clock, UART2Clock, UART3Clock};
+use hal::lpc17xx::peripheral_clock::{PeripheralClock, UART0Clock, UART1Clock};
+use hal::lpc17xx::peripheral_clock::{UART2Clock, UART3Clock};
 use drivers::chario::CharIO;
 use hal::uart;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
 #[path="../../util/wait_for.rs"] mod wait_for;
 
 /// Available UART peripherals.
 #[allow(missing_doc)]
 pub enum UARTPeripheral {
   UART0,
+  UART1,
   UART2,
   UART3,
 }
@@ -122,15 +125,17 @@ pub struct UART {
 impl UARTPeripheral {
   fn reg(self) -> &'static reg::UART {
     match self {
-      UART0 => &reg::UART0,
-      UART2 => &reg::UART2,
-      UART3 => &reg::UART3,
+      UART0 => &**reg::UART0(),
+      UART1 => &**reg::UART1(),
+      UART2 => &**reg::UART2(),
+      UART3 => &**reg::UART3(),
     }
   }
 
   fn peripheral_clock(self) -> PeripheralClock {
     match self {
       UART0 => UART0Clock,
+      UART1 => UART1Clock,
       UART2 => UART2Clock,
       UART3 => UART3Clock,
     }
@@ -147,32 +152,55 @@ impl UART {
     };
 
     uart.clock.enable();
-    uart.set_baud_rate(baudrate);
-    uart.set_mode(WordLen::from_u8(word_len), parity,
-        StopBit::from_u8(stop_bits));
-    uart.set_fifo_enabled(true, true);
+    let (dl, div_add_val, mul_val) = uart.calculate_divisors(baudrate);
+    uart.setup(dl, div_add_val, mul_val, word_len, parity, stop_bits);
+
+    uart
+  }
+
+  /// Create and setup a UART with precomputed baud rate divisors.
+  ///
+  /// The baud rate is `PCLK / (16 * dl * (1 + div_add_val / mul_val))`, where
+  /// PCLK is the UART peripheral clock.
+  pub fn new_with_divisors(peripheral: UARTPeripheral, dl: u16,
+      div_add_val: u8, mul_val: u8, word_len: u8, parity: uart::Parity,
+      stop_bits: u8) -> UART {
+    let uart = UART {
+      reg: peripheral.reg(),
+      clock: peripheral.peripheral_clock(),
+    };
+
+    uart.clock.enable();
+    uart.setup(dl as u32, div_add_val as u32, mul_val as u32, word_len, parity,
+        stop_bits);
 
     uart
   }
 
+  fn setup(&self, dl: u32, div_add_val: u32, mul_val: u32, word_len: u8,
+      parity: uart::Parity, stop_bits: u8) {
+    self.set_divisors(dl, div_add_val, mul_val);
+    self.set_mode(WordLen::from_u8(word_len), parity,
+        StopBit::from_u8(stop_bits));
+    self.set_fifo_enabled(true, true);
+  }
+
   fn uart_clock(&self) -> u32 {
     self.clock.frequency()
   }
 
-  fn set_baud_rate(&self, baud_rate: u32) {
-    self.reg.set_LCR(0b1000_0000); // enable divisor latch access
+  fn set_divisors(&self, dl: u32, div_add_val: u32, mul_val: u32) {
+    self.reg.lcr.write().set_value(0b1000_0000); // enable divisor latch access
 
-    let (dl, div_add_val, mul_val) = self.calculate_divisors(baud_rate);
+    self.reg.dlm_ier.write().set_data((dl >> 8) & 0xff);
+    self.reg.rbr_thr_dll.write().set_data(dl & 0xff);
+    self.reg.fdr.write().set_divaddval(div_add_val).set_mulval(mul_val);
 
-    self.reg.set_DLM((dl >> 8) & 0xff);
-    self.reg.set_DLL(dl & 0xff);
-    self.reg.set_FDR(div_add_val | (mul_val << 4));
-
-    self.reg.set_LCR(3);
+    self.reg.lcr.write().set_value(3);
   }
 
   fn set_mode(&self, word_len: WordLen, parity: uart::Parity, stop_bits: StopBit) {
-    let lcr: u8 = (*(self.reg)).LCR() as u8;
+    let lcr: u8 = self.reg.lcr.value() as u8;
     let computed_val: u8 = word_len as u8 | stop_bits as u8 | match parity {
       uart::Disabled => PEDisabled as u8  | PSOdd as u8,
       uart::Odd      => PEEnabled as u8   | PSOdd as u8,
@@ -182,7 +210,7 @@ impl UART {
     };
     let new_lcr = (lcr & !LCRModeMask) | computed_val;
 
-    (*(self.reg)).set_LCR(new_lcr as u32);
+    self.reg.lcr.write().set_value(new_lcr as u32);
   }
 
   fn set_fifo_enabled(&self, enabled: bool, reset: bool) {
@@ -190,11 +218,11 @@ impl UART {
       true => FEEnabled as u8,
       false => FEDisabled as u8
     } | match reset {
-      true  => FIFOResetTx & FIFOResetRx,
+      true  => FIFOResetTx | FIFOResetRx,
       false => 0,
     } | FDDisabled as u8 | FT1char as u8;
 
-    (*(self.reg)).set_FCR(val as u32);
+    self.reg.iir_fcr.write().set_data(val as u32);
   }
 
   // TODO(farcaller): license note
@@ -263,8 +291,8 @@ impl UART {
 
 impl CharIO for UART {
   fn putc(&self, value: char) {
-    wait_for!(self.reg.LSR() as u8 & LSRTHREmpty == LSRTHREmpty);
-    self.reg.set_THR(value as u32);
+    wait_for!(self.reg.lsr.thre());
+    self.reg.rbr_thr_dll.write().set_data(value as u8 as u32);
   }
 }
 
@@ -273,30 +301,135 @@ static FIFOResetTx: u8 = 0b1_0_0;
 
 static LCRModeMask: u8 = 0b1_11_1_1_11;
 
-static LSRTHREmpty: u8 = 0x20;
-
-mod reg {
+/// Register definitions
+pub mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(UART = {
+    0x00   => reg32 rbr_thr_dll { //! Receive buffer (read) and transmit
+                                  //! holding (write) registers, divisor
+                                  //! latch LSB when DLAB is set
+      0..7  => data,
+    },
+
+    0x04   => reg32 dlm_ier {     //! Interrupt enable register, divisor
+                                  //! latch MSB when DLAB is set
+      0..9  => data,
+    },
+
+    0x08   => reg32 iir_fcr {     //! Interrupt identification (read) and
+                                  //! FIFO control (write) registers
+      0..7  => data,
+    },
+
+    0x0c   => reg32 lcr {         //! Line control
+      0..7  => value,             //= word length, stop bits, parity, break
+                                  //= control and DLAB
+    },
+
+    0x14   => reg32 lsr {         //! Line status
+      0     => rdr: ro,           //= receiver data ready
+      1     => oe: rc,            //= overrun error
+      2     => pe: rc,            //= parity error
+      3     => fe: rc,            //= framing error
+      4     => bi: rc,            //= break interrupt
+      5     => thre: ro,          //= transmit holding register empty
+      6     => temt: ro,          //= transmitter empty
+      7     => rxfe: ro,          //= error in RX FIFO
+    },
+
+    0x1c   => reg32 scr {         //! Scratch pad
+      0..7  => pad,
+    },
+
+    0x20   => reg32 acr {         //! Auto-baud control
+      0     => start,             //= start auto-baud
+      1     => mode,              //= auto-baud mode select
+      2     => autorestart,       //= restart on timeout
+      8     => abeointclr: wo,    //= clear end of auto-baud interrupt
+      9     => abtointclr: wo,    //= clear auto-baud time-out interrupt
+    },
+
+    0x28   => reg32 fdr {         //! Fractional divider
+      0..3  => divaddval,         //= baud rate pre-scaler divisor
+      4..7  => mulval,            //= baud rate pre-scaler multiplier
+    },
+
+    0x30   => reg32 ter {         //! Transmit enable
+      7     => txen,              //= transmitter enable
+    },
+  }
+    (UART0 @ 0x4000_C000, UART2 @ 0x4009_8000, UART3 @ 0x4009_C000) {
+      0x24   => reg32 icr {       //! IrDA control
+        0     => irdaen,          //= IrDA mode enable
+        1     => irdainv,         //= serial input inverter
+        2     => fixpulseen,      //= IrDA fixed pulse width mode
+        3..5  => pulsediv,        //= fixed pulse width divider
+      },
+    },
+    UART1 @ 0x4001_0000 {
+      0x10   => reg32 mcr {       //! Modem control
+        0     => dtr,             //= DTR output control
+        1     => rts,             //= RTS output control
+        4     => lms,             //= loopback mode
+        6     => rtsen,           //= auto-RTS flow control
+        7     => ctsen,           //= auto-CTS flow control
+      },
+
+      0x18   => reg32 msr {       //! Modem status
+        0     => dcts: rc,        //= CTS changed state
+        1     => ddsr: rc,        //= DSR changed state
+        2     => teri: rc,        //= RI changed from low to high
+        3     => ddcd: rc,        //= DCD changed state
+        4     => cts: ro,         //= clear to send
+        5     => dsr: ro,         //= data set ready
+        6     => ri: ro,          //= ring indicator
+        7     => dcd: ro,         //= data carrier detect
+      },
+
+      0x4c   => reg32 rs485ctrl { //! RS-485/EIA-485 control
+        0     => nmmen,           //= normal multidrop mode
+        1     => rxdis,           //= receiver disable
+        2     => aaden,           //= auto address detect
+        3     => sel,             //= direction control pin select
+        4     => dctrl,           //= auto direction control
+        5     => oinv,            //= direction control pin polarity
+      },
+
+      0x50   => reg32 rs485adrmatch { //! RS-485/EIA-485 address match
+        0..7  => adrmatch,
+      },
+
+      0x54   => reg32 rs485dly {  //! RS-485/EIA-485 direction control delay
+        0..7  => dly,
+      },
+    },
+  )
+}
 
-  ioreg_old!(UART: u32, RBR_THR_DLL, DLM_IER, IIR_FCR, LCR, _pad_0, LSR, _pad_1, SCR, ACR, ICR, FDR, _pad_2, TER)
-  reg_r!( UART, u32, RBR,          RBR_THR_DLL)
-  reg_w!( UART, u32,      set_THR, RBR_THR_DLL)
-  reg_rw!(UART, u32, DLL, set_DLL, RBR_THR_DLL)
-  reg_rw!(UART, u32, DLM, set_DLM, DLM_IER)
-  reg_rw!(UART, u32, IER, set_IER, DLM_IER)
-  reg_r!( UART, u32, IIR,          IIR_FCR)
-  reg_w!( UART, u32,      set_FCR, IIR_FCR)
-  reg_rw!(UART, u32, LCR, set_LCR, LCR)
-  reg_r!( UART, u32, LSR,          LSR)
-  reg_rw!(UART, u32, SCR, set_SCR, SCR)
-  reg_rw!(UART, u32, ACR, set_ACR, ACR)
-  reg_rw!(UART, u32, ICR, set_ICR, ICR)
-  reg_rw!(UART, u32, FDR, set_FDR, FDR)
-  reg_rw!(UART, u32, TER, set_TER, TER)
-
-  extern {
-    #[link_name="lpc17xx_iomem_UART0"] pub static UART0: UART;
-    #[link_name="lpc17xx_iomem_UART2"] pub static UART2: UART;
-    #[link_name="lpc17xx_iomem_UART3"] pub static UART3: UART;
+#[cfg(test)]
+mod test {
+  use hal::lpc17xx::peripheral_clock::UART0Clock;
+  use hal::uart;
+  use util::volatile_cell::mock::{MockRegisters, Read, Write};
+  use super::{UART, reg};
+
+  #[test]
+  fn sets_up_uart() {
+    let regs = MockRegisters::new(0x4000_C000);
+    let serial = UART { reg: &**reg::UART0(), clock: UART0Clock };
+
+    serial.setup(93, 3, 4, 8, uart::Disabled, 1);
+
+    assert_eq!(regs.log(), vec!(
+        Write(0x0c, 0x80),  // enable divisor latch access
+        Write(0x04, 0),     // divisor latch MSB
+        Write(0x00, 93),    // divisor latch LSB
+        Write(0x28, 0x43),  // fractional divider
+        Write(0x0c, 0x03),  // disable divisor latch access
+        Read(0x0c, 0x03),
+        Write(0x0c, 0x03),  // 8N1
+        Write(0x08, 0x07))) // enable and reset FIFOs
   }
 }
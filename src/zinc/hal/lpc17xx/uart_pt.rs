@@ -13,10 +13,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::iter::range_inclusive;
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 
-use builder::{Builder, TokenString, add_node_dependency};
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
 use node;
 
 
@@ -42,10 +43,17 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
-pub fn mutate_pins(builder: &mut Builder, _: &mut ExtCtxt, sub: Rc<node::Node>) {
+pub fn mutate_pins(builder: &mut Builder, cx: &mut ExtCtxt, sub: Rc<node::Node>) {
   let tx_node_name = sub.get_ref_attr("tx").unwrap();
   let rx_node_name = sub.get_ref_attr("rx").unwrap();
 
+  let tx_node = builder.pt().get_by_name(tx_node_name.as_slice()).unwrap();
+  let rx_node = builder.pt().get_by_name(rx_node_name.as_slice()).unwrap();
+  if !builder.claim_pin(cx, &tx_node, sub.get_attr("tx").value_span) ||
+     !builder.claim_pin(cx, &rx_node, sub.get_attr("rx").value_span) {
+    return;
+  }
+
   build_uart_gpio(builder, from_str(sub.path.as_slice()).unwrap(),
       tx_node_name.as_slice(), true);
   build_uart_gpio(builder, from_str(sub.path.as_slice()).unwrap(),
@@ -56,14 +64,18 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
     sub: Rc<node::Node>) {
   let uart_peripheral_str = format!("UART{}",
       match from_str::<uint>(sub.path.as_slice()).unwrap() {
-        0|2|3 => sub.path.clone(),
+        0...3 => sub.path.clone(),
         other => {
           cx.parse_sess().span_diagnostic.span_err(sub.path_span,
-              format!("unknown UART `{}`, allowed values: 0, 2, 3",
+              format!("unknown UART `{}`, allowed values: 0, 1, 2, 3",
                   other).as_slice());
           return
         }
       });
+  if !builder.claim(cx, Peripheral(uart_peripheral_str.clone()),
+      sub.path_span) {
+    return
+  }
   let uart_peripheral = TokenString(uart_peripheral_str);
 
   if sub.name.is_none() {
@@ -73,16 +85,41 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
   }
 
   if !sub.expect_attributes(cx, [
-      ("baud_rate", node::IntAttribute),
+      ("baud_rate", node::BaudRateAttribute),
       ("mode", node::StrAttribute),
       ("tx", node::RefAttribute),
       ("rx", node::RefAttribute)]) {
     return
   }
 
-  let baud_rate: u32 = sub.get_int_attr("baud_rate").unwrap() as u32;
+  let baud_rate = sub.get_baud_rate_attr("baud_rate").unwrap();
   let mode = sub.get_string_attr("mode").unwrap();
 
+  let clock_node = builder.pt().get_by_path("mcu").unwrap()
+      .get_by_path("clock").unwrap();
+  let pclk = match clock_node.get_int_attr(
+      format!("pclk_uart{}", sub.path).as_slice()) {
+    Some(freq) => freq,
+    // the clock node failed to build
+    None => return,
+  };
+  let (dl, div_add_val, mul_val) = match calculate_divisors(pclk, baud_rate) {
+    Some((dl, div_add_val, mul_val, actual)) => {
+      if !::uart_pt::check_baud_rate(cx, &sub, "baud_rate", baud_rate,
+          actual) {
+        return
+      }
+      (dl as u16, div_add_val as u8, mul_val as u8)
+    },
+    None => {
+      cx.parse_sess().span_diagnostic.span_err(
+          sub.get_attr("baud_rate").value_span,
+          format!("baud rate {} can't be derived from the {} Hz peripheral \
+              clock", baud_rate, pclk).as_slice());
+      return
+    },
+  };
+
   let word_len = mode.as_slice().char_at(0).to_digit(10).unwrap() as u8;
   let parity = TokenString(
       match mode.as_slice().char_at(1) {
@@ -99,9 +136,11 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
   let uart_name = TokenString(sub.name.clone().unwrap());
 
   let st = quote_stmt!(&*cx,
-      let $uart_name = zinc::hal::lpc17xx::uart::UART::new(
+      let $uart_name = zinc::hal::lpc17xx::uart::UART::new_with_divisors(
           zinc::hal::lpc17xx::uart::$uart_peripheral,
-          $baud_rate,
+          $dl,
+          $div_add_val,
+          $mul_val,
           $word_len,
           zinc::hal::uart::$parity,
           $stop_bits)
@@ -109,6 +148,41 @@ pub fn build_uart(builder: &mut Builder, cx: &mut ExtCtxt,
   builder.add_main_statement(st);
 }
 
+/// Finds the divisor latch and fractional divider values giving the baud rate
+/// closest to `baud_rate`, as `(dl, div_add_val, mul_val, actual baud rate)`.
+fn calculate_divisors(pclk: uint, baud_rate: uint)
+    -> Option<(uint, uint, uint, uint)> {
+  if baud_rate == 0 {
+    return None;
+  }
+  let mut best = None;
+  let mut best_deviation = 0;
+  for mul_val in range_inclusive(1u, 15) {
+    for div_add_val in range(0u, mul_val) {
+      let divisor = 16 * baud_rate * (mul_val + div_add_val);
+      let dl = (pclk * mul_val + divisor / 2) / divisor;
+      // the fractional divider requires dl to be at least 3
+      let min_dl = if div_add_val > 0 { 3 } else { 1 };
+      if dl < min_dl || dl > 0xffff {
+        continue;
+      }
+
+      let actual_divisor = 16 * dl * (mul_val + div_add_val);
+      let actual = (pclk * mul_val + actual_divisor / 2) / actual_divisor;
+      let deviation = if actual > baud_rate {
+        actual - baud_rate
+      } else {
+        baud_rate - actual
+      };
+      if best.is_none() || deviation < best_deviation {
+        best = Some((dl, div_add_val, mul_val, actual));
+        best_deviation = deviation;
+      }
+    }
+  }
+  best
+}
+
 pub fn build_uart_gpio(builder: &Builder, uart_idx: uint, name: &str,
     istx: bool) {
   let node = builder.pt().get_by_name(name).unwrap();
@@ -130,7 +204,7 @@ mod test {
     with_parsed("
       mcu {
         clock {
-          system_frequency = 100_000_000;
+          pclk_uart0 = 25_000_000;
         }
       }
       uart {
@@ -153,9 +227,11 @@ mod test {
       assert!(builder.main_stmts().len() == 1);
 
       assert_equal_source(builder.main_stmts()[0].deref(),
-          "let uart = zinc::hal::lpc17xx::uart::UART::new(
+          "let uart = zinc::hal::lpc17xx::uart::UART::new_with_divisors(
                zinc::hal::lpc17xx::uart::UART0,
-               9600u32,
+               93u16,
+               3u8,
+               4u8,
                8u8,
                zinc::hal::uart::Disabled,
                1u8);");
@@ -169,4 +245,80 @@ mod test {
       assert!(rx_node.get_string_attr("function").unwrap() == "rxd0".to_string());
     });
   }
+
+  #[test]
+  fn fails_to_use_uart_twice() {
+    with_parsed("
+      mcu {
+        clock {
+          pclk_uart0 = 25_000_000;
+        }
+      }
+      uart {
+        uart@0 {
+          baud_rate = 9600;
+          mode = \"8N1\";
+          tx = &uart_tx;
+          rx = &uart_rx;
+        }
+      }
+      serial {
+        serial@0 {
+          baud_rate = 9600;
+          mode = \"8N1\";
+          tx = &uart_tx;
+          rx = &uart_rx;
+        }
+      }
+      gpio {
+        uart_tx@0;
+        uart_rx@1;
+      }
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_uart(&mut builder, cx, pt.get_by_name("uart").unwrap());
+      assert!(unsafe{*failed} == false);
+      super::build_uart(&mut builder, cx, pt.get_by_name("serial").unwrap());
+      assert!(unsafe{*failed} == true);
+    });
+  }
+
+  #[test]
+  fn calculates_divisors() {
+    assert!(super::calculate_divisors(25_000_000, 9600) ==
+        Some((93, 3, 4, 9601)));
+    assert!(super::calculate_divisors(25_000_000, 115200) ==
+        Some((10, 5, 14, 115132)));
+    assert!(super::calculate_divisors(100_000_000, 115200) ==
+        Some((31, 3, 4, 115207)));
+  }
+
+  #[test]
+  fn fails_to_build_inexact_baud_rate() {
+    with_parsed("
+      mcu {
+        clock {
+          pclk_uart0 = 25_000_000;
+        }
+      }
+      uart {
+        uart@0 {
+          baud_rate = 115200;
+          baud_tolerance = 0;
+          mode = \"8N1\";
+          tx = &uart_tx;
+          rx = &uart_rx;
+        }
+      }
+      gpio {
+        uart_tx@0;
+        uart_rx@1;
+      }
+      ", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_uart(&mut builder, cx, pt.get_by_name("uart").unwrap());
+      assert!(unsafe{*failed} == true);
+      assert!(builder.main_stmts().len() == 0);
+    });
+  }
 }
@@ -16,7 +16,7 @@
 /*!
 SPI interface.
 
-SPIConf is a MCU-specific struct.
+SPI is implemented by MCU-specific structs, e.g. `lpc17xx::ssp::SSP`.
 
 As SPI performs read and write as one operation, special care should be taken if
 `write()` and `read()` methods are used with several devices on one SPI
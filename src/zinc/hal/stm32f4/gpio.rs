@@ -18,9 +18,6 @@
 use hal::stm32f4::pin;
 use hal::pin::{GPIODirection};
 
-#[path="../../util/ioreg.rs"]
-mod ioreg;
-
 /// Configuration for a GPIO.
 pub struct GPIOConf {
   /// Pin configuration for this GPIO.
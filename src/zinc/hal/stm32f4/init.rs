@@ -21,7 +21,6 @@
 use hal::mem_init::init_data;
 use core::intrinsics::abort;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
 #[path="../../util/wait_for.rs"] mod wait_for;
 
 /// System clock source.
@@ -68,6 +67,12 @@ pub struct PLLConf {
 pub struct ClockConf {
   /// Clocking source.
   pub source: SystemClockSource,
+  /// AHB clock divisor: 1, 2, 4, 8, 16, 64, 128, 256 or 512.
+  pub ahb_divisor: u16,
+  /// Low speed APB (APB1) clock divisor: 1, 2, 4, 8 or 16.
+  pub apb_low_divisor: u8,
+  /// High speed APB (APB2) clock divisor: 1, 2, 4, 8 or 16.
+  pub apb_high_divisor: u8,
 }
 
 /// MCU configuration.
@@ -103,28 +108,24 @@ impl SysConf {
 }
 
 impl ClockConf {
-  fn setup(&self) {
-    match self.source {
+  /// Switches the system clock to the configured source.
+  pub fn setup(&self) {
+    let sysfreq: u32 = match self.source {
       SystemClockHSI => {
-        // HSI is default boot mode, do nothing
-        unsafe {
-          SystemClock = 16_000_000;
-          APBLowClock = 16_000_000;  // no divisor
-        };
+        // HSI is default boot mode, only the divisors need to be set
+        self.set_clock_divisors();
+        16_000_000
       },
       SystemClockHSE(freq) => {
         // Switch to HSE
         if freq > 30_000_000 {
           // this code doesn't support wait states configuration for HSE
           unsafe { abort() };
-        } else {
-          self.enable_hse();
-          self.set_system_clock(reg::SystemClockHSE);
-          unsafe {
-            SystemClock = freq;
-            APBLowClock = freq;  // no divisor
-          };
         }
+        self.enable_hse();
+        self.set_clock_divisors();
+        self.set_system_clock(reg::SystemClockHSE);
+        freq
       },
       SystemClockPLL(pll_conf) => {
         // Init and switch to pll
@@ -135,65 +136,57 @@ impl ClockConf {
         let sysfreq: u32 = match pll_conf.source {
           PLLClockHSE(freq) => freq,
           PLLClockHSI       => 16_000_000,
-        } as u32 * (pll_conf.n as u32 / pll_conf.m as u32) / pll_conf.p as u32;
+        } as u32 / pll_conf.m as u32 * pll_conf.n as u32 / pll_conf.p as u32;
         // system_stm32f4xx.c enables PWR and sets VOS to 1 here, but VOS
         // defaults to 1 so I see no real reason to do that.
         // peripheral_clock::PWRClock.enable();
-        // reg::PWR.set_CR(reg::PWR.CR() | 0x0000C000);
+        // reg::PWR().cr.set_vos(3);
 
-        // TODO(farcaller): this should be configureable via ClockConf
-        let apb_low_divisor = 4;
-        self.set_clock_divisors(1, apb_low_divisor, 2);
+        self.set_clock_divisors();
         pll_conf.setup();
         // TODO(farcaller): this doesn't really belong here.
         self.setup_flash(sysfreq);
         self.set_system_clock(reg::SystemClockPLL);
-        unsafe {
-          SystemClock = sysfreq;
-          APBLowClock = sysfreq / apb_low_divisor as u32;
-        };
+        sysfreq
       },
-    }
+    };
+    unsafe {
+      SystemClock = sysfreq;
+      APBLowClock = sysfreq / self.ahb_divisor as u32 /
+          self.apb_low_divisor as u32;
+    };
   }
 
   fn setup_flash(&self, freq: u32) {
-    reg::FLASH.set_ACR(
-      (1 << 8)  |  // enable prefetch
-      (1 << 9)  |  // enable instruction cache
-      (1 << 10) |  // enable data cache
-      match freq/1_000_000 {  // wait states are calculated for 2.7-3.6V range
-        0...30    => 0,
-        31...60   => 1,
-        61...90   => 2,
-        90...120  => 3,
-        121...150 => 4,
-        151...168 => 5,
-        _        => unsafe { abort() },
-      }
-    );
+    // wait states are calculated for 2.7-3.6V range
+    let latency: u32 = match freq/1_000_000 {
+      0...30    => 0,
+      31...60   => 1,
+      61...90   => 2,
+      90...120  => 3,
+      121...150 => 4,
+      151...168 => 5,
+      _        => unsafe { abort() },
+    };
+    reg::FLASH().acr.write()
+      .set_prften(true)
+      .set_icen(true)
+      .set_dcen(true)
+      .set_latency(latency);
   }
 
   fn enable_hse(&self) {
-    let val = reg::RCC.CR();
-    let hse_on_bit: u32 = 1 << 16;
-    let hse_ready_bit: u32 = 1 << 17;
-    reg::RCC.set_CR(val | hse_on_bit);
+    reg::RCC().cr.set_hseon(true);
 
-    wait_for!(reg::RCC.CR() & hse_ready_bit == hse_ready_bit);
+    wait_for!(reg::RCC().cr.hserdy());
   }
 
-  fn set_system_clock(&self, clock: reg::SystemClockSwitch) {
-    let val = reg::RCC.CFGR();
-    let bits: u32 = clock as u32;
-    let mask: u32 = !0b1111;
-
-    reg::RCC.set_CFGR((val & mask) | bits);
+  fn set_system_clock(&self, clock: reg::RCC_cfgr_sw) {
+    reg::RCC().cfgr.set_sw(clock);
   }
 
-  fn set_clock_divisors(&self, ahb: u16, apb_low_speed: u8, apb_hi_speed: u8) {
-    let val = reg::RCC.CFGR();
-    let mask: u32 = !0b111_111_00_1111_0000;
-    let bits: u32 = (match ahb {
+  fn set_clock_divisors(&self) {
+    let hpre: u32 = match self.ahb_divisor {
       1   => 0b0000,
       2   => 0b1000,
       4   => 0b1001,
@@ -204,59 +197,59 @@ impl ClockConf {
       256 => 0b1110,
       512 => 0b1111,
       _   => unsafe { abort() },
-    } << 4) | (match apb_low_speed {
+    };
+    let ppre1: u32 = match self.apb_low_divisor {
       1   => 0b000,
       2   => 0b100,
       4   => 0b101,
       8   => 0b110,
       16  => 0b111,
       _   => unsafe { abort() },
-    } << 10) | (match apb_hi_speed {
+    };
+    let ppre2: u32 = match self.apb_high_divisor {
       1   => 0b000,
       2   => 0b100,
       4   => 0b101,
       8   => 0b110,
       16  => 0b111,
       _   => unsafe { abort() },
-    } << 13);
+    };
 
-    reg::RCC.set_CFGR((val & mask) | bits);
+    reg::RCC().cfgr
+      .set_hpre(hpre)
+      .set_ppre1(ppre1)
+      .set_ppre2(ppre2);
   }
 }
 
 impl PLLConf {
   fn setup(&self) {
-    // TODO(farcaller): cmsis code overrides reserved bits in here, is that ok?
-    let val = reg::RCC.PLLCFGR();
-    let mask: u32 = !0b0000_1111_0_1_0000_11_0_111111111_111111;
-    let bits: u32 =
-      self.m as u32 |
-      (self.n as u32 << 6) |
-      (match self.p {
-        2 => 0b00u32,
-        4 => 0b01u32,
-        6 => 0b10u32,
-        8 => 0b11u32,
-        _ => unsafe { abort() },
-      } << 16) |
-      (match self.source {
-        PLLClockHSI    => 0u32,
-        PLLClockHSE(_) => 1u32,
-      } << 22) |
-      (self.q as u32 << 24);
-
-    reg::RCC.set_PLLCFGR(val & mask | bits);
+    let pllp: u32 = match self.p {
+      2 => 0b00,
+      4 => 0b01,
+      6 => 0b10,
+      8 => 0b11,
+      _ => unsafe { abort() },
+    };
+    let pllsrc_hse = match self.source {
+      PLLClockHSI    => false,
+      PLLClockHSE(_) => true,
+    };
+
+    reg::RCC().pllcfgr
+      .set_pllm(self.m as u32)
+      .set_plln(self.n as u32)
+      .set_pllp(pllp)
+      .set_pllsrc(pllsrc_hse)
+      .set_pllq(self.q as u32);
 
     self.enable_pll();
   }
 
   fn enable_pll(&self) {
-    let val = reg::RCC.CR();
-    let pll_on_bit: u32 = 1 << 24;
-    let pll_ready_bit: u32 = 1 << 25;
-    reg::RCC.set_CR(val | pll_on_bit);
+    reg::RCC().cr.set_pllon(true);
 
-    wait_for!(reg::RCC.CR() & pll_ready_bit == pll_ready_bit);
+    wait_for!(reg::RCC().cr.pllrdy());
   }
 }
 
@@ -266,58 +259,169 @@ impl PLLConf {
 #[allow(missing_doc)]
 pub mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(RCC = {
+    0x00   => reg32 cr {          //! Clock control
+      0      => hsion,            //= HSI clock enable
+      1      => hsirdy: ro,       //= HSI clock ready
+      3..7   => hsitrim,          //= HSI clock trimming
+      8..15  => hsical: ro,       //= HSI clock calibration
+      16     => hseon,            //= HSE clock enable
+      17     => hserdy: ro,       //= HSE clock ready
+      18     => hsebyp,           //= HSE clock bypass
+      19     => csson,            //= clock security system enable
+      24     => pllon,            //= main PLL enable
+      25     => pllrdy: ro,       //= main PLL clock ready
+      26     => plli2son,         //= PLLI2S enable
+      27     => plli2srdy: ro,    //= PLLI2S clock ready
+    }
 
-  pub enum SystemClockSwitch {
-    SystemClockHSI = 0,
-    SystemClockHSE = 1,
-    SystemClockPLL = 2,
-  }
+    0x04   => reg32 pllcfgr {     //! PLL configuration
+      0..5   => pllm,             //= PLL input division factor
+      6..14  => plln,             //= main PLL multiplication factor
+      16..17 => pllp,             //= main PLL division factor for system clock
+      22     => pllsrc,           //= PLL clock source is HSE
+      24..27 => pllq,             //= main PLL division factor for USB
+    }
 
-  ioreg_old!(RCCReg: u32, CR, PLLCFGR, CFGR, CIR, AHB1RSTR, AHB2RSTR, AHB3RSTR,
-                      _pad_0, APB1RSTR, APB2RSTR, _pad_1, _pad_2, AHB1ENR,
-                      AHB2ENR, AHB3ENR, _pad_3, APB1ENR, APB2ENR, _pad_4,
-                      _pad_5, AHB1LPENR, AHB2LPENR, AHB3LPENR, _pad_6,
-                      APB1LPENR, APB2LPENR, _pad_7, _pad_8, BDCR, CSR, _pad_9,
-                      _pad_10, SSCGR, PLLI2SCFGR)
-  reg_rw!(RCCReg, u32, CR,         set_CR,         CR)
-  reg_rw!(RCCReg, u32, PLLCFGR,    set_PLLCFGR,    PLLCFGR)
-  reg_rw!(RCCReg, u32, CFGR,       set_CFGR,       CFGR)
-  reg_rw!(RCCReg, u32, CIR,        set_CIR,        CIR)
-  reg_rw!(RCCReg, u32, AHB1RSTR,   set_AHB1RSTR,   AHB1RSTR)
-  reg_rw!(RCCReg, u32, AHB2RSTR,   set_AHB2RSTR,   AHB2RSTR)
-  reg_rw!(RCCReg, u32, AHB3RSTR,   set_AHB3RSTR,   AHB3RSTR)
-  reg_rw!(RCCReg, u32, APB1RSTR,   set_APB1RSTR,   APB1RSTR)
-  reg_rw!(RCCReg, u32, APB2RSTR,   set_APB2RSTR,   APB2RSTR)
-  reg_rw!(RCCReg, u32, AHB1ENR,    set_AHB1ENR,    AHB1ENR)
-  reg_rw!(RCCReg, u32, AHB2ENR,    set_AHB2ENR,    AHB2ENR)
-  reg_rw!(RCCReg, u32, AHB3ENR,    set_AHB3ENR,    AHB3ENR)
-  reg_rw!(RCCReg, u32, APB1ENR,    set_APB1ENR,    APB1ENR)
-  reg_rw!(RCCReg, u32, APB2ENR,    set_APB2ENR,    APB2ENR)
-  reg_rw!(RCCReg, u32, AHB1LPENR,  set_AHB1LPENR,  AHB1LPENR)
-  reg_rw!(RCCReg, u32, AHB2LPENR,  set_AHB2LPENR,  AHB2LPENR)
-  reg_rw!(RCCReg, u32, AHB3LPENR,  set_AHB3LPENR,  AHB3LPENR)
-  reg_rw!(RCCReg, u32, APB1LPENR,  set_APB1LPENR,  APB1LPENR)
-  reg_rw!(RCCReg, u32, APB2LPENR,  set_APB2LPENR,  APB2LPENR)
-  reg_rw!(RCCReg, u32, BDCR,       set_BDCR,       BDCR)
-  reg_rw!(RCCReg, u32, CSR,        set_CSR,        CSR)
-  reg_rw!(RCCReg, u32, SSCGR,      set_SSCGR,      SSCGR)
-  reg_rw!(RCCReg, u32, PLLI2SCFGR, set_PLLI2SCFGR, PLLI2SCFGR)
-
-  ioreg_old!(FLASHReg: u32, ACR, KEYR, OPTKEYR, SR, CR, OPTCR)
-  reg_rw!(FLASHReg, u32, ACR,   set_ACR,     ACR)
-  reg_w!(FLASHReg,  u32,        set_KEYR,    KEYR)
-  reg_w!(FLASHReg,  u32,        set_OPTKEYR, OPTKEYR)
-  reg_rw!(FLASHReg, u32, SR,    set_SR,      SR)
-  reg_rw!(FLASHReg, u32, CR,    set_CR,      CR)
-  reg_rw!(FLASHReg, u32, OPTCR, set_OPTCR,   OPTCR)
-
-  ioreg_old!(PWRReg: u32, CR, CSR)
-  reg_rw!(PWRReg, u32, CR,  set_CR,  CR)
-  reg_rw!(PWRReg, u32, CSR, set_CSR, CSR)
-
-  extern {
-    #[link_name="stm32f4_iomem_RCC"] pub static RCC: RCCReg;
-    #[link_name="stm32f4_iomem_FLASH"] pub static FLASH: FLASHReg;
-    #[link_name="stm32f4_iomem_PWR"] pub static PWR: PWRReg;
-  }
+    0x08   => reg32 cfgr {        //! Clock configuration
+      0..1   => sw {              //= system clock switch
+        0 => SystemClockHSI,
+        1 => SystemClockHSE,
+        2 => SystemClockPLL,
+      }
+      2..3   => sws: ro,          //= system clock switch status
+      4..7   => hpre,             //= AHB prescaler
+      10..12 => ppre1,            //= low speed APB (APB1) prescaler
+      13..15 => ppre2,            //= high speed APB (APB2) prescaler
+    }
+
+    0x0c   => reg32 cir {         //! Clock interrupt
+      0..31  => value,
+    }
+
+    0x10   => reg32 ahb1rstr {    //! AHB1 peripheral reset
+      0..31  => rst[32],
+    }
+
+    0x14   => reg32 ahb2rstr {    //! AHB2 peripheral reset
+      0..31  => rst[32],
+    }
+
+    0x18   => reg32 ahb3rstr {    //! AHB3 peripheral reset
+      0..31  => rst[32],
+    }
+
+    0x20   => reg32 apb1rstr {    //! APB1 peripheral reset
+      0..31  => rst[32],
+    }
+
+    0x24   => reg32 apb2rstr {    //! APB2 peripheral reset
+      0..31  => rst[32],
+    }
+
+    0x30   => reg32 ahb1enr {     //! AHB1 peripheral clock enable
+      0..31  => en[32],
+    }
+
+    0x34   => reg32 ahb2enr {     //! AHB2 peripheral clock enable
+      0..31  => en[32],
+    }
+
+    0x38   => reg32 ahb3enr {     //! AHB3 peripheral clock enable
+      0..31  => en[32],
+    }
+
+    0x40   => reg32 apb1enr {     //! APB1 peripheral clock enable
+      0..31  => en[32],
+    }
+
+    0x44   => reg32 apb2enr {     //! APB2 peripheral clock enable
+      0..31  => en[32],
+    }
+
+    0x50   => reg32 ahb1lpenr {   //! AHB1 peripheral clock enable in sleep
+      0..31  => lpen[32],
+    }
+
+    0x54   => reg32 ahb2lpenr {   //! AHB2 peripheral clock enable in sleep
+      0..31  => lpen[32],
+    }
+
+    0x58   => reg32 ahb3lpenr {   //! AHB3 peripheral clock enable in sleep
+      0..31  => lpen[32],
+    }
+
+    0x60   => reg32 apb1lpenr {   //! APB1 peripheral clock enable in sleep
+      0..31  => lpen[32],
+    }
+
+    0x64   => reg32 apb2lpenr {   //! APB2 peripheral clock enable in sleep
+      0..31  => lpen[32],
+    }
+
+    0x70   => reg32 bdcr {        //! Backup domain control
+      0..31  => value,
+    }
+
+    0x74   => reg32 csr {         //! Clock control and status
+      0..31  => value,
+    }
+
+    0x80   => reg32 sscgr {       //! Spread spectrum clock generation
+      0..31  => value,
+    }
+
+    0x84   => reg32 plli2scfgr {  //! PLLI2S configuration
+      0..31  => value,
+    }
+  } RCC @ 0x4002_3800)
+
+  ioregs!(FLASH = {
+    0x00   => reg32 acr {         //! Access control
+      0..2   => latency,          //= wait states
+      8      => prften,           //= prefetch enable
+      9      => icen,             //= instruction cache enable
+      10     => dcen,             //= data cache enable
+      11     => icrst,            //= instruction cache reset
+      12     => dcrst,            //= data cache reset
+    }
+
+    0x04   => reg32 keyr {        //! Key
+      0..31  => key: wo,
+    }
+
+    0x08   => reg32 optkeyr {     //! Option key
+      0..31  => optkey: wo,
+    }
+
+    0x0c   => reg32 sr {          //! Status
+      0      => eop: set_to_clear,    //= end of operation
+      1      => operr: set_to_clear,  //= operation error
+      4      => wrperr: set_to_clear, //= write protection error
+      5      => pgaerr: set_to_clear, //= programming alignment error
+      6      => pgperr: set_to_clear, //= programming parallelism error
+      7      => pgserr: set_to_clear, //= programming sequence error
+      16     => bsy: ro,          //= busy
+    }
+
+    0x10   => reg32 cr {          //! Control
+      0..31  => value,
+    }
+
+    0x14   => reg32 optcr {       //! Option control
+      0..31  => value,
+    }
+  } FLASH @ 0x4002_3C00)
+
+  ioregs!(PWR = {
+    0x00   => reg32 cr {          //! Power control
+      14..15 => vos,              //= regulator voltage scaling output
+    }
+
+    0x04   => reg32 csr {         //! Power control and status
+      0..31  => value,
+    }
+  } PWR @ 0x4000_7000)
 }
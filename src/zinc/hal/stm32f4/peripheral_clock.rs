@@ -21,8 +21,6 @@
 
 use super::init::reg;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
-
 /// Configures the state of peripheral clock.
 ///
 /// This enum contains all available clocks from both AHB and APB.
@@ -111,8 +109,8 @@ impl PeripheralClock {
     self.set_reg(false);
   }
 
-  fn to_reg_bit(self) -> u32 {
-    1 << match self {
+  fn to_reg_bit(self) -> uint {
+    match self {
       GPIOAClock      => 0,
       GPIOBClock      => 1,
       GPIOCClock      => 2,
@@ -183,37 +181,31 @@ impl PeripheralClock {
   }
 
   fn set_reg(self, enable: bool) {
-    let reg_bit = self.to_reg_bit();
-    let mask: u32 = !reg_bit;
-    let bit: u32 = if enable {reg_bit} else {0};
+    let bit = self.to_reg_bit();
+    let rcc = reg::RCC();
     match self {
       GPIOAClock|GPIOBClock|GPIOCClock|GPIODClock|GPIOEClock|GPIOFClock|
       GPIOGClock|GPIOHClock|GPIOIClock|CRCClock|BKPSRAMClock|CCMDATARAMClock|
       DMA1Clock|DMA2Clock|ETHMACClock|ETHMACTxClock|ETHMACRxClock|
       ETHMACPTPClock|OTGHSClock|OTGHSULPIClock => {
-        let val = reg::RCC.AHB1ENR();
-        reg::RCC.set_AHB1ENR((val & mask) | bit);
+        rcc.ahb1enr.set_en(bit, enable);
       },
       DCMIClock|CRYPClock|HASHClock|RNGClock|OTGFSClock => {
-        let val = reg::RCC.AHB2ENR();
-        reg::RCC.set_AHB2ENR((val & mask) | bit);
+        rcc.ahb2enr.set_en(bit, enable);
       },
       FSMCClock => {
-        let val = reg::RCC.AHB3ENR();
-        reg::RCC.set_AHB3ENR((val & mask) | bit);
+        rcc.ahb3enr.set_en(bit, enable);
       },
       TIM2Clock|TIM3Clock|TIM4Clock|TIM5Clock|TIM6Clock|TIM7Clock|TIM12Clock|
       TIM13Clock|TIM14Clock|WWDGClock|SPI2Clock|SPI3Clock|USART2Clock|
       USART3Clock|UART4Clock|UART5Clock|I2C1Clock|I2C2Clock|I2C3Clock|
       CAN1Clock|CAN2Clock|PWRClock|DACClock => {
-        let val = reg::RCC.APB1ENR();
-        reg::RCC.set_APB1ENR((val & mask) | bit);
+        rcc.apb1enr.set_en(bit, enable);
       },
       TIM1Clock|TIM8Clock|USART1Clock|USART6Clock|ADC1Clock|ADC2Clock|ADC3Clock|
       SDIOClock|SPI1Clock|SYSCFGClock|TIM9Clock|TIM10Clock|
       TIM11Clock => {
-        let val = reg::RCC.APB2ENR();
-        reg::RCC.set_AHB1ENR((val & mask) | bit);
+        rcc.apb2enr.set_en(bit, enable);
       },
     }
 
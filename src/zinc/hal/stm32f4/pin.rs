@@ -19,9 +19,6 @@
 //! depending on the package.
 
 use super::peripheral_clock;
-use core::intrinsics::abort;
-
-#[path="../../util/ioreg.rs"] mod ioreg;
 
 /// Available port names.
 #[allow(missing_doc)]
@@ -40,10 +37,11 @@ pub enum Port {
 /// Pin functions.
 #[allow(missing_doc)]
 pub enum Function {
-  GPIOIn      = 0,
-  GPIOOut     = 1,
-  AltFunction = 2,
-  Analog      = 3,
+  GPIOIn,
+  GPIOOut,
+  /// Alternate function with the given index, 0 to 15.
+  AltFunction(u8),
+  Analog,
 }
 
 impl Port {
@@ -81,85 +79,113 @@ impl PinConf {
   pub fn setup(&self) {
     self.port.clock().enable();  // TODO(farcaller): should be done once per port
 
-    let offset: u32 = self.pin as u32 * 2;
-    let gpreg = self.get_reg();
-
-    let bits: u32 = match self.function {
-      GPIOOut => 0b01 << offset as uint,
-      GPIOIn  => 0b00 << offset as uint,
-      _       => unsafe { abort() },  // FIXME(farcaller): not implemented
+    let mode = match self.function {
+      GPIOIn         => reg::Input,
+      GPIOOut        => reg::Output,
+      AltFunction(f) => {
+        self.set_alt_function(f);
+        reg::AltFunction
+      },
+      Analog         => reg::Analog,
     };
-    let mask: u32 = !(0b11 << offset as uint);
-    let val: u32 = gpreg.MODER();
 
-    gpreg.set_MODER(val & mask | bits);
+    self.get_reg().moder.set_mode(self.pin as uint, mode);
   }
 
   /// Sets output GPIO value to high.
   pub fn set_high(&self) {
-    let bit: u32 = 1 << self.pin as uint;
-    self.get_reg().set_BSRR(bit);
+    self.get_reg().bsrr.set_bs(self.pin as uint, true);
   }
 
   /// Sets output GPIO value to low.
   pub fn set_low(&self) {
-    let bit: u32 = 1 << (self.pin as uint + 16);
-    self.get_reg().set_BSRR(bit);
+    self.get_reg().bsrr.set_br(self.pin as uint, true);
   }
 
   /// Returns input GPIO level.
   pub fn level(&self) -> ::hal::pin::GPIOLevel {
-    let bit: u32 = 1 << (self.pin as uint);
-    let reg = self.get_reg();
-
-    match reg.IDR() & bit {
-      0 => ::hal::pin::Low,
-      _ => ::hal::pin::High,
+    match self.get_reg().idr.id(self.pin as uint) {
+      false => ::hal::pin::Low,
+      _     => ::hal::pin::High,
     }
   }
 
-  fn get_reg(&self) -> &reg::GPIO {
+  fn set_alt_function(&self, function: u8) {
+    let pin = self.pin as uint;
+    self.get_reg().afr[pin / 8].set_af(pin % 8, function as u32 & 0b1111);
+  }
+
+  fn get_reg(&self) -> &'static reg::GPIO {
     match self.port {
-      PortA => &reg::GPIOA,
-      PortB => &reg::GPIOB,
-      PortC => &reg::GPIOC,
-      PortD => &reg::GPIOD,
-      PortE => &reg::GPIOE,
-      PortF => &reg::GPIOF,
-      PortG => &reg::GPIOG,
-      PortH => &reg::GPIOH,
-      PortI => &reg::GPIOI,
+      PortA => reg::GPIOA(),
+      PortB => reg::GPIOB(),
+      PortC => reg::GPIOC(),
+      PortD => reg::GPIOD(),
+      PortE => reg::GPIOE(),
+      PortF => reg::GPIOF(),
+      PortG => reg::GPIOG(),
+      PortH => reg::GPIOH(),
+      PortI => reg::GPIOI(),
     }
   }
 }
 
-#[allow(dead_code)]
 mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
+
+  ioregs!(GPIO = {
+    0x00   => reg32 moder {       //! Port mode
+      0..31  => mode[16] {
+        0 => Input,
+        1 => Output,
+        2 => AltFunction,
+        3 => Analog,
+      }
+    }
 
-  ioreg_old!(GPIO: u32, MODER, OTYPER, OSPEEDER, PUPDR, IDR, ODR, BSRR, LCKR, AFRL, AFRH)
-  reg_rw!(GPIO, u32, MODER,    set_MODER,    MODER)
-  reg_rw!(GPIO, u32, OTYPER,   set_OTYPER,   OTYPER)
-  reg_rw!(GPIO, u32, OSPEEDER, set_OSPEEDER, OSPEEDER)
-  reg_rw!(GPIO, u32, PUPDR,    set_PUPDR,    PUPDR)
-  reg_rw!(GPIO, u32, IDR,      set_IDR,      IDR)
-  reg_rw!(GPIO, u32, ODR,      set_ODR,      ODR)
-  reg_rw!(GPIO, u32, BSRR,     set_BSRR,     BSRR)
-  reg_rw!(GPIO, u32, LCKR,     set_LCKR,     LCKR)
-  reg_rw!(GPIO, u32, AFRL,     set_AFRL,     AFRL)
-  reg_rw!(GPIO, u32, AFRH,     set_AFRH,     AFRH)
-
-  extern {
-    #[link_name="stm32f4_iomem_GPIOA"] pub static GPIOA: GPIO;
-    #[link_name="stm32f4_iomem_GPIOB"] pub static GPIOB: GPIO;
-    #[link_name="stm32f4_iomem_GPIOC"] pub static GPIOC: GPIO;
-    #[link_name="stm32f4_iomem_GPIOD"] pub static GPIOD: GPIO;
-    #[link_name="stm32f4_iomem_GPIOE"] pub static GPIOE: GPIO;
-    #[link_name="stm32f4_iomem_GPIOF"] pub static GPIOF: GPIO;
-    #[link_name="stm32f4_iomem_GPIOG"] pub static GPIOG: GPIO;
-    #[link_name="stm32f4_iomem_GPIOH"] pub static GPIOH: GPIO;
-    #[link_name="stm32f4_iomem_GPIOI"] pub static GPIOI: GPIO;
-    // define_reg!(GPIO_J: GPIO @ 0x40022400)
-    // define_reg!(GPIO_K: GPIO @ 0x40022800)
+    0x04   => reg32 otyper {      //! Port output type
+      0..15  => ot[16],           //= output is open-drain
+    }
+
+    0x08   => reg32 ospeedr {     //! Port output speed
+      0..31  => ospeed[16],
+    }
+
+    0x0c   => reg32 pupdr {       //! Port pull-up and pull-down
+      0..31  => pupd[16],
+    }
+
+    0x10   => reg32 idr {         //! Port input data
+      0..15  => id[16]: ro,
+    }
+
+    0x14   => reg32 odr {         //! Port output data
+      0..15  => od[16],
+    }
+
+    0x18   => reg32 bsrr {        //! Port bit set and reset
+      0..15  => bs[16]: wo,       //= set the output bit
+      16..31 => br[16]: wo,       //= reset the output bit
+    }
+
+    0x1c   => reg32 lckr {        //! Port configuration lock
+      0..15  => lck[16],
+      16     => lckk,             //= lock key
+    }
+
+    0x20   => reg32 afr[2] {      //! Alternate function, low and high pins
+      0..31  => af[8],
+    }
   }
+    GPIOA @ 0x4002_0000,
+    GPIOB @ 0x4002_0400,
+    GPIOC @ 0x4002_0800,
+    GPIOD @ 0x4002_0C00,
+    GPIOE @ 0x4002_1000,
+    GPIOF @ 0x4002_1400,
+    GPIOG @ 0x4002_1800,
+    GPIOH @ 0x4002_1C00,
+    GPIOI @ 0x4002_2000,
+  )
 }
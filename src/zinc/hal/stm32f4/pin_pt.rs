@@ -0,0 +1,228 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString, PinFunction, add_node_dependency};
+use node;
+
+/// U(S)ART signals by port, pin and alternate function.
+static UART_PINS: &'static [(&'static str, &'static str, uint, uint)] = &[
+  ("USART1_TX", "A", 9, 7),  ("USART1_RX", "A", 10, 7),
+  ("USART1_TX", "B", 6, 7),  ("USART1_RX", "B", 7, 7),
+  ("USART2_TX", "A", 2, 7),  ("USART2_RX", "A", 3, 7),
+  ("USART2_TX", "D", 5, 7),  ("USART2_RX", "D", 6, 7),
+  ("USART3_TX", "B", 10, 7), ("USART3_RX", "B", 11, 7),
+  ("USART3_TX", "C", 10, 7), ("USART3_RX", "C", 11, 7),
+  ("USART3_TX", "D", 8, 7),  ("USART3_RX", "D", 9, 7),
+  ("UART4_TX",  "A", 0, 8),  ("UART4_RX",  "A", 1, 8),
+  ("UART4_TX",  "C", 10, 8), ("UART4_RX",  "C", 11, 8),
+  ("UART5_TX",  "C", 12, 8), ("UART5_RX",  "D", 2, 8),
+  ("USART6_TX", "C", 6, 8),  ("USART6_RX", "C", 7, 8),
+];
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+  for port_node in node.subnodes().iter() {
+    port_node.materializer.set(Some(verify));
+    add_node_dependency(&node, port_node);
+    for pin_node in port_node.subnodes().iter() {
+      pin_node.materializer.set(Some(build_pin));
+      add_node_dependency(port_node, pin_node);
+      super::add_node_dependency_on_clock(builder, pin_node);
+    }
+  }
+}
+
+pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+}
+
+fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  let port_node = node.parent.clone().unwrap().upgrade().unwrap();
+  let ref port_path = port_node.path;
+  let port_str = format!("Port{}", match port_path.as_slice() {
+    "A" | "B" | "C" | "D" | "E" | "F" | "G" | "H" | "I" => port_path,
+    other => {
+      cx.parse_sess().span_diagnostic.span_err(port_node.path_span,
+          format!("unknown port `{}`, allowed values: A...I",
+              other).as_slice());
+      return;
+    }
+  });
+  let port = TokenString(port_str);
+
+  if node.name.is_none() {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "pin node must have a name");
+    return;
+  }
+
+  if !node.expect_no_subnodes(cx) ||
+     !node.expect_attributes(cx, [("mode", node::StrAttribute)]) {
+    return;
+  }
+
+  let pin_str = match from_str::<uint>(node.path.as_slice()) {
+    Some(pin) if pin < 16 => &node.path,
+    _ => {
+      cx.parse_sess().span_diagnostic.span_err(node.path_span,
+          format!("unknown pin `{}`, allowed values: 0...15",
+              node.path).as_slice());
+      return;
+    }
+  };
+
+  let mode = node.get_string_attr("mode").unwrap();
+  let function_str = match mode.as_slice() {
+    "alt" => match node.get_required_int_attr(cx, "function") {
+      None => return,
+      Some(f) if f < 16 => format!("AltFunction({}u8)", f),
+      Some(f) => {
+        cx.parse_sess().span_diagnostic.span_err(
+            node.get_attr("function").value_span,
+            format!("unknown alternate function `{}`, allowed values: 0...15",
+                f).as_slice());
+        return;
+      }
+    },
+    other => {
+      if node.get_int_attr("function").is_some() {
+        cx.parse_sess().span_diagnostic.span_err(
+            node.get_attr("function").value_span,
+            "alternate function is only allowed with mode `alt`");
+        return;
+      }
+      match other {
+        "in"     => "GPIOIn".to_string(),
+        "out"    => "GPIOOut".to_string(),
+        "analog" => "Analog".to_string(),
+        _ => {
+          cx.parse_sess().span_diagnostic.span_err(
+              node.get_attr("mode").value_span,
+              format!("unknown mode `{}`, allowed values: `in`, `out`, \
+                  `alt`, `analog`", other).as_slice());
+          return;
+        }
+      }
+    }
+  };
+
+  // only one pin can be routed to a U(S)ART signal
+  if mode.as_slice() == "alt" {
+    let function = node.get_int_attr("function").unwrap();
+    let pin_idx = from_str::<uint>(pin_str.as_slice()).unwrap();
+    match UART_PINS.iter().find(|&&(_, port, pin, f)|
+        port == port_path.as_slice() && pin == pin_idx && f == function) {
+      Some(&(signal, _, _, _)) => {
+        if !builder.claim(cx, PinFunction(signal.to_string()),
+            node.path_span) {
+          return;
+        }
+      },
+      None => (),
+    }
+  }
+
+  let function = TokenString(function_str);
+  let pin = TokenString(format!("{}u8", pin_str));
+  let pin_name = TokenString(node.name.clone().unwrap());
+
+  node.set_type_name("zinc::hal::stm32f4::pin::PinConf".to_string());
+
+  let st = quote_stmt!(&*cx,
+      let $pin_name = zinc::hal::stm32f4::pin::PinConf {
+        port: zinc::hal::stm32f4::pin::$port,
+        pin: $pin,
+        function: zinc::hal::stm32f4::pin::$function,
+      };
+  );
+  builder.add_main_statement(st);
+  let st = quote_stmt!(&*cx, $pin_name.setup(););
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_output_gpio() {
+    with_parsed("
+      gpio {
+        G {
+          led@13 { mode = \"out\"; }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_pin(&mut builder, cx, pt.get_by_name("led").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 2);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let led = zinc::hal::stm32f4::pin::PinConf {
+               port: zinc::hal::stm32f4::pin::PortG,
+               pin: 13u8,
+               function: zinc::hal::stm32f4::pin::GPIOOut,
+             };");
+      assert_equal_source(builder.main_stmts()[1].deref(),
+          "led.setup();");
+    });
+  }
+
+  #[test]
+  fn builds_altfn_gpio() {
+    with_parsed("
+      gpio {
+        A {
+          tx@2 { mode = \"alt\"; function = 7; }
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_pin(&mut builder, cx, pt.get_by_name("tx").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 2);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let tx = zinc::hal::stm32f4::pin::PinConf {
+               port: zinc::hal::stm32f4::pin::PortA,
+               pin: 2u8,
+               function: zinc::hal::stm32f4::pin::AltFunction(7u8),
+             };");
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_pins() {
+    fails_to_build("stm32f4@mcu {
+      clock { source = \"hsi\"; }
+      gpio { J { p@1 { mode = \"out\"; } } }
+    }");
+    fails_to_build("stm32f4@mcu {
+      clock { source = \"hsi\"; }
+      gpio { A { p@16 { mode = \"out\"; } } }
+    }");
+    fails_to_build("stm32f4@mcu {
+      clock { source = \"hsi\"; }
+      gpio { A { p@1 { mode = \"alt\"; } } }
+    }");
+    fails_to_build("stm32f4@mcu {
+      clock { source = \"hsi\"; }
+      gpio { A { p@1 { mode = \"out\"; function = 7; } } }
+    }");
+  }
+}
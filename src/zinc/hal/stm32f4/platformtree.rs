@@ -0,0 +1,161 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, add_node_dependency};
+use node;
+
+mod system_clock_pt;
+mod timer_pt;
+mod pin_pt;
+
+pub fn attach(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+  for sub in node.subnodes().iter() {
+    add_node_dependency(&node, sub);
+
+    match sub.path.as_slice() {
+      "clock" => system_clock_pt::attach(builder, cx, sub.clone()),
+      "timer" => timer_pt::attach(builder, cx, sub.clone()),
+      "gpio"  => pin_pt::attach(builder, cx, sub.clone()),
+      _ => (),
+    }
+  }
+}
+
+fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+  node.expect_subnodes(cx, ["clock", "timer", "gpio"]);
+}
+
+pub fn add_node_dependency_on_clock(builder: &mut Builder,
+    node: &Rc<node::Node>) {
+  let mcu_node = builder.pt().get_by_path("mcu").unwrap();
+  let clock_node = mcu_node.get_by_path("clock").unwrap();
+  add_node_dependency(node, &clock_node);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_items, with_parsed, fails_to_build};
+
+  #[test]
+  fn fails_to_parse_garbage_attrs() {
+    fails_to_build("stm32f4@mcu { key = 1; }");
+  }
+
+  #[test]
+  fn fails_to_parse_unknown_subnodes() {
+    fails_to_build("stm32f4@mcu { uart {} }");
+  }
+
+  #[test]
+  fn builds_stm32f4_pt() {
+    with_parsed("
+      stm32f4@mcu {
+        clock {
+          source = \"hse\";
+          source_frequency = 8_000_000;
+          apb_low_divisor = 4;
+          apb_high_divisor = 2;
+          pll {
+            m = 8;
+            n = 336;
+            p = 2;
+            q = 7;
+          }
+        }
+
+        timer {
+          timer@2 {
+            counter = 42;
+          }
+        }
+
+        gpio {
+          G {
+            led1@13 { mode = \"out\"; }
+            led2@14 { mode = \"out\"; }
+          }
+        }
+      }
+
+      os {
+        single_task {
+          loop = \"run\";
+          args {
+            timer = &timer;
+            led1 = &led1;
+            led2 = &led2;
+          }
+        }
+      }", |cx, failed, pt| {
+      let items = Builder::build(cx, pt)
+        .expect(format!("Unexpected failure on {}", line!()).as_slice())
+        .emit_items(cx);
+
+      assert!(unsafe{*failed} == false);
+      assert!(items.len() == 3);
+
+      assert_equal_items(items[1].deref(), "
+          #[no_mangle]
+          #[allow(unused_variable)]
+          pub unsafe fn main() {
+            zinc::hal::mem_init::init_stack();
+            zinc::hal::mem_init::init_data();
+            {
+              use zinc::hal::stm32f4::init;
+              let conf = init::ClockConf{
+                source: init::SystemClockPLL(init::PLLConf{
+                  source: init::PLLClockHSE(8000000),
+                  m: 8u8,
+                  n: 336u16,
+                  p: 2u8,
+                  q: 7u8,
+                }),
+                ahb_divisor: 1u16,
+                apb_low_divisor: 4u8,
+                apb_high_divisor: 2u8,
+              };
+              conf.setup();
+            };
+            let timer = zinc::hal::stm32f4::timer::Timer::new(
+                zinc::hal::stm32f4::timer::Timer2, 42u32);
+            let led1 = zinc::hal::stm32f4::pin::PinConf{
+              port: zinc::hal::stm32f4::pin::PortG,
+              pin: 13u8,
+              function: zinc::hal::stm32f4::pin::GPIOOut,
+            };
+            led1.setup();
+            let led2 = zinc::hal::stm32f4::pin::PinConf{
+              port: zinc::hal::stm32f4::pin::PortG,
+              pin: 14u8,
+              function: zinc::hal::stm32f4::pin::GPIOOut,
+            };
+            led2.setup();
+            loop {
+              run(&pt::run_args{
+                timer: &timer,
+                led1: &led1,
+                led2: &led2,
+              });
+            }
+          }");
+    });
+  }
+}
@@ -0,0 +1,383 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+use syntax::ext::build::AstBuilder;
+
+use builder::{Builder, TokenString};
+use node;
+
+static HSI_FREQUENCY: uint = 16_000_000;
+static MAX_SYSTEM_FREQUENCY: uint = 168_000_000;
+static MAX_APB_LOW_FREQUENCY: uint = 42_000_000;
+static MAX_APB_HIGH_FREQUENCY: uint = 84_000_000;
+
+pub fn attach(_: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(build_clock));
+}
+
+/// Reports an error and returns false if an integer attribute is out of the
+/// given inclusive range.
+fn check_range(cx: &ExtCtxt, node: &Rc<node::Node>, key: &str, value: uint,
+    min: uint, max: uint) -> bool {
+  if value < min || value > max {
+    cx.parse_sess().span_diagnostic.span_err(node.get_attr(key).value_span,
+        format!("invalid {} `{}`, allowed values: {}...{}",
+            key, value, min, max).as_slice());
+    false
+  } else {
+    true
+  }
+}
+
+/// Returns the value of an optional divisor attribute, 1 if it's missing.
+/// Reports an error and returns None if the value isn't one of `allowed`.
+fn get_divisor(cx: &ExtCtxt, node: &Rc<node::Node>, key: &str,
+    allowed: &[uint]) -> Option<uint> {
+  if node.attributes.borrow().find(&key.to_string()).is_none() {
+    return Some(1);
+  }
+  let divisor = match node.get_required_int_attr(cx, key) {
+    Some(divisor) => divisor,
+    None => return None,
+  };
+  if !allowed.contains(&divisor) {
+    let allowed_str: Vec<String> =
+        allowed.iter().map(|d| d.to_string()).collect();
+    cx.parse_sess().span_diagnostic.span_err(node.get_attr(key).value_span,
+        format!("invalid {} `{}`, allowed values: {}",
+            key, divisor, allowed_str.connect(", ")).as_slice());
+    return None;
+  }
+  Some(divisor)
+}
+
+fn build_clock(builder: &mut Builder, cx: &mut ExtCtxt,
+    node: Rc<node::Node>) {
+  if !node.expect_attributes(cx, [("source", node::StrAttribute)]) ||
+     !node.expect_subnodes(cx, ["pll"]) {
+    return;
+  }
+
+  let source = node.get_string_attr("source").unwrap();
+  let (source_freq, system_source, pll_source) = match source.as_slice() {
+    "hsi" => (HSI_FREQUENCY,
+        "init::SystemClockHSI".to_string(),
+        "init::PLLClockHSI".to_string()),
+    "hse" => {
+      let freq = match node.get_required_frequency_attr(cx,
+          "source_frequency") {
+        Some(freq) => freq,
+        None => return,
+      };
+      if !check_range(cx, &node, "source_frequency", freq,
+          4_000_000, 26_000_000) {
+        return;
+      }
+      (freq,
+          format!("init::SystemClockHSE({})", freq),
+          format!("init::PLLClockHSE({})", freq))
+    },
+    other => {
+      cx.span_err(
+          node.get_attr("source").value_span,
+          format!("unknown oscillator value `{}`, allowed values: \
+              `hsi`, `hse`", other).as_slice());
+      return;
+    },
+  };
+
+  let some_ahb = get_divisor(cx, &node, "ahb_divisor",
+      [1, 2, 4, 8, 16, 64, 128, 256, 512]);
+  let some_apb_low = get_divisor(cx, &node, "apb_low_divisor",
+      [1, 2, 4, 8, 16]);
+  let some_apb_high = get_divisor(cx, &node, "apb_high_divisor",
+      [1, 2, 4, 8, 16]);
+  if some_ahb.is_none() || some_apb_low.is_none() || some_apb_high.is_none() {
+    return;
+  }
+  let ahb_divisor = some_ahb.unwrap();
+  let apb_low_divisor = some_apb_low.unwrap();
+  let apb_high_divisor = some_apb_high.unwrap();
+
+  let (sysfreq, clock_source) = match node.get_by_path("pll") {
+    None => {
+      let system_source = TokenString(system_source);
+      (source_freq, quote_expr!(&*cx, $system_source))
+    },
+    Some(sub) => {
+      if !sub.expect_no_subnodes(cx) || !sub.expect_attributes(cx, [
+          ("m", node::IntAttribute),
+          ("n", node::IntAttribute),
+          ("p", node::IntAttribute),
+          ("q", node::IntAttribute)]) {
+        return;
+      }
+      let m = sub.get_int_attr("m").unwrap();
+      let n = sub.get_int_attr("n").unwrap();
+      let p = sub.get_int_attr("p").unwrap();
+      let q = sub.get_int_attr("q").unwrap();
+
+      if !check_range(cx, &sub, "m", m, 2, 63) ||
+         !check_range(cx, &sub, "n", n, 50, 432) ||
+         !check_range(cx, &sub, "q", q, 2, 15) {
+        return;
+      }
+      match p {
+        2 | 4 | 6 | 8 => (),
+        other => {
+          cx.parse_sess().span_diagnostic.span_err(
+              sub.get_attr("p").value_span,
+              format!("invalid p `{}`, allowed values: 2, 4, 6, 8",
+                  other).as_slice());
+          return;
+        },
+      }
+
+      let vco_in = source_freq / m;
+      if vco_in < 1_000_000 || vco_in > 2_000_000 {
+        cx.parse_sess().span_diagnostic.span_err(sub.get_attr("m").value_span,
+            format!("PLL input frequency {} Hz is out of the 1-2 MHz range",
+                vco_in).as_slice());
+        return;
+      }
+      let vco_out = vco_in * n;
+      if vco_out < 100_000_000 || vco_out > 432_000_000 {
+        cx.parse_sess().span_diagnostic.span_err(sub.get_attr("n").value_span,
+            format!("PLL VCO frequency {} Hz is out of the 100-432 MHz range",
+                vco_out).as_slice());
+        return;
+      }
+      if vco_out / q > 48_000_000 {
+        cx.parse_sess().span_diagnostic.span_err(sub.get_attr("q").value_span,
+            format!("PLL USB clock frequency {} Hz is over 48 MHz",
+                vco_out / q).as_slice());
+        return;
+      }
+
+      let pll_source = TokenString(pll_source);
+      let pll_m: u8 = m as u8;
+      let pll_n: u16 = n as u16;
+      let pll_p: u8 = p as u8;
+      let pll_q: u8 = q as u8;
+      (vco_out / p, quote_expr!(&*cx,
+          init::SystemClockPLL(init::PLLConf {
+            source: $pll_source,
+            m: $pll_m,
+            n: $pll_n,
+            p: $pll_p,
+            q: $pll_q,
+          })
+      ))
+    },
+  };
+
+  if sysfreq > MAX_SYSTEM_FREQUENCY {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        format!("system clock frequency {} Hz is over {} Hz",
+            sysfreq, MAX_SYSTEM_FREQUENCY).as_slice());
+    return;
+  }
+  let hclk = sysfreq / ahb_divisor;
+  if hclk / apb_low_divisor > MAX_APB_LOW_FREQUENCY {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        format!("low speed APB frequency {} Hz is over {} Hz, increase \
+            `apb_low_divisor`",
+            hclk / apb_low_divisor, MAX_APB_LOW_FREQUENCY).as_slice());
+    return;
+  }
+  if hclk / apb_high_divisor > MAX_APB_HIGH_FREQUENCY {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        format!("high speed APB frequency {} Hz is over {} Hz, increase \
+            `apb_high_divisor`",
+            hclk / apb_high_divisor, MAX_APB_HIGH_FREQUENCY).as_slice());
+    return;
+  }
+
+  node.attributes.borrow_mut().insert("system_frequency".to_string(),
+      Rc::new(node::Attribute::new_nosp(node::IntValue(sysfreq))));
+
+  let ahb: u16 = ahb_divisor as u16;
+  let apb_low: u8 = apb_low_divisor as u8;
+  let apb_high: u8 = apb_high_divisor as u8;
+  let ex = quote_expr!(&*cx,
+      {
+        use zinc::hal::stm32f4::init;
+        let conf = init::ClockConf {
+          source: $clock_source,
+          ahb_divisor: $ahb,
+          apb_low_divisor: $apb_low,
+          apb_high_divisor: $apb_high,
+        };
+        conf.setup();
+      }
+  );
+  builder.add_main_statement(cx.stmt_expr(ex));
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_hsi_clock_init() {
+    with_parsed("
+      clock {
+        source = \"hsi\";
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_clock(&mut builder, cx, pt.get_by_path("clock").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "{
+            use zinc::hal::stm32f4::init;
+            let conf = init::ClockConf {
+              source: init::SystemClockHSI,
+              ahb_divisor: 1u16,
+              apb_low_divisor: 1u8,
+              apb_high_divisor: 1u8,
+            };
+            conf.setup();
+          };");
+    });
+  }
+
+  #[test]
+  fn builds_pll_clock_init() {
+    with_parsed("
+      clock {
+        source = \"hsi\";
+        apb_low_divisor = 4;
+        apb_high_divisor = 2;
+        pll {
+          m = 16;
+          n = 336;
+          p = 2;
+          q = 7;
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_clock(&mut builder, cx, pt.get_by_path("clock").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "{
+            use zinc::hal::stm32f4::init;
+            let conf = init::ClockConf {
+              source: init::SystemClockPLL(init::PLLConf {
+                source: init::PLLClockHSI,
+                m: 16u8,
+                n: 336u16,
+                p: 2u8,
+                q: 7u8,
+              }),
+              ahb_divisor: 1u16,
+              apb_low_divisor: 4u8,
+              apb_high_divisor: 2u8,
+            };
+            conf.setup();
+          };");
+    });
+  }
+
+  #[test]
+  fn clock_provides_out_frequency() {
+    with_parsed("
+      clock {
+        source = \"hse\";
+        source_frequency = 8_000_000;
+        apb_low_divisor = 4;
+        apb_high_divisor = 2;
+        pll {
+          m = 8;
+          n = 336;
+          p = 2;
+          q = 7;
+        }
+      }", |cx, _, pt| {
+      let mut builder = Builder::new(pt.clone());
+      let node = pt.get_by_path("clock").unwrap();
+      super::build_clock(&mut builder, cx, node.clone());
+
+      let out_freq = node.get_int_attr("system_frequency");
+      assert!(out_freq.is_some());
+      assert!(out_freq.unwrap() == 168_000_000);
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_clock_conf() {
+    fails_to_build("stm32f4@mcu { clock {
+      no_source = 1;
+    }}");
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"missing\";
+    }}");
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hse\";
+    }}");
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hse\";
+      source_frequency = 40_000_000;
+    }}");
+  }
+
+  #[test]
+  fn fails_to_parse_bad_divisors() {
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hsi\";
+      ahb_divisor = 32;
+    }}");
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hsi\";
+      apb_low_divisor = 3;
+    }}");
+  }
+
+  #[test]
+  fn fails_to_parse_bad_pll_conf() {
+    // VCO input frequency is 4 MHz
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hse\";
+      source_frequency = 8_000_000;
+      pll { m = 2; n = 100; p = 2; q = 4; }
+    }}");
+    // p must be even
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hsi\";
+      pll { m = 16; n = 336; p = 3; q = 7; }
+    }}");
+    // system clock is 216 MHz
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hsi\";
+      apb_low_divisor = 8;
+      apb_high_divisor = 4;
+      pll { m = 16; n = 432; p = 2; q = 9; }
+    }}");
+  }
+
+  #[test]
+  fn fails_to_parse_fast_apb() {
+    fails_to_build("stm32f4@mcu { clock {
+      source = \"hsi\";
+      pll { m = 16; n = 336; p = 2; q = 7; }
+    }}");
+  }
+}
@@ -20,8 +20,6 @@
 use super::peripheral_clock;
 use hal::timer;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
-
 /// Available timer peripherals.
 #[allow(missing_doc)]
 pub enum TimerPeripheral {
@@ -37,14 +35,14 @@ impl Timer {
   /// Create and start a Timer.
   pub fn new(peripheral: TimerPeripheral, counter: u32) -> Timer {
     let (clock, reg) = match peripheral {
-      Timer2 => (peripheral_clock::TIM2Clock, &reg::TIM2),
+      Timer2 => (peripheral_clock::TIM2Clock, reg::TIM2()),
     };
 
     clock.enable();
 
-    reg.set_PSC(counter - 1);
-    reg.set_CR1(1);
-    reg.set_EGR(1);
+    reg.psc.write().set_psc(counter - 1);
+    reg.cr1.write().set_cen(true);
+    reg.egr.set_ug(true);
 
     Timer {
       reg: reg,
@@ -55,37 +53,82 @@ impl Timer {
 impl timer::Timer for Timer {
   #[inline(always)]
   fn get_counter(&self) -> u32 {
-    self.reg.CNT()
+    self.reg.cnt.cnt()
   }
 }
 
 mod reg {
   use util::volatile_cell::VolatileCell;
+  use core::ops::Drop;
 
-  ioreg_old!(TIM2To5: u32, CR1, CR2, SMCR, DIER, SR, EGR, CCMR1, CCMR2, CCER, CNT,
-                       PSC, ARR, _pad_0, CCR1, CCR2, CCR3, CCR4, _pad_1, DCR,
-                       DMAR, OR)
-  reg_rw!(TIM2To5, u32, CR1,   set_CR1,   CR1)
-  reg_rw!(TIM2To5, u32, CR2,   set_CR2,   CR2)
-  reg_rw!(TIM2To5, u32, SMCR,  set_SMCR,  SMCR)
-  reg_rw!(TIM2To5, u32, DIER,  set_DIER,  DIER)
-  reg_rw!(TIM2To5, u32, SR,    set_SR,    SR)
-  reg_w!( TIM2To5, u32,        set_EGR,   EGR)
-  reg_rw!(TIM2To5, u32, CCMR1, set_CCMR1, CCMR1)
-  reg_rw!(TIM2To5, u32, CCMR2, set_CCMR2, CCMR2)
-  reg_rw!(TIM2To5, u32, CCER,  set_CCER,  CCER)
-  reg_rw!(TIM2To5, u32, CNT,   set_CNT,   CNT)
-  reg_rw!(TIM2To5, u32, PSC,   set_PSC,   PSC)
-  reg_rw!(TIM2To5, u32, ARR,   set_ARR,   ARR)
-  reg_rw!(TIM2To5, u32, CCR1,  set_CCR1,  CCR1)
-  reg_rw!(TIM2To5, u32, CCR2,  set_CCR2,  CCR2)
-  reg_rw!(TIM2To5, u32, CCR3,  set_CCR3,  CCR3)
-  reg_rw!(TIM2To5, u32, CCR4,  set_CCR4,  CCR4)
-  reg_rw!(TIM2To5, u32, DCR,   set_DCR,   DCR)
-  reg_rw!(TIM2To5, u32, DMAR,  set_DMAR,  DMAR)
-  reg_rw!(TIM2To5, u32, OR,    set_OR,    OR)
-
-  extern {
-    #[link_name="stm32f4_iomem_TIM2"] pub static TIM2: TIM2To5;
-  }
+  ioregs!(TIM2To5 = {
+    0x00   => reg32 cr1 {         //! Control 1
+      0      => cen,              //= counter enable
+      1      => udis,             //= update disable
+      2      => urs,              //= update request source
+      3      => opm,              //= one-pulse mode
+      4      => dir,              //= count down
+      5..6   => cms,              //= center-aligned mode selection
+      7      => arpe,             //= auto-reload preload enable
+      8..9   => ckd,              //= clock division
+    }
+
+    0x04   => reg32 cr2 {         //! Control 2
+      0..31  => value,
+    }
+
+    0x08   => reg32 smcr {        //! Slave mode control
+      0..31  => value,
+    }
+
+    0x0c   => reg32 dier {        //! DMA and interrupt enable
+      0..31  => value,
+    }
+
+    0x10   => reg32 sr {          //! Status
+      0..31  => value,
+    }
+
+    0x14   => reg32 egr {         //! Event generation
+      0      => ug: wo,           //= update generation
+      1..4   => ccg[4]: wo,       //= capture and compare generation
+      6      => tg: wo,           //= trigger generation
+    }
+
+    0x18   => reg32 ccmr[2] {     //! Capture and compare mode
+      0..31  => value,
+    }
+
+    0x20   => reg32 ccer {        //! Capture and compare enable
+      0..31  => value,
+    }
+
+    0x24   => reg32 cnt {         //! Counter
+      0..31  => cnt,
+    }
+
+    0x28   => reg32 psc {         //! Prescaler
+      0..15  => psc,
+    }
+
+    0x2c   => reg32 arr {         //! Auto-reload
+      0..31  => arr,
+    }
+
+    0x34   => reg32 ccr[4] {      //! Capture and compare
+      0..31  => ccr,
+    }
+
+    0x48   => reg32 dcr {         //! DMA control
+      0..31  => value,
+    }
+
+    0x4c   => reg32 dmar {        //! DMA address for full transfer
+      0..31  => value,
+    }
+
+    0x50   => reg32 or {          //! Option
+      0..31  => value,
+    }
+  } TIM2 @ 0x4000_0000)
 }
@@ -0,0 +1,118 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
+use node;
+
+pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.materializer.set(Some(verify));
+  for timer_node in node.subnodes().iter() {
+    timer_node.materializer.set(Some(build_timer));
+    add_node_dependency(&node, timer_node);
+    super::add_node_dependency_on_clock(builder, timer_node);
+  }
+}
+
+pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  node.expect_no_attributes(cx);
+}
+
+fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
+  if !node.expect_no_subnodes(cx) ||
+     !node.expect_attributes(cx, [("counter", node::IntAttribute)]) {
+    return
+  }
+
+  if node.name.is_none() {
+    cx.parse_sess().span_diagnostic.span_err(node.name_span,
+        "timer node must have a name");
+    return
+  }
+
+  let name = TokenString(node.name.clone().unwrap());
+  let timer_index: Option<uint> = from_str(node.path.as_slice());
+  let timer_name = match timer_index {
+    Some(2) => TokenString("zinc::hal::stm32f4::timer::Timer2".to_string()),
+    _ => {
+      cx.parse_sess().span_diagnostic.span_err(node.path_span,
+          format!("unknown timer index `{}`, allowed indexes: 2",
+              node.path).as_slice());
+      return
+    }
+  };
+  if !builder.claim(cx, Peripheral(format!("TIM{}", node.path)),
+      node.path_span) {
+    return
+  }
+
+  // the counter is written to the 16-bit prescaler as counter - 1
+  let counter = node.get_int_attr("counter").unwrap();
+  if counter < 1 || counter > 65536 {
+    cx.parse_sess().span_diagnostic.span_err(
+        node.get_attr("counter").value_span,
+        format!("invalid counter `{}`, allowed values: 1...65536",
+            counter).as_slice());
+    return
+  }
+  let counter: u32 = counter as u32;
+
+  node.set_type_name("zinc::hal::stm32f4::timer::Timer".to_string());
+
+  let st = quote_stmt!(&*cx,
+      let $name = zinc::hal::stm32f4::timer::Timer::new(
+          $timer_name, $counter);
+  );
+  builder.add_main_statement(st);
+}
+
+#[cfg(test)]
+mod test {
+  use builder::Builder;
+  use test_helpers::{assert_equal_source, with_parsed, fails_to_build};
+
+  #[test]
+  fn builds_timer() {
+    with_parsed("
+      timer {
+        tim@2 {
+          counter = 25;
+        }
+      }", |cx, failed, pt| {
+      let mut builder = Builder::new(pt.clone());
+      super::build_timer(&mut builder, cx, pt.get_by_name("tim").unwrap());
+      assert!(unsafe{*failed} == false);
+      assert!(builder.main_stmts().len() == 1);
+
+      assert_equal_source(builder.main_stmts()[0].deref(),
+          "let tim = zinc::hal::stm32f4::timer::Timer::new(
+              zinc::hal::stm32f4::timer::Timer2, 25u32);");
+    });
+  }
+
+  #[test]
+  fn fails_to_parse_bad_timer() {
+    fails_to_build("stm32f4@mcu {
+      clock { source = \"hsi\"; }
+      timer { tim@3 { counter = 25; } }
+    }");
+    fails_to_build("stm32f4@mcu {
+      clock { source = \"hsi\"; }
+      timer { tim@2 { counter = 0; } }
+    }");
+  }
+}
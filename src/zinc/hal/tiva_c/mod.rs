@@ -6,5 +6,3 @@ pub mod sysctl;
 pub mod pin;
 pub mod timer;
 pub mod uart;
-
-#[path="../../util/ioreg.rs"] mod util;
@@ -1,9 +1,22 @@
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 
-use builder::{Builder, TokenString, add_node_dependency};
+use builder::{Builder, TokenString, PinFunction, add_node_dependency};
 use node;
 
+/// UART signals by port, pin and function.
+static UART_PINS: &'static [(&'static str, &'static str, uint, u8)] = &[
+  ("U0RX", "PortA", 0, 1), ("U0TX", "PortA", 1, 1),
+  ("U1RX", "PortB", 0, 1), ("U1TX", "PortB", 1, 1),
+  ("U1RX", "PortC", 4, 2), ("U1TX", "PortC", 5, 2),
+  ("U2RX", "PortD", 6, 1), ("U2TX", "PortD", 7, 1),
+  ("U3RX", "PortC", 6, 1), ("U3TX", "PortC", 7, 1),
+  ("U4RX", "PortC", 4, 1), ("U4TX", "PortC", 5, 1),
+  ("U5RX", "PortE", 4, 1), ("U5TX", "PortE", 5, 1),
+  ("U6RX", "PortD", 4, 1), ("U6TX", "PortD", 5, 1),
+  ("U7RX", "PortE", 0, 1), ("U7TX", "PortE", 1, 1),
+];
+
 pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(verify));
   for port_node in node.subnodes().iter() {
@@ -26,6 +39,23 @@ fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   let ref port_path = port_node.path;
   let port = TokenString(port_path.clone());
 
+  let function = match node.get_int_attr("function") {
+    None       => 0, /* Default to GPIO function */
+    Some(f)    => f as u8,
+  };
+
+  // only one pin can be routed to a UART signal
+  let pin_idx = from_str::<uint>(node.path.as_slice());
+  match UART_PINS.iter().find(|&&(_, port, pin, f)|
+      port == port_path.as_slice() && Some(pin) == pin_idx && f == function) {
+    Some(&(signal, _, _, _)) => {
+      if !builder.claim(cx, PinFunction(signal.to_string()), node.path_span) {
+        return;
+      }
+    },
+    None => (),
+  }
+
   let error = | err: &str | {
     cx.parse_sess().span_diagnostic.span_err(port_node.path_span, err);
   };
@@ -49,11 +79,6 @@ fn build_pin(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
 
   let direction = TokenString(direction_str.to_string());
 
-  let function = match node.get_int_attr("function") {
-    None       => 0, /* Default to GPIO function */
-    Some(f)    => f as u8,
-  };
-
   let pin_str = match from_str::<uint>(node.path.as_slice()).unwrap() {
     0 ...7  => &node.path,
     other  => {
@@ -16,7 +16,7 @@
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 
-use builder::{Builder, TokenString, add_node_dependency};
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
 use node;
 
 pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
@@ -89,6 +89,11 @@ fn build_timer(builder: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
                 return;
               }}));
 
+  let peripheral = format!("{}{}", if wide_timer {"WTIMER"} else {"TIMER"}, id);
+  if !builder.claim(cx, Peripheral(peripheral), node.path_span) {
+    return;
+  }
+
   let timer_name = TokenString(format!("zinc::hal::tiva_c::timer::{}{}",
                                        if wide_timer {
                                          "TimerW"
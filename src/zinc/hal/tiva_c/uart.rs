@@ -22,7 +22,6 @@ use hal::tiva_c::io;
 use drivers::chario::CharIO;
 use hal::uart;
 
-#[path="../../util/ioreg.rs"] mod ioreg;
 #[path="../../util/wait_for.rs"] mod wait_for;
 
 /// There are 8 UART instances in total
@@ -51,7 +50,24 @@ impl Uart {
              word_len:  u8,
              parity:    uart::Parity,
              stop_bits: u8) -> Uart {
+    let sysclk = 16_000_000u;
+
+    /* compute the baud rate divisor rounded to the nearest */
+    let brd = ((((sysclk / 16) << 6) + baudrate / 2) / baudrate) as u32;
+
+    Uart::new_with_divisors(id, (brd >> 6) as u16, (brd & ((1 << 6) - 1)) as u8,
+                            word_len, parity, stop_bits)
+  }
 
+  /// Create and setup a UART with precomputed baud rate divisors.
+  ///
+  /// The baud rate is `sysclk / (16 * (ibrd + fbrd / 64))`.
+  pub fn new_with_divisors(id:        UARTID,
+                           ibrd:      u16,
+                           fbrd:      u8,
+                           word_len:  u8,
+                           parity:    uart::Parity,
+                           stop_bits: u8) -> Uart {
     let (periph, regs) = match id {
       UART0 => (sysctl::periph::uart::UART_0, reg::UART_0),
       UART1 => (sysctl::periph::uart::UART_1, reg::UART_1),
@@ -67,22 +83,18 @@ impl Uart {
 
     periph.ensure_enabled();
 
-    uart.configure(baudrate, word_len, parity, stop_bits);
+    let brd = ((ibrd as u32) << 6) | fbrd as u32;
+    uart.configure(brd, word_len, parity, stop_bits);
 
     uart
   }
 
-  /// Configure the UART
+  /// Configure the UART, `brd` is the baud rate divisor in 1/64 units
   fn configure(&self,
-               baudrate:  uint,
+               brd:       u32,
                word_len:  u8,
                parity:    uart::Parity,
                stop_bits: u8) {
-    let sysclk = 16_000_000u;
-
-    /* compute the baud rate divisor rounded to the nearest */
-    let brd = ((((sysclk / 16) << 6) + baudrate / 2) / baudrate) as u32;
-
     self.regs.ctl
       /* Disable the UART before configuration */
       .set_uarten(false)
@@ -16,9 +16,12 @@
 use std::rc::Rc;
 use syntax::ext::base::ExtCtxt;
 
-use builder::{Builder, TokenString, add_node_dependency};
+use builder::{Builder, TokenString, Peripheral, add_node_dependency};
 use node;
 
+/// System clock the UARTs run from, the HAL doesn't configure the PLL.
+static SYSTEM_FREQUENCY: uint = 16_000_000;
+
 pub fn attach(builder: &mut Builder, _: &mut ExtCtxt, node: Rc<node::Node>) {
   node.materializer.set(Some(verify));
 
@@ -34,6 +37,22 @@ pub fn verify(_: &mut Builder, cx: &mut ExtCtxt, node: Rc<node::Node>) {
   node.expect_no_attributes(cx);
 }
 
+/// Computes the integer and fractional baud rate divisors, as
+/// `(ibrd, fbrd, actual baud rate)`.
+fn calculate_divisors(sysclk: uint, baud_rate: uint)
+    -> Option<(uint, uint, uint)> {
+  if baud_rate == 0 {
+    return None;
+  }
+  // the divisor in 1/64 units, rounded to the nearest
+  let brd = (4 * sysclk + baud_rate / 2) / baud_rate;
+  let ibrd = brd >> 6;
+  if ibrd < 1 || ibrd > 0xffff {
+    return None;
+  }
+  Some((ibrd, brd & 0x3f, (4 * sysclk + brd / 2) / brd))
+}
+
 pub fn build_uart(builder: &mut Builder,
                   cx: &mut ExtCtxt,
                   sub: Rc<node::Node>) {
@@ -50,6 +69,10 @@ pub fn build_uart(builder: &mut Builder,
           return;
         }
       });
+  if !builder.claim(cx, Peripheral(uart_peripheral_str.clone()),
+      sub.path_span) {
+    return
+  }
   let uart_peripheral = TokenString(uart_peripheral_str);
 
   if sub.name.is_none() {
@@ -100,13 +123,28 @@ pub fn build_uart(builder: &mut Builder,
     s  => from_str::<u8>(s).unwrap(),
   };
 
+  let (ibrd, fbrd) = match calculate_divisors(SYSTEM_FREQUENCY, baud_rate) {
+    Some((ibrd, fbrd, actual)) => {
+      if !::uart_pt::check_baud_rate(cx, &sub, "mode", baud_rate, actual) {
+        return;
+      }
+      (ibrd as u16, fbrd as u8)
+    },
+    None => {
+      error(format!("baud rate {} can't be derived from the {} Hz system \
+                     clock", baud_rate, SYSTEM_FREQUENCY).as_slice());
+      return;
+    },
+  };
+
   sub.set_type_name("zinc::hal::tiva_c::uart::Uart".to_string());
   let uart_name = TokenString(sub.name.clone().unwrap());
 
   let st = quote_stmt!(&*cx,
-      let $uart_name = zinc::hal::tiva_c::uart::Uart::new(
+      let $uart_name = zinc::hal::tiva_c::uart::Uart::new_with_divisors(
           zinc::hal::tiva_c::uart::$uart_peripheral,
-          $baud_rate,
+          $ibrd,
+          $fbrd,
           $word_len,
           zinc::hal::uart::$parity,
           $stop_bits)
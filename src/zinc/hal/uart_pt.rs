@@ -0,0 +1,62 @@
+// Zinc, the bare metal stack for rust.
+// Copyright 2014 Vladimir "farcaller" Pouzanov <farcaller@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Baud rate checks shared by the UART nodes of all MCUs.
+
+use std::rc::Rc;
+use syntax::ext::base::ExtCtxt;
+
+use node;
+
+/// Baud rate tolerance used if the UART node has no `baud_tolerance`
+/// attribute, in tenths of a percent.
+static DEFAULT_BAUD_TOLERANCE: uint = 20;
+
+/// Checks that the baud rate achievable with the computed divisors deviates
+/// from the requested one by no more than the `baud_tolerance` of the UART
+/// node, given in tenths of a percent.
+///
+/// Reports an error on `attr`, the attribute holding the baud rate, and
+/// returns false otherwise.
+pub fn check_baud_rate(cx: &ExtCtxt, node: &Rc<node::Node>, attr: &str,
+    requested: uint, actual: uint) -> bool {
+  let has_tolerance =
+      node.attributes.borrow().contains_key(&"baud_tolerance".to_string());
+  let tolerance = if has_tolerance {
+    match node.get_required_int_attr(cx, "baud_tolerance") {
+      Some(tolerance) => tolerance,
+      None => return false,
+    }
+  } else {
+    DEFAULT_BAUD_TOLERANCE
+  };
+
+  let deviation = if actual > requested {
+    actual - requested
+  } else {
+    requested - actual
+  };
+  // in hundredths of a percent
+  let error = deviation * 10_000 / requested;
+  if error > tolerance * 10 {
+    cx.parse_sess().span_diagnostic.span_err(node.get_attr(attr).value_span,
+        format!("baud rate {} can't be matched within {}.{}%, the closest \
+            achievable one is {} ({}.{:02}% off)",
+            requested, tolerance / 10, tolerance % 10,
+            actual, error / 100, error % 100).as_slice());
+    return false;
+  }
+  true
+}
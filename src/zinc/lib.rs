@@ -56,6 +56,13 @@ pub mod hal;
 pub mod util;
 pub mod os;
 
+/// Gives code generated by `ioregs!` in this crate the same paths to
+/// zinc items as in applications.
+mod zinc {
+  pub use super::hal;
+  pub use super::util;
+}
+
 /// Export a subset of `core` as `std.
 ///
 /// This is exported as a temporary convenience as `rustc` still looks
@@ -128,6 +128,16 @@ impl TasksCollection {
 /// t should point to initial task.
 #[inline(never)]
 pub fn setup(t: Task, stack_size: u32) {
+  setup_tasks(&[(t, 0, stack_size)]);
+}
+
+/// Initialize task manager with several tasks and start the first one.
+///
+/// Each task is given as a `(task, arg, stack_size)` tuple. Like `setup()`,
+/// this function keeps main stack intact, so `arg` may point to data on it.
+/// It starts the task scheduler and never returns.
+#[inline(never)]
+pub fn setup_tasks(tasks: &[(Task, u32, u32)]) {
   systick::setup(::hal::cortex_m3::systick::CALIBRATED, true);
 
   let current_stack = sched::get_current_stack_pointer();
@@ -136,7 +146,11 @@ pub fn setup(t: Task, stack_size: u32) {
   let task_stack_base: u32 = (current_stack as u32 - ReservedPivilegedStackSize) & !3;
   current_stack_offset::set(task_stack_base);
 
-  let td = define_task(t, 0, stack_size, true);
+  let (t, arg, stack_size) = tasks[0];
+  let td = define_task(t, arg, stack_size, true);
+  for &(t, arg, stack_size) in tasks.iter().skip(1) {
+    define_task(t, arg, stack_size, false);
+  }
 
   td.load();
 
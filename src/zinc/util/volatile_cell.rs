@@ -14,10 +14,16 @@
 // limitations under the License.
 
 //! A cell that with volatile setter and getter.
+//!
+//! When built for tests, accesses can be redirected to an in-memory
+//! register file by installing a `mock::MockRegisters`, allowing drivers
+//! to be exercised on the host.
 
 use core::kinds::marker;
 use core::intrinsics::{volatile_load, volatile_store};
 
+#[cfg(test)] use self::mock::MockValue;
+
 /// This structure is used to represent a hardware register.
 /// It is mostly used by the ioreg family of macros.
 pub struct VolatileCell<T> {
@@ -33,7 +39,10 @@ impl<T> VolatileCell<T> {
       invariant: marker::InvariantType::<T>,
     }
   }
+}
 
+#[cfg(not(test))]
+impl<T> VolatileCell<T> {
   /// Get register value.
   #[inline]
   pub fn get(&self) -> T {
@@ -50,3 +59,206 @@ impl<T> VolatileCell<T> {
     }
   }
 }
+
+#[cfg(test)]
+impl<T: MockValue> VolatileCell<T> {
+  /// Get register value.
+  pub fn get(&self) -> T {
+    match mock::read(&self.value as *const T as uint) {
+      Some(v) => MockValue::from_u64(v),
+      None => unsafe { volatile_load(&self.value) },
+    }
+  }
+
+  /// Set register value.
+  pub fn set(&self, value: T) {
+    if !mock::write(&self.value as *const T as uint, value.to_u64()) {
+      unsafe {
+        volatile_store(&self.value as *const T as *mut T, value)
+      }
+    }
+  }
+}
+
+/// A recording register backend for host tests.
+///
+/// While a `MockRegisters` is alive, every `VolatileCell` access made by
+/// the current task goes to an in-memory register file instead of
+/// memory, so register blocks can be placed at their real hardware
+/// addresses. Accesses are logged with their offset from the base
+/// address given on creation.
+///
+/// ```ignore
+/// let regs = MockRegisters::new(0x4000_C000);
+/// regs.script_read(0x14, 0x20);
+/// uart.putc('a');
+/// assert_eq!(regs.log(), vec!(Read(0x14, 0x20), Write(0x0, 0x61)));
+/// ```
+#[cfg(test)]
+pub mod mock {
+  use std::cell::RefCell;
+  use std::collections::{HashMap, RingBuf, Deque};
+  use std::num::CheckedSub;
+
+  /// A register access
+  #[deriving(PartialEq, Eq, Show, Clone)]
+  pub enum Access {
+    /// A read of the given offset returning the given value
+    Read(uint, u64),
+    /// A write of the given value to the given offset
+    Write(uint, u64),
+  }
+
+  /// Values which can be stored in a mocked register
+  pub trait MockValue: Copy {
+    /// Convert to the register file's representation
+    fn to_u64(self) -> u64;
+    /// Convert from the register file's representation
+    fn from_u64(v: u64) -> Self;
+  }
+
+  impl MockValue for u64 {
+    fn to_u64(self) -> u64 { self }
+    fn from_u64(v: u64) -> u64 { v }
+  }
+
+  impl MockValue for u32 {
+    fn to_u64(self) -> u64 { self as u64 }
+    fn from_u64(v: u64) -> u32 { v as u32 }
+  }
+
+  impl MockValue for u16 {
+    fn to_u64(self) -> u64 { self as u64 }
+    fn from_u64(v: u64) -> u16 { v as u16 }
+  }
+
+  impl MockValue for u8 {
+    fn to_u64(self) -> u64 { self as u64 }
+    fn from_u64(v: u64) -> u8 { v as u8 }
+  }
+
+  struct State {
+    base: uint,
+    values: HashMap<uint, u64>,
+    scripted: HashMap<uint, RingBuf<u64>>,
+    clear_on_read: HashMap<uint, u64>,
+    log: Vec<Access>,
+  }
+
+  local_data_key!(MOCK: RefCell<State>)
+
+  /// Handle to the register file installed for the current task. The
+  /// register file is removed when the handle is dropped.
+  pub struct MockRegisters;
+
+  impl MockRegisters {
+    /// Install an empty register file for the current task. All
+    /// registers read as zero until written or scripted.
+    pub fn new(base: uint) -> MockRegisters {
+      MOCK.replace(Some(RefCell::new(State {
+        base: base,
+        values: HashMap::new(),
+        scripted: HashMap::new(),
+        clear_on_read: HashMap::new(),
+        log: Vec::new(),
+      })));
+      MockRegisters
+    }
+
+    /// Queue a value to be returned by a future read of the given
+    /// offset. Queued values are returned in order before falling back
+    /// to the register's stored value.
+    pub fn script_read(&self, offset: uint, value: u64) {
+      with_state(|s| {
+        let addr = s.base + offset;
+        s.scripted.find_or_insert_with(addr, |_| RingBuf::new())
+          .push_back(value);
+      });
+    }
+
+    /// Emulate read-to-clear flags: reads of the given offset clear the
+    /// bits of `mask` in the register's stored value
+    pub fn clear_on_read(&self, offset: uint, mask: u64) {
+      with_state(|s| { s.clear_on_read.insert(s.base + offset, mask); });
+    }
+
+    /// Set the stored value of a register without logging an access
+    pub fn set(&self, offset: uint, value: u64) {
+      with_state(|s| { s.values.insert(s.base + offset, value); });
+    }
+
+    /// The stored value of a register, without logging an access
+    pub fn get(&self, offset: uint) -> u64 {
+      with_state(|s| *s.values.find(&(s.base + offset)).unwrap_or(&0))
+    }
+
+    /// The accesses performed so far, in order
+    pub fn log(&self) -> Vec<Access> {
+      with_state(|s| s.log.clone())
+    }
+
+    /// Forget the accesses performed so far
+    pub fn clear_log(&self) {
+      with_state(|s| s.log.clear());
+    }
+  }
+
+  impl Drop for MockRegisters {
+    fn drop(&mut self) {
+      MOCK.replace(None);
+    }
+  }
+
+  fn with_state<R>(f: |&mut State| -> R) -> R {
+    match MOCK.get() {
+      Some(state) => f(&mut *state.borrow_mut()),
+      None => fail!("no mock registers installed"),
+    }
+  }
+
+  /// The offset of an accessed address from the base of the register file
+  fn offset_of(s: &State, addr: uint) -> uint {
+    match addr.checked_sub(&s.base) {
+      Some(offset) => offset,
+      None => fail!("register access at 0x{:x} below the mock base 0x{:x}",
+                    addr, s.base),
+    }
+  }
+
+  /// Read from the register file if one is installed
+  pub fn read(addr: uint) -> Option<u64> {
+    MOCK.get().map(|state| {
+      let mut s = state.borrow_mut();
+      let scripted = match s.scripted.find_mut(&addr) {
+        Some(q) => q.pop_front(),
+        None => None,
+      };
+      let value = match scripted {
+        Some(v) => v,
+        None => *s.values.find(&addr).unwrap_or(&0),
+      };
+      match s.clear_on_read.find(&addr).map(|mask| *mask) {
+        Some(mask) => { s.values.insert(addr, value & !mask); },
+        None => (),
+      }
+      let offset = offset_of(&*s, addr);
+      s.log.push(Read(offset, value));
+      value
+    })
+  }
+
+  /// Write to the register file if one is installed, returning whether
+  /// the write was handled
+  pub fn write(addr: uint, value: u64) -> bool {
+    match MOCK.get() {
+      Some(state) => {
+        let mut s = state.borrow_mut();
+        let offset = offset_of(&*s, addr);
+        s.values.insert(addr, value);
+        s.log.push(Write(offset, value));
+        true
+      },
+      None => false,
+    }
+  }
+}
@@ -27,17 +27,21 @@ class Architecture
   # Target triple
   attr_reader :target
 
+  # Architecture features, passed to rustc as cfg_<feature>
+  attr_reader :features
+
   def self.from_yaml(filename)
     y = YAML.load_file(filename)
     y.merge(y) do |k, v|
-      Architecture.new(k, v['arch'], v['cpu'], v['target'])
+      Architecture.new(k, v['arch'], v['cpu'], v['target'], v['features'])
     end
   end
 
-  def initialize(name, arch, cpu, target)
+  def initialize(name, arch, cpu, target, features)
     @name = name
     @arch = arch
     @cpu = cpu
     @target = target
+    @features = features || []
   end
 end
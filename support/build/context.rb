@@ -91,10 +91,12 @@ class Context
   end
 
   def collect_config_flags!
-    @config_flags = @platform.features.map { |f| "cfg_#{f}" }
+    features = @platform.arch.features + @platform.features
+    @config_flags = features.map { |f| "cfg_#{f}" }
 
     @config_flags << "mcu_#{@platform.name}"
     @config_flags << "arch_#{@platform.arch.name}"
+    @config_flags << "cfg_debug" if ENV['DEBUG']
 
     @config_flags.map! do |c|
       "--cfg #{c}"